@@ -0,0 +1,189 @@
+use std::marker::PhantomData;
+
+use bevy::asset::{Asset, HandleId, LoadState};
+use bevy::prelude::*;
+
+use crate::{TaskId, TaskQueue};
+
+/// 把通过 [`AssetServer`] 加载的资源登记成 [`TaskQueue`] 中的任务
+///
+/// [`poll_asset_tasks`] 会根据 load state 更新任务的进度，全部加载完成时任务完成，有资源加载失败时任务失败。
+/// 缺少时有替代资源的可以通过 `track_optional` 之类的方法登记，加载失败时只输出警告，任务仍然算作完成。
+/// 只有 [`AssetServer::load`] 返回的 handle 有 load state，`Assets::add` 得到的 handle 不能登记
+///
+/// 每种任务类型的 [`TaskQueue`] 对应一个 `AssetTasks`
+#[derive(Resource)]
+pub struct AssetTasks<T> {
+    tracked: Vec<TrackedAssets>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for AssetTasks<T> {
+    fn default() -> Self {
+        Self {
+            tracked: vec![],
+            _marker: PhantomData,
+        }
+    }
+}
+
+struct TrackedAssets {
+    id: TaskId,
+    /// 用在错误信息中
+    name: String,
+    handles: Vec<HandleUntyped>,
+    /// 加载失败时只输出警告
+    optional: bool,
+}
+
+impl<T: Send + Sync + 'static> AssetTasks<T> {
+    pub fn track<A: Asset>(
+        &mut self,
+        task_queue: &mut TaskQueue<T>,
+        server: &AssetServer,
+        handle: &Handle<A>,
+        task: T,
+    ) -> TaskId {
+        let name = handle_path(server, handle.id());
+        self.push(task_queue, name, vec![handle.clone_untyped()], task, false)
+    }
+    /// 和 [`AssetTasks::track`] 一样，但是加载失败时任务也算作完成，用在缺少时有占位资源的地方
+    pub fn track_optional<A: Asset>(
+        &mut self,
+        task_queue: &mut TaskQueue<T>,
+        server: &AssetServer,
+        handle: &Handle<A>,
+        task: T,
+    ) -> TaskId {
+        let name = handle_path(server, handle.id());
+        self.push(task_queue, name, vec![handle.clone_untyped()], task, true)
+    }
+    /// 加载目录中所有可以加载的资源，返回的 handle 需要保存起来，否则资源加载完就会被释放
+    ///
+    /// 目录不存在时任务直接失败
+    pub fn track_folder(
+        &mut self,
+        task_queue: &mut TaskQueue<T>,
+        server: &AssetServer,
+        path: &str,
+        task: T,
+    ) -> (TaskId, Vec<HandleUntyped>) {
+        self.push_folder(task_queue, server, path, task, false)
+    }
+    /// 和 [`AssetTasks::track_folder`] 一样，但是目录不存在或者有资源加载失败时任务也算作完成
+    pub fn track_folder_optional(
+        &mut self,
+        task_queue: &mut TaskQueue<T>,
+        server: &AssetServer,
+        path: &str,
+        task: T,
+    ) -> (TaskId, Vec<HandleUntyped>) {
+        self.push_folder(task_queue, server, path, task, true)
+    }
+    /// 一组 handle 作为一个任务，weight 是 handle 的数量
+    pub fn track_handles(
+        &mut self,
+        task_queue: &mut TaskQueue<T>,
+        name: impl Into<String>,
+        handles: Vec<HandleUntyped>,
+        task: T,
+    ) -> TaskId {
+        self.push(task_queue, name, handles, task, false)
+    }
+    fn push_folder(
+        &mut self,
+        task_queue: &mut TaskQueue<T>,
+        server: &AssetServer,
+        path: &str,
+        task: T,
+        optional: bool,
+    ) -> (TaskId, Vec<HandleUntyped>) {
+        match server.load_folder(path) {
+            Ok(handles) => (
+                self.push(task_queue, path, handles.clone(), task, optional),
+                handles,
+            ),
+            Err(e) => {
+                let id = task_queue.push(task);
+                let error = format!("failed to load folder {path}: {e}");
+                if optional {
+                    warn!("{error}");
+                    task_queue.complete(id);
+                } else {
+                    task_queue.fail(id, error);
+                }
+                (id, vec![])
+            }
+        }
+    }
+    fn push(
+        &mut self,
+        task_queue: &mut TaskQueue<T>,
+        name: impl Into<String>,
+        handles: Vec<HandleUntyped>,
+        task: T,
+        optional: bool,
+    ) -> TaskId {
+        let id = task_queue.push_weighted(task, handles.len().max(1) as f32);
+        self.tracked.push(TrackedAssets {
+            id,
+            name: name.into(),
+            handles,
+            optional,
+        });
+        id
+    }
+    pub fn is_empty(&self) -> bool {
+        self.tracked.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.tracked.clear();
+    }
+}
+
+/// 检查所有登记的资源的 load state，结束的任务不再检查
+pub fn poll_asset_tasks<T: Send + Sync + 'static>(
+    server: Res<AssetServer>,
+    mut task_queue: ResMut<TaskQueue<T>>,
+    mut asset_tasks: ResMut<AssetTasks<T>>,
+) {
+    asset_tasks.tracked.retain(|tracked| {
+        // 任务已经被移除或者清空
+        if task_queue.get(tracked.id).is_none() {
+            return false;
+        }
+        let mut loaded = 0;
+        let mut failed = vec![];
+        for handle in tracked.handles.iter() {
+            match server.get_load_state(handle.id()) {
+                LoadState::Loaded => loaded += 1,
+                LoadState::Failed => failed.push(handle_path(&server, handle.id())),
+                _ => {}
+            }
+        }
+        // 可选的资源加载失败时，其他资源都结束之后任务才算完成
+        if !failed.is_empty() && !tracked.optional {
+            task_queue.fail(
+                tracked.id,
+                format!("failed to load {}: {}", tracked.name, failed.join(", ")),
+            );
+            return false;
+        }
+        if loaded + failed.len() == tracked.handles.len() {
+            if !failed.is_empty() {
+                warn!("failed to load {}: {}", tracked.name, failed.join(", "));
+            }
+            task_queue.complete(tracked.id);
+            return false;
+        }
+        task_queue.set_progress(tracked.id, loaded as f32 / tracked.handles.len() as f32);
+        true
+    });
+}
+
+fn handle_path(server: &AssetServer, id: HandleId) -> String {
+    match server.get_handle_path(id) {
+        Some(path) => path.path().display().to_string(),
+        None => format!("{id:?}"),
+    }
+}
@@ -0,0 +1,202 @@
+use std::any::Any;
+use std::fmt::Display;
+use std::marker::PhantomData;
+use std::panic::AssertUnwindSafe;
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+
+use crate::{TaskId, TaskQueue};
+
+/// 在 [`AsyncComputeTaskPool`] 中执行比较耗时的计算，比如转换关卡、裁剪 tileset，避免阻塞主线程
+///
+/// 每个计算都会登记成 [`TaskQueue`] 中的任务，[`poll_compute_tasks`] 在计算结束后更新任务的状态：
+/// - 返回 `Ok` 时任务完成，结果可以通过 [`ComputeTasks::take_result`] 取出
+/// - 返回 `Err` 或者 panic 时任务失败，失败的原因就是错误信息或者 panic 的信息
+/// - 超时或者被取消时任务失败；已经开始执行的计算没有办法中断，只是结果会被丢弃
+#[derive(Resource)]
+pub struct ComputeTasks<T, R> {
+    running: Vec<RunningTask<R>>,
+    results: Vec<(TaskId, R)>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+struct RunningTask<R> {
+    id: TaskId,
+    task: Task<Result<R, String>>,
+    deadline: Option<(Instant, Duration)>,
+}
+
+impl<T, R> Default for ComputeTasks<T, R> {
+    fn default() -> Self {
+        Self {
+            running: vec![],
+            results: vec![],
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static, R: Send + Sync + 'static> ComputeTasks<T, R> {
+    pub fn spawn<F, E>(&mut self, task_queue: &mut TaskQueue<T>, task: T, f: F) -> TaskId
+    where
+        F: FnOnce() -> Result<R, E> + Send + 'static,
+        E: Display,
+    {
+        self.spawn_task(task_queue, task, f, None)
+    }
+    /// 超过 `timeout` 还没有结束的任务会失败
+    pub fn spawn_with_timeout<F, E>(
+        &mut self,
+        task_queue: &mut TaskQueue<T>,
+        task: T,
+        timeout: Duration,
+        f: F,
+    ) -> TaskId
+    where
+        F: FnOnce() -> Result<R, E> + Send + 'static,
+        E: Display,
+    {
+        self.spawn_task(task_queue, task, f, Some((Instant::now(), timeout)))
+    }
+    fn spawn_task<F, E>(
+        &mut self,
+        task_queue: &mut TaskQueue<T>,
+        task: T,
+        f: F,
+        deadline: Option<(Instant, Duration)>,
+    ) -> TaskId
+    where
+        F: FnOnce() -> Result<R, E> + Send + 'static,
+        E: Display,
+    {
+        let id = task_queue.push(task);
+        let task = AsyncComputeTaskPool::get().spawn(async move {
+            match std::panic::catch_unwind(AssertUnwindSafe(f)) {
+                Ok(result) => result.map_err(|e| e.to_string()),
+                Err(panic) => Err(format!("panicked: {}", panic_message(panic.as_ref()))),
+            }
+        });
+        self.running.push(RunningTask { id, task, deadline });
+        id
+    }
+    /// 取消还在执行的计算，任务会失败
+    pub fn cancel(&mut self, task_queue: &mut TaskQueue<T>, id: TaskId) -> bool {
+        let Some(index) = self.running.iter().position(|running| running.id == id) else {
+            return false;
+        };
+        self.running.remove(index);
+        task_queue.fail(id, "cancelled");
+        true
+    }
+    /// 取出已经完成的计算的结果，每个结果只能取出一次
+    pub fn take_result(&mut self, id: TaskId) -> Option<R> {
+        let index = self
+            .results
+            .iter()
+            .position(|(result_id, _)| *result_id == id)?;
+        Some(self.results.remove(index).1)
+    }
+    /// 取出所有已经完成的计算的结果
+    pub fn drain_results(&mut self) -> impl Iterator<Item = (TaskId, R)> + '_ {
+        self.results.drain(..)
+    }
+    pub fn is_running(&self, id: TaskId) -> bool {
+        self.running.iter().any(|running| running.id == id)
+    }
+    /// 没有还在执行的计算，也没有没取出的结果
+    pub fn is_empty(&self) -> bool {
+        self.running.is_empty() && self.results.is_empty()
+    }
+    /// 取消所有计算并丢弃还没有取出的结果
+    pub fn clear(&mut self) {
+        self.running.clear();
+        self.results.clear();
+    }
+}
+
+/// 添加结果类型为 `R` 的 [`ComputeTasks`]，计算的任务放在类型为 `T` 的 [`TaskQueue`] 中
+///
+/// 计算绑定到 `state` 上，只在 `state` 中检查计算是否结束，离开 `state` 时还没有结束的计算和没有取出的结果都会被丢弃。
+/// 和 [`crate::TaskQueuePlugin`] 一起使用时，计算也会阻止跳转到下一个 state
+pub struct ComputeTaskPlugin<S: States, T, R> {
+    state: S,
+    _marker: PhantomData<fn() -> (T, R)>,
+}
+
+impl<S: States, T, R> ComputeTaskPlugin<S, T, R> {
+    pub fn new(state: S) -> Self {
+        Self {
+            state,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<S: States, T: Send + Sync + 'static, R: Send + Sync + 'static> Plugin
+    for ComputeTaskPlugin<S, T, R>
+{
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TaskQueue<T>>()
+            .init_resource::<ComputeTasks<T, R>>()
+            .add_system(poll_compute_tasks::<T, R>.in_set(OnUpdate(self.state.clone())))
+            .add_system(clear_compute_tasks::<T, R>.in_schedule(OnExit(self.state.clone())));
+    }
+}
+
+/// 检查所有计算是否已经结束，把结果保存到 [`ComputeTasks`] 中
+pub fn poll_compute_tasks<T: Send + Sync + 'static, R: Send + Sync + 'static>(
+    mut task_queue: ResMut<TaskQueue<T>>,
+    mut compute_tasks: ResMut<ComputeTasks<T, R>>,
+) {
+    let compute_tasks = &mut *compute_tasks;
+    let mut i = 0;
+    while i < compute_tasks.running.len() {
+        let running = &compute_tasks.running[i];
+        // 任务已经被移除或者清空时不再需要结果
+        if task_queue.get(running.id).is_none() {
+            compute_tasks.running.remove(i);
+            continue;
+        }
+        if running.task.is_finished() {
+            let running = compute_tasks.running.remove(i);
+            // 已经结束的 task 可以直接得到结果，不会阻塞
+            match futures_lite::future::block_on(running.task) {
+                Ok(result) => {
+                    task_queue.complete(running.id);
+                    compute_tasks.results.push((running.id, result));
+                }
+                Err(error) => {
+                    task_queue.fail(running.id, error);
+                }
+            }
+            continue;
+        }
+        if let Some((start, timeout)) = running.deadline {
+            if start.elapsed() >= timeout {
+                let running = compute_tasks.running.remove(i);
+                task_queue.fail(running.id, format!("timed out after {timeout:?}"));
+                continue;
+            }
+        }
+        i += 1;
+    }
+}
+
+/// 丢弃还在执行的计算，已经开始的计算会在后台执行完，但是结果不会再被使用
+fn clear_compute_tasks<T: Send + Sync + 'static, R: Send + Sync + 'static>(
+    mut compute_tasks: ResMut<ComputeTasks<T, R>>,
+) {
+    compute_tasks.clear();
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
@@ -1,46 +1,174 @@
 use bevy::prelude::*;
 
+pub use asset::{poll_asset_tasks, AssetTasks};
+pub use compute::{poll_compute_tasks, ComputeTaskPlugin, ComputeTasks};
+pub use state::TaskQueuePlugin;
+
+mod asset;
+mod compute;
+mod state;
 #[cfg(test)]
 mod test;
 
-#[derive(Eq, PartialEq, FromReflect, Reflect)]
-pub enum AsyncTask {
-    LoadingResource,
+/// 添加任务时返回的唯一标识，之后通过它更新任务的进度和状态
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, FromReflect, Reflect)]
+pub struct TaskId(u64);
+
+#[derive(Clone, PartialEq, Debug, FromReflect, Reflect)]
+pub enum TaskStatus {
+    Pending,
+    Completed,
+    /// 失败的原因
+    Failed(String),
+}
+
+/// 队列中的一个任务，`task` 是使用者自己定义的任务内容，比如用来在 loading 界面显示任务的名称
+#[derive(Debug)]
+pub struct TaskEntry<T> {
+    pub id: TaskId,
+    pub task: T,
+    /// 计算整体进度时所占的比重
+    pub weight: f32,
+    /// 0.0 到 1.0 之间，完成时是 1.0
+    pub progress: f32,
+    pub status: TaskStatus,
+}
+
+impl<T> TaskEntry<T> {
+    /// 已经完成或者失败，不会再有变化
+    pub fn is_finished(&self) -> bool {
+        self.status != TaskStatus::Pending
+    }
 }
 
-#[derive(Resource, Reflect)]
-pub struct TaskQueue {
-    async_task_queue: Vec<AsyncTask>,
+#[derive(Resource)]
+pub struct TaskQueue<T: Send + Sync + 'static> {
+    next_id: u64,
+    async_task_queue: Vec<TaskEntry<T>>,
 }
 
-impl TaskQueue {
+impl<T: Send + Sync + 'static> TaskQueue<T> {
     pub fn new() -> Self {
         Self {
+            next_id: 0,
             async_task_queue: vec![],
         }
     }
-    pub fn push(&mut self, task: AsyncTask) {
-        self.async_task_queue.push(task);
+    pub fn push(&mut self, task: T) -> TaskId {
+        self.push_weighted(task, 1.0)
+    }
+    /// 耗时更长的任务可以设置更大的 weight，让整体进度更接近实际情况
+    pub fn push_weighted(&mut self, task: T, weight: f32) -> TaskId {
+        let id = TaskId(self.next_id);
+        self.next_id += 1;
+        self.async_task_queue.push(TaskEntry {
+            id,
+            task,
+            weight: weight.max(0.0),
+            progress: 0.0,
+            status: TaskStatus::Pending,
+        });
+        id
+    }
+    pub fn get(&self, id: TaskId) -> Option<&TaskEntry<T>> {
+        self.async_task_queue.iter().find(|entry| entry.id == id)
+    }
+    fn get_mut(&mut self, id: TaskId) -> Option<&mut TaskEntry<T>> {
+        self.async_task_queue
+            .iter_mut()
+            .find(|entry| entry.id == id)
+    }
+    pub fn status(&self, id: TaskId) -> Option<&TaskStatus> {
+        self.get(id).map(|entry| &entry.status)
+    }
+    /// 更新还没有结束的任务的进度，任务不存在或者已经结束时返回 false
+    pub fn set_progress(&mut self, id: TaskId, progress: f32) -> bool {
+        match self.get_mut(id) {
+            Some(entry) if !entry.is_finished() => {
+                entry.progress = progress.clamp(0.0, 1.0);
+                true
+            }
+            _ => false,
+        }
+    }
+    pub fn complete(&mut self, id: TaskId) -> bool {
+        self.finish(id, TaskStatus::Completed)
     }
-    pub fn remove(&mut self, task: AsyncTask) -> bool {
-        if let Some(index) = self.async_task_queue.iter().position(|t| t == &task) {
-            self.async_task_queue.remove(index);
-            true
-        } else {
-            false
+    pub fn fail(&mut self, id: TaskId, error: impl Into<String>) -> bool {
+        self.finish(id, TaskStatus::Failed(error.into()))
+    }
+    fn finish(&mut self, id: TaskId, status: TaskStatus) -> bool {
+        match self.get_mut(id) {
+            Some(entry) if !entry.is_finished() => {
+                if status == TaskStatus::Completed {
+                    entry.progress = 1.0;
+                }
+                entry.status = status;
+                true
+            }
+            _ => false,
         }
     }
+    pub fn remove(&mut self, id: TaskId) -> Option<TaskEntry<T>> {
+        let index = self
+            .async_task_queue
+            .iter()
+            .position(|entry| entry.id == id)?;
+        Some(self.async_task_queue.remove(index))
+    }
     pub fn clear(&mut self) {
         self.async_task_queue.clear();
     }
-    /// 主要就是在只有异步任务的state不断检测该队列是否为空，为空就可以跳转到下一state了
     pub fn is_empty(&self) -> bool {
         self.async_task_queue.is_empty()
     }
     pub fn len(&self) -> usize {
         self.async_task_queue.len()
     }
-    pub fn contains(&self, task: AsyncTask) -> bool {
-        self.async_task_queue.contains(&task)
+    pub fn iter(&self) -> impl Iterator<Item = &TaskEntry<T>> {
+        self.async_task_queue.iter()
+    }
+    /// 还没有结束的任务数量
+    pub fn pending(&self) -> usize {
+        self.iter().filter(|entry| !entry.is_finished()).count()
+    }
+    /// 主要就是在只有异步任务的state不断检测所有任务是否都已经结束，结束了就可以跳转到下一state了，见 [`TaskQueuePlugin`]
+    pub fn is_finished(&self) -> bool {
+        self.iter().all(TaskEntry::is_finished)
+    }
+    /// 失败的任务和失败的原因
+    pub fn failures(&self) -> impl Iterator<Item = (&TaskEntry<T>, &str)> {
+        self.iter().filter_map(|entry| match &entry.status {
+            TaskStatus::Failed(error) => Some((entry, error.as_str())),
+            _ => None,
+        })
+    }
+    pub fn has_failed(&self) -> bool {
+        self.failures().next().is_some()
+    }
+    /// 按照 weight 计算的整体进度，失败的任务按照失败时的进度计算，没有任务时是 1.0
+    pub fn progress(&self) -> f32 {
+        let total = self.iter().map(|entry| entry.weight).sum::<f32>();
+        if total <= 0.0 {
+            return if self.is_finished() { 1.0 } else { 0.0 };
+        }
+        self.iter()
+            .map(|entry| entry.weight * entry.progress)
+            .sum::<f32>()
+            / total
+    }
+}
+
+impl<T: PartialEq + Send + Sync + 'static> TaskQueue<T> {
+    pub fn contains(&self, task: &T) -> bool {
+        self.async_task_queue
+            .iter()
+            .any(|entry| &entry.task == task)
+    }
+}
+
+impl<T: Send + Sync + 'static> Default for TaskQueue<T> {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -0,0 +1,99 @@
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+
+use crate::{poll_asset_tasks, AssetTasks, TaskQueue};
+
+/// 把类型为 `T` 的 [`TaskQueue`] 绑定到 `state` 上，队列中的任务都完成后自动跳转到 `next`
+///
+/// - 进入 `state` 之前或者 `OnEnter(state)` 中添加的任务都会被等待，没有任务时直接跳转
+/// - 有任务失败时停留在 `state`，并输出失败的原因
+/// - 离开 `state` 时清空队列
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_task_queue::TaskQueuePlugin;
+///
+/// #[derive(States, PartialEq, Eq, Debug, Clone, Hash, Default)]
+/// enum AppState {
+///     #[default]
+///     Loading,
+///     InGame,
+/// }
+///
+/// App::new()
+///     .add_state::<AppState>()
+///     .add_plugin(TaskQueuePlugin::<AppState, &'static str>::new(
+///         AppState::Loading,
+///         AppState::InGame,
+///     ));
+/// ```
+pub struct TaskQueuePlugin<S: States, T> {
+    state: S,
+    next: S,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<S: States, T> TaskQueuePlugin<S, T> {
+    pub fn new(state: S, next: S) -> Self {
+        Self {
+            state,
+            next,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// 当前 state 的队列完成后要跳转到的 state
+#[derive(Resource)]
+struct NextStateAfterTasks<S, T> {
+    next: S,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<S: States, T: Send + Sync + 'static> Plugin for TaskQueuePlugin<S, T> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TaskQueue<T>>()
+            .init_resource::<AssetTasks<T>>()
+            .insert_resource(NextStateAfterTasks::<S, T> {
+                next: self.next.clone(),
+                _marker: PhantomData,
+            })
+            .add_systems(
+                (poll_asset_tasks::<T>, advance_state::<S, T>)
+                    .chain()
+                    .in_set(OnUpdate(self.state.clone())),
+            )
+            .add_system(clear_tasks::<T>.in_schedule(OnExit(self.state.clone())));
+    }
+}
+
+/// 所有任务都完成后跳转到下一个 state，有任务失败时只在第一次发现时输出错误
+fn advance_state<S: States, T: Send + Sync + 'static>(
+    task_queue: Res<TaskQueue<T>>,
+    target: Res<NextStateAfterTasks<S, T>>,
+    mut next_state: ResMut<NextState<S>>,
+    mut reported: Local<bool>,
+) {
+    if !task_queue.is_finished() {
+        *reported = false;
+    } else if task_queue.has_failed() {
+        if !*reported {
+            for (entry, error) in task_queue.failures() {
+                error!("task {:?} failed: {error}", entry.id);
+            }
+            *reported = true;
+        }
+    } else {
+        debug!("all tasks are finished, auto change to {:?}", target.next);
+        next_state.set(target.next.clone());
+    }
+}
+
+fn clear_tasks<T: Send + Sync + 'static>(
+    mut task_queue: ResMut<TaskQueue<T>>,
+    mut asset_tasks: ResMut<AssetTasks<T>>,
+) {
+    task_queue.clear();
+    asset_tasks.clear();
+}
@@ -1,14 +1,321 @@
-use crate::{AsyncTask, TaskQueue};
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::utils::BoxedFuture;
+
+use crate::{AssetTasks, ComputeTaskPlugin, ComputeTasks, TaskQueue, TaskQueuePlugin, TaskStatus};
+
+#[derive(PartialEq, Debug)]
+enum Task {
+    Image,
+    Sound,
+}
+
+#[derive(States, PartialEq, Eq, Debug, Clone, Hash, Default)]
+enum AppState {
+    #[default]
+    Loading,
+    InGame,
+}
 
 #[test]
 fn check_task_queue() {
-    let mut task_queue = TaskQueue::new();
+    let mut task_queue = TaskQueue::<Task>::new();
     assert!(task_queue.is_empty());
-    task_queue.push(AsyncTask::LoadingResource);
-    task_queue.push(AsyncTask::LoadingResource);
+    let first = task_queue.push(Task::Image);
+    let second = task_queue.push(Task::Image);
+    assert_ne!(first, second);
     assert!(!task_queue.is_empty());
-    task_queue.remove(AsyncTask::LoadingResource);
+    assert!(task_queue.contains(&Task::Image));
+    assert!(!task_queue.contains(&Task::Sound));
+    // 相同的任务也可以通过 id 区分
+    assert!(task_queue.remove(second).is_some());
+    assert!(task_queue.remove(second).is_none());
     assert!(!task_queue.is_empty());
-    task_queue.remove(AsyncTask::LoadingResource);
+    assert!(task_queue.remove(first).is_some());
     assert!(task_queue.is_empty());
 }
+
+#[test]
+fn check_progress() {
+    let mut task_queue = TaskQueue::<Task>::new();
+    assert_eq!(task_queue.progress(), 1.0);
+    let light = task_queue.push(Task::Image);
+    let heavy = task_queue.push_weighted(Task::Sound, 3.0);
+    assert_eq!(task_queue.progress(), 0.0);
+    assert!(task_queue.set_progress(heavy, 0.5));
+    assert_eq!(task_queue.progress(), 0.375);
+    assert!(task_queue.complete(light));
+    assert_eq!(task_queue.progress(), 0.625);
+    assert_eq!(task_queue.pending(), 1);
+    assert!(!task_queue.is_finished());
+    // 结束的任务不能再修改
+    assert!(!task_queue.set_progress(light, 0.0));
+    assert!(!task_queue.fail(light, "too late"));
+
+    assert!(task_queue.fail(heavy, "missing art/floor.png"));
+    assert!(task_queue.is_finished());
+    assert!(task_queue.has_failed());
+    assert_eq!(
+        task_queue.status(heavy),
+        Some(&TaskStatus::Failed(String::from("missing art/floor.png")))
+    );
+    let failures = task_queue.failures().collect::<Vec<_>>();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].0.id, heavy);
+    assert_eq!(task_queue.progress(), 0.625);
+}
+
+#[derive(bevy::reflect::TypeUuid)]
+#[uuid = "5f2d6bd4-3d0f-4c35-9a43-0f1c36d7d0f1"]
+struct Bytes(#[allow(dead_code)] Vec<u8>);
+
+#[derive(Default)]
+struct BytesLoader;
+
+impl AssetLoader for BytesLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            load_context.set_default_asset(LoadedAsset::new(Bytes(bytes.to_vec())));
+            Ok(())
+        })
+    }
+    fn extensions(&self) -> &[&str] {
+        &["ldtk", "ttf"]
+    }
+}
+
+fn asset_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(AssetPlugin {
+            asset_folder: String::from("../../assets"),
+            ..default()
+        })
+        .add_state::<AppState>()
+        .add_plugin(TaskQueuePlugin::<AppState, Task>::new(
+            AppState::Loading,
+            AppState::InGame,
+        ))
+        .add_asset::<Bytes>()
+        .init_asset_loader::<BytesLoader>();
+    app
+}
+
+/// 资源在其他线程加载，多运行几帧直到所有任务结束
+fn update_until_finished(app: &mut App) {
+    for _ in 0..1000 {
+        app.update();
+        if app.world.resource::<TaskQueue<Task>>().is_finished() {
+            return;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    panic!("asset tasks are not finished");
+}
+
+#[test]
+fn check_asset_tasks() {
+    let mut app = asset_app();
+    let server = app.world.resource::<AssetServer>().clone();
+    let level: Handle<Bytes> = server.load("level.ldtk");
+    let missing: Handle<Bytes> = server.load("missing.ldtk");
+    let (level_task, missing_task, folder_task, fonts) =
+        app.world
+            .resource_scope(|world, mut task_queue: Mut<TaskQueue<Task>>| {
+                let mut asset_tasks = world.resource_mut::<AssetTasks<Task>>();
+                let level_task = asset_tasks.track(&mut task_queue, &server, &level, Task::Image);
+                let missing_task =
+                    asset_tasks.track(&mut task_queue, &server, &missing, Task::Image);
+                let (folder_task, fonts) =
+                    asset_tasks.track_folder(&mut task_queue, &server, "fonts", Task::Image);
+                (level_task, missing_task, folder_task, fonts)
+            });
+    assert_eq!(fonts.len(), 1);
+    update_until_finished(&mut app);
+
+    let task_queue = app.world.resource::<TaskQueue<Task>>();
+    assert_eq!(task_queue.status(level_task), Some(&TaskStatus::Completed));
+    assert_eq!(task_queue.status(folder_task), Some(&TaskStatus::Completed));
+    match task_queue.status(missing_task) {
+        Some(TaskStatus::Failed(error)) => assert!(error.contains("missing.ldtk")),
+        status => panic!("unexpected status {status:?}"),
+    }
+    assert!(app.world.resource::<AssetTasks<Task>>().is_empty());
+    // 有任务失败时不会跳转
+    app.update();
+    assert_eq!(app.world.resource::<State<AppState>>().0, AppState::Loading);
+}
+
+#[test]
+fn check_missing_folder() {
+    let mut app = asset_app();
+    let server = app.world.resource::<AssetServer>().clone();
+    app.world
+        .resource_scope(|world, mut task_queue: Mut<TaskQueue<Task>>| {
+            let mut asset_tasks = world.resource_mut::<AssetTasks<Task>>();
+            let (id, handles) =
+                asset_tasks.track_folder(&mut task_queue, &server, "missing", Task::Sound);
+            assert!(handles.is_empty());
+            assert!(matches!(task_queue.status(id), Some(TaskStatus::Failed(_))));
+        });
+}
+
+#[test]
+fn check_optional_assets() {
+    let mut app = asset_app();
+    let server = app.world.resource::<AssetServer>().clone();
+    let missing: Handle<Bytes> = server.load("missing.ldtk");
+    let (missing_task, folder_task) =
+        app.world
+            .resource_scope(|world, mut task_queue: Mut<TaskQueue<Task>>| {
+                let mut asset_tasks = world.resource_mut::<AssetTasks<Task>>();
+                let missing_task =
+                    asset_tasks.track_optional(&mut task_queue, &server, &missing, Task::Image);
+                let (folder_task, handles) = asset_tasks.track_folder_optional(
+                    &mut task_queue,
+                    &server,
+                    "missing",
+                    Task::Sound,
+                );
+                assert!(handles.is_empty());
+                (missing_task, folder_task)
+            });
+    update_until_finished(&mut app);
+
+    // 可选的资源加载失败时任务也算作完成，不会阻止跳转
+    let task_queue = app.world.resource::<TaskQueue<Task>>();
+    assert_eq!(
+        task_queue.status(missing_task),
+        Some(&TaskStatus::Completed)
+    );
+    assert_eq!(task_queue.status(folder_task), Some(&TaskStatus::Completed));
+    app.update();
+    assert_eq!(app.world.resource::<State<AppState>>().0, AppState::InGame);
+}
+
+#[test]
+fn check_next_state() {
+    let mut app = asset_app();
+    let id = app
+        .world
+        .resource_mut::<TaskQueue<Task>>()
+        .push(Task::Sound);
+    app.update();
+    app.update();
+    assert_eq!(app.world.resource::<State<AppState>>().0, AppState::Loading);
+
+    app.world.resource_mut::<TaskQueue<Task>>().complete(id);
+    app.update();
+    app.update();
+    assert_eq!(app.world.resource::<State<AppState>>().0, AppState::InGame);
+    // 离开 state 时清空队列
+    assert!(app.world.resource::<TaskQueue<Task>>().is_empty());
+}
+
+fn compute_app() -> App {
+    let mut app = asset_app();
+    app.add_plugin(ComputeTaskPlugin::<AppState, Task, u32>::new(
+        AppState::Loading,
+    ));
+    app
+}
+
+#[test]
+fn check_compute_tasks() {
+    let mut app = compute_app();
+    let (sum, error, panic) =
+        app.world
+            .resource_scope(|world, mut task_queue: Mut<TaskQueue<Task>>| {
+                let mut compute_tasks = world.resource_mut::<ComputeTasks<Task, u32>>();
+                let sum = compute_tasks.spawn(&mut task_queue, Task::Image, || {
+                    Ok::<_, String>((1..=10).sum())
+                });
+                let error = compute_tasks.spawn(&mut task_queue, Task::Image, || {
+                    Err::<u32, _>("missing art/floor.png")
+                });
+                let panic =
+                    compute_tasks.spawn(&mut task_queue, Task::Image, || -> Result<u32, String> {
+                        panic!("broken tileset")
+                    });
+                (sum, error, panic)
+            });
+    update_until_finished(&mut app);
+
+    let task_queue = app.world.resource::<TaskQueue<Task>>();
+    assert_eq!(task_queue.status(sum), Some(&TaskStatus::Completed));
+    assert_eq!(
+        task_queue.status(error),
+        Some(&TaskStatus::Failed(String::from("missing art/floor.png")))
+    );
+    match task_queue.status(panic) {
+        Some(TaskStatus::Failed(error)) => assert!(error.contains("broken tileset")),
+        status => panic!("unexpected status {status:?}"),
+    }
+    let mut compute_tasks = app.world.resource_mut::<ComputeTasks<Task, u32>>();
+    assert_eq!(compute_tasks.take_result(sum), Some(55));
+    assert_eq!(compute_tasks.take_result(sum), None);
+    assert_eq!(compute_tasks.take_result(error), None);
+}
+
+#[test]
+fn check_cancel_and_timeout() {
+    let mut app = compute_app();
+    let (cancelled, timeout) =
+        app.world
+            .resource_scope(|world, mut task_queue: Mut<TaskQueue<Task>>| {
+                let mut compute_tasks = world.resource_mut::<ComputeTasks<Task, u32>>();
+                let slow = || {
+                    std::thread::sleep(std::time::Duration::from_millis(500));
+                    Ok::<_, String>(0)
+                };
+                let cancelled = compute_tasks.spawn(&mut task_queue, Task::Sound, slow);
+                let timeout = compute_tasks.spawn_with_timeout(
+                    &mut task_queue,
+                    Task::Sound,
+                    std::time::Duration::from_millis(10),
+                    slow,
+                );
+                assert!(compute_tasks.cancel(&mut task_queue, cancelled));
+                assert!(!compute_tasks.is_running(cancelled));
+                (cancelled, timeout)
+            });
+    update_until_finished(&mut app);
+
+    let task_queue = app.world.resource::<TaskQueue<Task>>();
+    assert_eq!(
+        task_queue.status(cancelled),
+        Some(&TaskStatus::Failed(String::from("cancelled")))
+    );
+    match task_queue.status(timeout) {
+        Some(TaskStatus::Failed(error)) => assert!(error.contains("timed out")),
+        status => panic!("unexpected status {status:?}"),
+    }
+}
+
+#[test]
+fn check_compute_tasks_state_exit() {
+    let mut app = compute_app();
+    app.world
+        .resource_scope(|world, mut task_queue: Mut<TaskQueue<Task>>| {
+            let mut compute_tasks = world.resource_mut::<ComputeTasks<Task, u32>>();
+            compute_tasks.spawn(&mut task_queue, Task::Image, || Ok::<_, String>(1));
+            compute_tasks.spawn(&mut task_queue, Task::Sound, || {
+                std::thread::sleep(std::time::Duration::from_millis(500));
+                Ok::<_, String>(2)
+            });
+        });
+    app.update();
+    assert!(!app.world.resource::<ComputeTasks<Task, u32>>().is_empty());
+    // 计算还没有结束时离开 state，计算和结果都被丢弃
+    app.world
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::InGame);
+    app.update();
+    assert_eq!(app.world.resource::<State<AppState>>().0, AppState::InGame);
+    assert!(app.world.resource::<ComputeTasks<Task, u32>>().is_empty());
+    assert!(app.world.resource::<TaskQueue<Task>>().is_empty());
+}
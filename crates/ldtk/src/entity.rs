@@ -0,0 +1,270 @@
+//! 根据 field 的 identifier 把 [`EntityInstance`] 转换成 rust 的结构
+//!
+//! ```
+//! use ldtk::{EntityInstance, FieldError, FromEntity};
+//!
+//! ldtk::field_enum! {
+//!     #[derive(Debug, PartialEq)]
+//!     pub enum Material { Stone, Water }
+//! }
+//!
+//! struct Door {
+//!     material: Material,
+//!     locked: bool,
+//!     key: Option<String>,
+//! }
+//!
+//! impl FromEntity for Door {
+//!     fn from_entity(entity: &EntityInstance) -> Result<Self, FieldError> {
+//!         Ok(Self {
+//!             material: entity.field("Material")?,
+//!             locked: entity.field_or("Locked", false)?,
+//!             key: entity.field("Key")?,
+//!         })
+//!     }
+//! }
+//! ```
+
+use std::fmt::{Display, Formatter};
+
+use crate::{EntityInstance, FieldValue};
+
+/// 从 entity 中读取所有需要的 field
+pub trait FromEntity: Sized {
+    fn from_entity(entity: &EntityInstance) -> Result<Self, FieldError>;
+}
+
+/// 单个 field 的值可以转换成的类型
+pub trait FromField: Sized {
+    /// 类型不匹配时返回 None
+    fn from_field(value: &FieldValue) -> Option<Self>;
+    /// 用在错误信息中的类型名称
+    fn expected() -> String;
+    /// field 的值是 null 时使用的值，只有 [`Option`] 可以接受 null
+    fn from_null() -> Option<Self> {
+        None
+    }
+}
+
+impl EntityInstance {
+    /// 读取 field，field 不存在、是 null（除了 [`Option`]）或者类型不匹配时返回错误
+    pub fn field<T: FromField>(&self, identifier: &str) -> Result<T, FieldError> {
+        let Some(field) = self
+            .field_instances
+            .iter()
+            .find(|field| field.identifier == identifier)
+        else {
+            return Err(self.field_error(identifier, FieldErrorKind::Missing));
+        };
+        let value = field.get_value();
+        if value == FieldValue::Null {
+            return T::from_null()
+                .ok_or_else(|| self.field_error(identifier, FieldErrorKind::Null));
+        }
+        T::from_field(&value).ok_or_else(|| {
+            self.field_error(
+                identifier,
+                FieldErrorKind::Mistyped {
+                    expected: T::expected(),
+                    found: value,
+                },
+            )
+        })
+    }
+    /// 和 [`EntityInstance::field`] 一样，但是 field 不存在或者是 null 时使用默认值
+    pub fn field_or<T: FromField>(&self, identifier: &str, default: T) -> Result<T, FieldError> {
+        match self.field::<Option<T>>(identifier) {
+            Ok(value) => Ok(value.unwrap_or(default)),
+            Err(FieldError {
+                kind: FieldErrorKind::Missing,
+                ..
+            }) => Ok(default),
+            Err(e) => Err(e),
+        }
+    }
+    fn field_error(&self, field: &str, kind: FieldErrorKind) -> FieldError {
+        FieldError {
+            entity: self.identifier.clone(),
+            field: field.to_string(),
+            kind,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+    pub entity: String,
+    pub field: String,
+    pub kind: FieldErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldErrorKind {
+    Missing,
+    Null,
+    Mistyped { expected: String, found: FieldValue },
+}
+
+impl Display for FieldError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "field `{}` of entity `{}` ", self.field, self.entity)?;
+        match &self.kind {
+            FieldErrorKind::Missing => write!(f, "is missing"),
+            FieldErrorKind::Null => write!(f, "is null"),
+            FieldErrorKind::Mistyped { expected, found } => {
+                write!(f, "expects {expected} but found {found:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FieldError {}
+
+impl FromField for bool {
+    fn from_field(value: &FieldValue) -> Option<Self> {
+        match value {
+            FieldValue::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+    fn expected() -> String {
+        String::from("Bool")
+    }
+}
+
+/// 整数需要在类型的范围内
+macro_rules! impl_from_int {
+    ($($type_:ty),*) => {
+        $(
+            impl FromField for $type_ {
+                fn from_field(value: &FieldValue) -> Option<Self> {
+                    match value {
+                        FieldValue::Int(v) => <$type_>::try_from(*v).ok(),
+                        _ => None,
+                    }
+                }
+                fn expected() -> String {
+                    format!("Int in range of {}", stringify!($type_))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_int!(u8, u16, u32, u64, usize, i8, i16, i32, i64);
+
+impl FromField for f32 {
+    fn from_field(value: &FieldValue) -> Option<Self> {
+        match value {
+            FieldValue::Float(v) => Some(*v),
+            FieldValue::Int(v) => Some(*v as f32),
+            _ => None,
+        }
+    }
+    fn expected() -> String {
+        String::from("Float")
+    }
+}
+
+impl FromField for String {
+    fn from_field(value: &FieldValue) -> Option<Self> {
+        match value {
+            FieldValue::String(v) | FieldValue::Enum(v) | FieldValue::EntityRef(v) => {
+                Some(v.clone())
+            }
+            _ => None,
+        }
+    }
+    fn expected() -> String {
+        String::from("String")
+    }
+}
+
+/// Color 转换成 `[r, g, b]`
+impl FromField for [u8; 3] {
+    fn from_field(value: &FieldValue) -> Option<Self> {
+        match value {
+            FieldValue::Color { r, g, b } => Some([*r, *g, *b]),
+            _ => None,
+        }
+    }
+    fn expected() -> String {
+        String::from("Color")
+    }
+}
+
+/// Point 转换成 grid 坐标 `[cx, cy]`
+impl FromField for [u32; 2] {
+    fn from_field(value: &FieldValue) -> Option<Self> {
+        match value {
+            FieldValue::Point { cx, cy } => Some([*cx, *cy]),
+            _ => None,
+        }
+    }
+    fn expected() -> String {
+        String::from("Point")
+    }
+}
+
+impl<T: FromField> FromField for Option<T> {
+    fn from_field(value: &FieldValue) -> Option<Self> {
+        T::from_field(value).map(Some)
+    }
+    fn expected() -> String {
+        format!("optional {}", T::expected())
+    }
+    fn from_null() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl<T: FromField> FromField for Vec<T> {
+    fn from_field(value: &FieldValue) -> Option<Self> {
+        match value {
+            FieldValue::Array(values) => values.iter().map(T::from_field).collect(),
+            _ => None,
+        }
+    }
+    fn expected() -> String {
+        format!("Array<{}>", T::expected())
+    }
+}
+
+/// 定义和 ldtk 中的 enum 对应的 rust enum，variant 的名称需要和 ldtk 中一致
+///
+/// ```
+/// ldtk::field_enum! {
+///     #[derive(Debug, Clone, Copy, PartialEq)]
+///     pub enum Material { Stone, Water }
+/// }
+/// ```
+#[macro_export]
+macro_rules! field_enum {
+    ($(#[$meta:meta])* $vis:vis enum $name:ident { $($variant:ident),* $(,)? }) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($variant),*
+        }
+
+        impl $crate::FromField for $name {
+            fn from_field(value: &$crate::FieldValue) -> Option<Self> {
+                match value {
+                    $crate::FieldValue::Enum(variant) => match variant.as_str() {
+                        $(stringify!($variant) => Some(Self::$variant),)*
+                        _ => None,
+                    },
+                    _ => None,
+                }
+            }
+            fn expected() -> String {
+                String::from(concat!(
+                    "enum ",
+                    stringify!($name),
+                    " (",
+                    stringify!($($variant)|*),
+                    ")"
+                ))
+            }
+        }
+    };
+}
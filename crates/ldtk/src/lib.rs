@@ -1,4 +1,12 @@
-use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+pub use entity::{FieldError, FieldErrorKind, FromEntity, FromField};
+pub use version::{Version, VersionError, MIN_SUPPORTED_VERSION, UNSUPPORTED_VERSION};
+
+mod entity;
+mod version;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Project {
@@ -7,7 +15,98 @@ pub struct Project {
     #[serde(rename = "defaultGridSize")]
     pub default_grid_size: usize,
     pub defs: Definitions,
+    /// 只有一个 world 时 level 都在这里，开启 multi-worlds 之后这里是空的，level 在 [`Project::worlds`] 中
     pub levels: Vec<Level>,
+    /// level 是否保存在单独的 `.ldtkl` 文件中
+    #[serde(rename = "externalLevels", default)]
+    pub external_levels: bool,
+    /// 开启 multi-worlds 之后是 null
+    #[serde(rename = "worldLayout", default)]
+    pub world_layout: Option<WorldLayout>,
+    #[serde(default)]
+    pub worlds: Vec<World>,
+}
+
+impl Project {
+    /// 读取 project，并把保存在外部文件中的 level 一起加载进来
+    ///
+    /// 线性布局的 world 中 level 的 `worldX`/`worldY` 是 -1，加载时会按照排列顺序计算出实际的位置
+    pub fn from<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let mut project = Self::parse(&std::fs::read_to_string(path)?)?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for level in project.levels.iter_mut() {
+            level.load_external(dir)?;
+        }
+        layout_levels(project.world_layout, &mut project.levels);
+        for world in project.worlds.iter_mut() {
+            for level in world.levels.iter_mut() {
+                level.load_external(dir)?;
+            }
+            layout_levels(world.world_layout, &mut world.levels);
+        }
+        Ok(project)
+    }
+    /// 从 json 解析 project，不加载外部的 level
+    ///
+    /// 会先检查 `jsonVersion`，不支持的版本返回 [`VersionError`]，
+    /// 支持的版本中格式有差别的地方会先转换成当前结构能读取的格式
+    pub fn parse(content: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut value = serde_json::from_str::<serde_json::Value>(content)?;
+        version::upgrade(&mut value)?;
+        Ok(serde_json::from_value(value)?)
+    }
+    /// project 保存时使用的 ldtk 版本，无法解析时返回 None
+    pub fn version(&self) -> Option<Version> {
+        Version::parse(&self.json_version)
+    }
+    /// 所有 world 中的 level
+    pub fn all_levels(&self) -> impl Iterator<Item = &Level> {
+        self.levels
+            .iter()
+            .chain(self.worlds.iter().flat_map(|world| world.levels.iter()))
+    }
+    pub fn level_by_iid(&self, iid: &str) -> Option<&Level> {
+        self.all_levels().find(|level| level.iid == iid)
+    }
+}
+
+/// 开启 multi-worlds 之后，每个 world 单独保存自己的布局和 level
+#[derive(Serialize, Deserialize, Debug)]
+pub struct World {
+    pub iid: String,
+    pub identifier: String,
+    #[serde(rename = "worldLayout", default)]
+    pub world_layout: Option<WorldLayout>,
+    pub levels: Vec<Level>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WorldLayout {
+    Free,
+    GridVania,
+    LinearHorizontal,
+    LinearVertical,
+}
+
+/// 线性布局的 level 按照顺序首尾相接
+fn layout_levels(layout: Option<WorldLayout>, levels: &mut [Level]) {
+    let mut offset = 0;
+    for level in levels.iter_mut() {
+        match layout {
+            Some(WorldLayout::LinearHorizontal) => {
+                level.world_x = offset;
+                level.world_y = 0;
+                offset += level.px_wid as i32;
+            }
+            Some(WorldLayout::LinearVertical) => {
+                level.world_x = 0;
+                level.world_y = offset;
+                offset += level.px_hei as i32;
+            }
+            _ => {}
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -15,12 +114,42 @@ pub struct Definitions {
     pub layers: Vec<LayerDefinition>,
     pub tilesets: Vec<TilesetDefinition>,
     pub entities: Vec<EntityDefinition>,
+    #[serde(default)]
+    pub enums: Vec<EnumDefinition>,
+    /// 从外部文件导入的 enum
+    #[serde(rename = "externalEnums", default)]
+    pub external_enums: Vec<EnumDefinition>,
+}
+
+impl Definitions {
+    pub fn enum_by_uid(&self, uid: usize) -> Option<&EnumDefinition> {
+        self.enums
+            .iter()
+            .chain(self.external_enums.iter())
+            .find(|def| def.uid == uid)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EnumDefinition {
+    pub uid: usize,
+    pub identifier: String,
+    pub values: Vec<EnumValue>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EnumValue {
+    pub id: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum LayerType {
     Entities,
     Tiles,
+    IntGrid,
+    AutoLayer,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -49,6 +178,55 @@ pub struct TilesetDefinition {
     pub rel_path: String,
     #[serde(rename = "savedSelections")]
     pub saved_selections: Vec<SavedSelection>,
+    #[serde(rename = "customData", default)]
+    pub custom_data: Vec<TileCustomData>,
+    /// enumTags 中使用的 enum
+    #[serde(rename = "tagsSourceEnumUid", default)]
+    pub tags_source_enum_uid: Option<usize>,
+    /// 每个 enum value 标记了哪些 tile
+    #[serde(rename = "enumTags", default)]
+    pub enum_tags: Vec<EnumTag>,
+}
+
+impl TilesetDefinition {
+    /// tile 被标记的所有 enum value
+    pub fn tile_enum_tags(&self, tile_id: u32) -> impl Iterator<Item = &str> {
+        self.enum_tags
+            .iter()
+            .filter(move |tag| tag.tile_ids.contains(&tile_id))
+            .map(|tag| tag.enum_value_id.as_str())
+    }
+    pub fn tile_custom_data(&self, tile_id: u32) -> Option<&str> {
+        self.custom_data
+            .iter()
+            .find(|data| data.tile_id == tile_id)
+            .map(|data| data.data.as_str())
+    }
+    /// 根据 tile id 得到 tile 在 tileset 中的位置，根据 tile 的左上角定位
+    pub fn tile_src(&self, tile_id: u32) -> [u32; 2] {
+        let c_wid = self.c_wid.max(1);
+        [
+            (tile_id % c_wid) * self.tile_grid_size,
+            (tile_id / c_wid) * self.tile_grid_size,
+        ]
+    }
+}
+
+/// 给 tile 标记 enum value，比如脚步声的材质
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EnumTag {
+    #[serde(rename = "enumValueId")]
+    pub enum_value_id: String,
+    #[serde(rename = "tileIds")]
+    pub tile_ids: Vec<u32>,
+}
+
+/// 在 ldtk 中给单个 tile 配置的自定义数据
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TileCustomData {
+    #[serde(rename = "tileId")]
+    pub tile_id: u32,
+    pub data: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -113,6 +291,8 @@ pub struct FieldDefinition {
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Level {
+    #[serde(default)]
+    pub iid: String,
     pub identifier: String,
     #[serde(rename = "pxWid")]
     pub px_wid: u32,
@@ -122,8 +302,88 @@ pub struct Level {
     pub world_x: i32,
     #[serde(rename = "worldY")]
     pub world_y: i32,
-    #[serde(rename = "layerInstances")]
+    #[serde(rename = "worldDepth", default)]
+    pub world_depth: i32,
+    /// level 保存在外部文件时，相对 project 文件所在目录的路径
+    #[serde(rename = "externalRelPath", default)]
+    pub external_rel_path: Option<String>,
+    /// level 保存在外部文件时，project 中的这个字段是 null
+    #[serde(
+        rename = "layerInstances",
+        default,
+        deserialize_with = "null_as_default"
+    )]
     pub layer_instances: Vec<LayerInstance>,
+    #[serde(rename = "__neighbours", default)]
+    pub neighbours: Vec<Neighbour>,
+}
+
+impl Level {
+    /// 从外部文件中读取 layer 等内容，`dir` 是 project 文件所在的目录
+    fn load_external(&mut self, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(rel_path) = &self.external_rel_path else {
+            return Ok(());
+        };
+        let path = dir.join(rel_path);
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("failed to read external level {}: {e}", path.display()))?;
+        let external = serde_json::from_str::<Level>(&content)?;
+        // 线性布局时需要重新计算位置，所以保留 project 中的数据
+        *self = Level {
+            world_x: self.world_x,
+            world_y: self.world_y,
+            external_rel_path: self.external_rel_path.take(),
+            ..external
+        };
+        Ok(())
+    }
+}
+
+/// 和 level 相邻的 level，由 ldtk 计算好保存在文件中
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Neighbour {
+    #[serde(rename = "levelIid")]
+    pub level_iid: String,
+    pub dir: NeighbourDir,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum NeighbourDir {
+    #[serde(rename = "n")]
+    North,
+    #[serde(rename = "s")]
+    South,
+    #[serde(rename = "e")]
+    East,
+    #[serde(rename = "w")]
+    West,
+    #[serde(rename = "ne")]
+    NorthEast,
+    #[serde(rename = "nw")]
+    NorthWest,
+    #[serde(rename = "se")]
+    SouthEast,
+    #[serde(rename = "sw")]
+    SouthWest,
+    /// 在更低的 depth 中
+    #[serde(rename = "<")]
+    Below,
+    /// 在更高的 depth 中
+    #[serde(rename = ">")]
+    Above,
+    /// 在同一个 depth 中重叠
+    #[serde(rename = "o")]
+    Overlap,
+    #[serde(other)]
+    Unknown,
+}
+
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -140,10 +400,20 @@ pub struct LayerInstance {
     pub layer_def_uid: usize,
     #[serde(rename = "gridTiles")]
     pub grid_tiles: Vec<GridTile>,
+    /// IntGrid 和 AutoLayer 根据规则自动生成的 tile
+    #[serde(rename = "autoLayerTiles", default)]
+    pub auto_layer_tiles: Vec<GridTile>,
     #[serde(rename = "entityInstances")]
     pub entity_instances: Vec<EntityInstance>,
 }
 
+impl LayerInstance {
+    /// 手动放置的 tile 和自动生成的 tile
+    pub fn tiles(&self) -> impl Iterator<Item = &GridTile> {
+        self.grid_tiles.iter().chain(self.auto_layer_tiles.iter())
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GridTile {
     /// 表示在layer中的位置，根据tile的左上角定位
@@ -174,52 +444,154 @@ pub struct FieldInstances {
     value: serde_json::Value,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// ldtk 中 `__type` 是字符串，比如 `Int`、`LocalEnum.Material`、`Array<Point>`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "String", into = "String")]
 pub enum FieldType {
     Color,
     Int,
     Float,
     Bool,
+    String,
+    FilePath,
+    Point,
+    EntityRef,
+    /// enum 的名称
+    Enum(String),
+    /// 从外部文件导入的 enum 的名称
+    ExternEnum(String),
+    Array(Box<FieldType>),
+    Other(String),
+}
+
+impl From<String> for FieldType {
+    fn from(value: String) -> Self {
+        if let Some(inner) = value
+            .strip_prefix("Array<")
+            .and_then(|v| v.strip_suffix('>'))
+        {
+            return Self::Array(Box::new(Self::from(inner.to_string())));
+        }
+        if let Some((_, name)) = value.split_once("LocalEnum.") {
+            return Self::Enum(name.to_string());
+        }
+        if let Some((_, name)) = value.split_once("ExternEnum.") {
+            return Self::ExternEnum(name.to_string());
+        }
+        match value.as_str() {
+            "Color" => Self::Color,
+            "Int" => Self::Int,
+            "Float" => Self::Float,
+            "Bool" => Self::Bool,
+            "String" | "Multilines" => Self::String,
+            "FilePath" => Self::FilePath,
+            "Point" => Self::Point,
+            "EntityRef" => Self::EntityRef,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl From<FieldType> for String {
+    fn from(value: FieldType) -> Self {
+        match value {
+            FieldType::Color => String::from("Color"),
+            FieldType::Int => String::from("Int"),
+            FieldType::Float => String::from("Float"),
+            FieldType::Bool => String::from("Bool"),
+            FieldType::String => String::from("String"),
+            FieldType::FilePath => String::from("FilePath"),
+            FieldType::Point => String::from("Point"),
+            FieldType::EntityRef => String::from("EntityRef"),
+            FieldType::Enum(name) => format!("LocalEnum.{name}"),
+            FieldType::ExternEnum(name) => format!("ExternEnum.{name}"),
+            FieldType::Array(inner) => format!("Array<{}>", String::from(*inner)),
+            FieldType::Other(value) => value,
+        }
+    }
 }
 
 impl FieldInstances {
     pub fn get_value(&self) -> FieldValue {
-        fn hex_to_rgb(hex: &str) -> Option<(u8, u8, u8)> {
-            if hex.len() != 6 {
-                return None;
-            }
-            let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
-            let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
-            let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
-            Some((r, g, b))
+        parse_value(&self.type_, &self.value)
+    }
+}
+
+fn parse_value(type_: &FieldType, value: &serde_json::Value) -> FieldValue {
+    fn hex_to_rgb(hex: &str) -> Option<(u8, u8, u8)> {
+        if hex.len() != 6 {
+            return None;
         }
-        match self.type_ {
-            FieldType::Color => {
-                // self.value should be #FFFFFF
-                let hex = self
-                    .value
-                    .as_str()
-                    .unwrap_or_default()
-                    .chars()
-                    .skip(1)
-                    .collect::<String>();
-                if let Some((r, g, b)) = hex_to_rgb(&hex) {
-                    FieldValue::Color { r, g, b }
-                } else {
-                    FieldValue::Color { r: 0, g: 0, b: 0 }
-                }
+        let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
+        let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
+        let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
+        Some((r, g, b))
+    }
+    if value.is_null() {
+        return FieldValue::Null;
+    }
+    match type_ {
+        FieldType::Color => {
+            // value should be #FFFFFF
+            let hex = value
+                .as_str()
+                .unwrap_or_default()
+                .chars()
+                .skip(1)
+                .collect::<String>();
+            if let Some((r, g, b)) = hex_to_rgb(&hex) {
+                FieldValue::Color { r, g, b }
+            } else {
+                FieldValue::Color { r: 0, g: 0, b: 0 }
             }
-            FieldType::Int => FieldValue::Int(self.value.as_u64().unwrap_or_default() as usize),
-            FieldType::Float => FieldValue::Float(self.value.as_f64().unwrap_or_default() as f32),
-            FieldType::Bool => FieldValue::Bool(self.value.as_bool().unwrap_or_default()),
         }
+        FieldType::Int => FieldValue::Int(value.as_i64().unwrap_or_default()),
+        FieldType::Float => FieldValue::Float(value.as_f64().unwrap_or_default() as f32),
+        FieldType::Bool => FieldValue::Bool(value.as_bool().unwrap_or_default()),
+        FieldType::String | FieldType::FilePath => {
+            FieldValue::String(value.as_str().unwrap_or_default().to_string())
+        }
+        FieldType::Point => FieldValue::Point {
+            cx: value["cx"].as_u64().unwrap_or_default() as u32,
+            cy: value["cy"].as_u64().unwrap_or_default() as u32,
+        },
+        FieldType::EntityRef => {
+            FieldValue::EntityRef(value["entityIid"].as_str().unwrap_or_default().to_string())
+        }
+        FieldType::Enum(_) | FieldType::ExternEnum(_) => {
+            FieldValue::Enum(value.as_str().unwrap_or_default().to_string())
+        }
+        FieldType::Array(inner) => FieldValue::Array(
+            value
+                .as_array()
+                .map(|values| values.iter().map(|v| parse_value(inner, v)).collect())
+                .unwrap_or_default(),
+        ),
+        FieldType::Other(_) => FieldValue::Other(value.clone()),
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum FieldValue {
-    Color { r: u8, g: u8, b: u8 },
-    Int(usize),
+    Color {
+        r: u8,
+        g: u8,
+        b: u8,
+    },
+    Int(i64),
     Float(f32),
     Bool(bool),
+    String(String),
+    /// grid 坐标
+    Point {
+        cx: u32,
+        cy: u32,
+    },
+    /// 引用的 entity 的 iid
+    EntityRef(String),
+    /// enum 的 variant 名称
+    Enum(String),
+    Array(Vec<FieldValue>),
+    Null,
+    Other(serde_json::Value),
 }
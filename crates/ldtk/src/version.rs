@@ -0,0 +1,129 @@
+//! 不同版本的 ldtk 保存的 json 格式有一些差别
+//!
+//! 读取 project 时先检查 `jsonVersion` 是否在支持的范围内，
+//! 然后在反序列化之前把旧格式或者新格式中有变化的部分转换成当前结构能够读取的样子
+
+use std::fmt::{Display, Formatter};
+
+use serde_json::Value;
+
+/// 支持的最低版本，更早的版本中 level 和 tileset 的格式差别太大
+pub const MIN_SUPPORTED_VERSION: Version = Version::new(1, 0, 0);
+/// 不支持的最低版本，major 版本变化时格式可能完全不兼容
+pub const UNSUPPORTED_VERSION: Version = Version::new(2, 0, 0);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+    /// 解析 `1.3.3` 这样的版本号，缺少的部分当作 0，忽略 `-beta` 之类的后缀
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.split(['-', '+']).next()?;
+        let mut parts = value.split('.').map(str::parse::<u32>);
+        let major = parts.next()?.ok()?;
+        let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+        let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+        Some(Self::new(major, minor, patch))
+    }
+    pub fn is_supported(&self) -> bool {
+        MIN_SUPPORTED_VERSION <= *self && *self < UNSUPPORTED_VERSION
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VersionError {
+    Missing,
+    Invalid(String),
+    Unsupported(Version),
+}
+
+impl Display for VersionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing => write!(f, "missing jsonVersion in ldtk project"),
+            Self::Invalid(value) => write!(f, "invalid ldtk jsonVersion {value:?}"),
+            Self::Unsupported(version) => write!(
+                f,
+                "ldtk jsonVersion {version} is not supported, \
+                 please save the project with ldtk >= {MIN_SUPPORTED_VERSION} and < {UNSUPPORTED_VERSION}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VersionError {}
+
+/// 检查版本并转换格式，返回 project 的版本
+pub fn upgrade(project: &mut Value) -> Result<Version, VersionError> {
+    let raw = project
+        .get("jsonVersion")
+        .and_then(Value::as_str)
+        .ok_or(VersionError::Missing)?;
+    let version = Version::parse(raw).ok_or_else(|| VersionError::Invalid(raw.to_string()))?;
+    if !version.is_supported() {
+        return Err(VersionError::Unsupported(version));
+    }
+    if let Some(defs) = project.get_mut("defs") {
+        for layer in array_mut(defs, "layers") {
+            shim_auto_tileset(layer);
+        }
+        for tileset in array_mut(defs, "tilesets") {
+            shim_embed_atlas(tileset);
+        }
+    }
+    Ok(version)
+}
+
+fn array_mut<'a>(value: &'a mut Value, key: &str) -> impl Iterator<Item = &'a mut Value> {
+    value
+        .get_mut(key)
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+}
+
+/// 1.2 之前 auto layer 使用的 tileset 保存在 `autoTilesetDefUid` 中，之后统一使用 `tilesetDefUid`
+fn shim_auto_tileset(layer: &mut Value) {
+    let Some(layer) = layer.as_object_mut() else {
+        return;
+    };
+    if matches!(layer.get("tilesetDefUid"), None | Some(Value::Null)) {
+        if let Some(uid) = layer.get("autoTilesetDefUid").cloned() {
+            layer.insert(String::from("tilesetDefUid"), uid);
+        }
+    }
+}
+
+/// 使用 ldtk 内置图标的 tileset 没有 `relPath`，新版本中也不再保存 `savedSelections`
+fn shim_embed_atlas(tileset: &mut Value) {
+    let Some(tileset) = tileset.as_object_mut() else {
+        return;
+    };
+    if matches!(tileset.get("relPath"), None | Some(Value::Null)) {
+        let rel_path = match tileset.get("embedAtlas").and_then(Value::as_str) {
+            Some(atlas) => format!("<embed>/{atlas}"),
+            None => String::new(),
+        };
+        tileset.insert(String::from("relPath"), Value::from(rel_path));
+    }
+    tileset
+        .entry("savedSelections")
+        .or_insert_with(|| Value::Array(vec![]));
+}
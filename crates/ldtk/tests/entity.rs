@@ -0,0 +1,136 @@
+use ldtk::{EntityInstance, FieldError, FieldErrorKind, FieldValue, FromEntity};
+
+ldtk::field_enum! {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Material {
+        Stone,
+        Water,
+    }
+}
+
+#[derive(Debug)]
+struct Door {
+    color: [u8; 3],
+    material: Material,
+    locked: bool,
+    key: Option<String>,
+    path: Vec<[u32; 2]>,
+}
+
+impl FromEntity for Door {
+    fn from_entity(entity: &EntityInstance) -> Result<Self, FieldError> {
+        Ok(Self {
+            color: entity.field("Color")?,
+            material: entity.field("Material")?,
+            locked: entity.field_or("Locked", true)?,
+            key: entity.field("Key")?,
+            path: entity.field("Path")?,
+        })
+    }
+}
+
+/// 和 world_generator 中一样，`Light` 的 field 都是必须配置的
+#[derive(Debug)]
+struct Light {
+    color: [u8; 3],
+    alpha: u8,
+    inner: bool,
+}
+
+impl FromEntity for Light {
+    fn from_entity(entity: &EntityInstance) -> Result<Self, FieldError> {
+        Ok(Self {
+            color: entity.field("Color")?,
+            alpha: entity.field("Alpha")?,
+            inner: entity.field("Inner")?,
+        })
+    }
+}
+
+fn entity(fields: &str) -> EntityInstance {
+    named_entity("Door", fields)
+}
+
+fn named_entity(identifier: &str, fields: &str) -> EntityInstance {
+    serde_json::from_str(&format!(
+        r#"{{ "__identifier": "{identifier}", "defUid": 1, "px": [0, 0], "fieldInstances": [{fields}] }}"#
+    ))
+    .unwrap()
+}
+
+const COLOR: &str = r##"{ "__identifier": "Color", "__type": "Color", "__value": "#FF8800" }"##;
+const MATERIAL: &str =
+    r#"{ "__identifier": "Material", "__type": "LocalEnum.Material", "__value": "Water" }"#;
+const KEY: &str = r#"{ "__identifier": "Key", "__type": "String", "__value": null }"#;
+const PATH: &str =
+    r#"{ "__identifier": "Path", "__type": "Array<Point>", "__value": [{ "cx": 1, "cy": 2 }] }"#;
+
+#[test]
+fn from_entity() {
+    let door = Door::from_entity(&entity(&[COLOR, MATERIAL, KEY, PATH].join(","))).unwrap();
+    assert_eq!(door.color, [255, 136, 0]);
+    assert_eq!(door.material, Material::Water);
+    // 没有配置的 field 使用默认值，null 转换成 None
+    assert!(door.locked);
+    assert_eq!(door.key, None);
+    assert_eq!(door.path, vec![[1, 2]]);
+}
+
+#[test]
+fn extern_enum() {
+    let material =
+        r#"{ "__identifier": "Material", "__type": "ExternEnum.Material", "__value": "Stone" }"#;
+    let entity = entity(&[COLOR, material, KEY, PATH].join(","));
+    assert_eq!(
+        Door::from_entity(&entity).unwrap().material,
+        Material::Stone
+    );
+    // 写回时保留 enum 的来源
+    let content = serde_json::to_string(&entity).unwrap();
+    assert!(content.contains("ExternEnum.Material"), "{content}");
+}
+
+#[test]
+fn descriptive_error() {
+    let error = Door::from_entity(&entity(&[COLOR, KEY, PATH].join(","))).unwrap_err();
+    assert_eq!(error.entity, "Door");
+    assert_eq!(error.field, "Material");
+    assert_eq!(error.kind, FieldErrorKind::Missing);
+    assert_eq!(
+        error.to_string(),
+        "field `Material` of entity `Door` is missing"
+    );
+
+    let material =
+        r#"{ "__identifier": "Material", "__type": "LocalEnum.Material", "__value": "Lava" }"#;
+    let error = Door::from_entity(&entity(&[COLOR, material, KEY, PATH].join(","))).unwrap_err();
+    assert_eq!(
+        error.kind,
+        FieldErrorKind::Mistyped {
+            expected: String::from("enum Material (Stone | Water)"),
+            found: FieldValue::Enum(String::from("Lava")),
+        }
+    );
+
+    let entity = entity(r#"{ "__identifier": "Alpha", "__type": "Int", "__value": 300 }"#);
+    assert!(entity.field::<u8>("Alpha").is_err());
+    assert_eq!(entity.field::<u16>("Alpha").unwrap(), 300);
+}
+
+#[test]
+fn light_requires_fields() {
+    let inner = r#"{ "__identifier": "Inner", "__type": "Bool", "__value": true }"#;
+    let alpha = r#"{ "__identifier": "Alpha", "__type": "Int", "__value": 200 }"#;
+    let light =
+        Light::from_entity(&named_entity("Light", &[COLOR, alpha, inner].join(","))).unwrap();
+    assert_eq!(light.color, [255, 136, 0]);
+    assert_eq!(light.alpha, 200);
+    assert!(light.inner);
+
+    let error = Light::from_entity(&named_entity("Light", &[COLOR, inner].join(","))).unwrap_err();
+    assert_eq!(error.kind, FieldErrorKind::Missing);
+    assert_eq!(
+        error.to_string(),
+        "field `Alpha` of entity `Light` is missing"
+    );
+}
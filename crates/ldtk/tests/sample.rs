@@ -1,4 +1,4 @@
-use ldtk::Project;
+use ldtk::{NeighbourDir, Project, WorldLayout};
 
 #[test]
 fn check() {
@@ -23,3 +23,48 @@ fn check_real() {
     )
     .unwrap();
 }
+
+#[test]
+fn external_levels() {
+    let project = Project::from("tests/external.ldtk").unwrap();
+    assert!(project.external_levels);
+    let level = &project.levels[0];
+    assert_eq!(level.layer_instances[0].grid_tiles[0].src, [64, 0]);
+    assert_eq!(level.neighbours[0].dir, NeighbourDir::East);
+    let neighbour = project
+        .level_by_iid(&level.neighbours[0].level_iid)
+        .unwrap();
+    assert_eq!(neighbour.identifier, "Level_1");
+    // 线性布局的 level 依次向右排列
+    assert_eq!((level.world_x, level.world_y), (0, 0));
+    assert_eq!((neighbour.world_x, neighbour.world_y), (64, 0));
+}
+
+#[test]
+fn multi_worlds() {
+    let project = Project::from("tests/multi_world.ldtk").unwrap();
+    assert!(project.world_layout.is_none());
+    assert_eq!(project.worlds.len(), 2);
+    assert_eq!(
+        project.worlds[1].world_layout,
+        Some(WorldLayout::LinearVertical)
+    );
+    let levels = project.all_levels().collect::<Vec<_>>();
+    assert_eq!(levels.len(), 3);
+    assert_eq!((levels[0].world_x, levels[0].world_y), (128, 256));
+    assert_eq!(levels[0].neighbours[0].dir, NeighbourDir::Below);
+    assert_eq!((levels[2].world_x, levels[2].world_y), (0, 32));
+}
+
+#[test]
+fn enums_and_tile_data() {
+    let project = Project::from("tests/sample.ldtk").unwrap();
+    let footstep = project.defs.enum_by_uid(18).unwrap();
+    assert_eq!(footstep.identifier, "Footstep");
+    assert_eq!(footstep.values[1].id, "Water");
+    let floor = &project.defs.tilesets[3];
+    assert_eq!(floor.tags_source_enum_uid, Some(18));
+    assert_eq!(floor.tile_enum_tags(11).collect::<Vec<_>>(), vec!["Stone"]);
+    assert_eq!(floor.tile_enum_tags(0).count(), 0);
+    assert_eq!(floor.tile_custom_data(5), Some("slow"));
+}
@@ -0,0 +1,112 @@
+use ldtk::{LayerType, Project, Version, VersionError, MIN_SUPPORTED_VERSION, UNSUPPORTED_VERSION};
+use serde_json::Value;
+
+fn sample() -> Value {
+    serde_json::from_str(&std::fs::read_to_string("tests/sample.ldtk").unwrap()).unwrap()
+}
+
+fn parse(value: &Value) -> Result<Project, Box<dyn std::error::Error>> {
+    Project::parse(&value.to_string())
+}
+
+fn version_error(value: &Value) -> VersionError {
+    parse(value)
+        .unwrap_err()
+        .downcast::<VersionError>()
+        .map(|e| *e)
+        .unwrap()
+}
+
+#[test]
+fn parse_version() {
+    assert_eq!(Version::parse("1.3.3"), Some(Version::new(1, 3, 3)));
+    assert_eq!(Version::parse("1.5"), Some(Version::new(1, 5, 0)));
+    assert_eq!(Version::parse("1.4.0-beta"), Some(Version::new(1, 4, 0)));
+    assert_eq!(Version::parse("one"), None);
+    assert!(Version::new(1, 2, 5) < Version::new(1, 10, 0));
+    assert_eq!(Version::new(1, 2, 5).to_string(), "1.2.5");
+
+    let project = Project::from("tests/sample.ldtk").unwrap();
+    assert_eq!(project.version(), Some(Version::new(1, 2, 5)));
+}
+
+#[test]
+fn unsupported_version() {
+    let mut value = sample();
+    value["jsonVersion"] = Value::from("0.9.3");
+    let e = version_error(&value);
+    assert_eq!(e, VersionError::Unsupported(Version::new(0, 9, 3)));
+    assert!(e.to_string().contains("0.9.3"));
+
+    value["jsonVersion"] = Value::from("2.0.0");
+    assert_eq!(
+        version_error(&value),
+        VersionError::Unsupported(Version::new(2, 0, 0))
+    );
+
+    value["jsonVersion"] = Value::from("latest");
+    assert_eq!(
+        version_error(&value),
+        VersionError::Invalid(String::from("latest"))
+    );
+
+    value.as_object_mut().unwrap().remove("jsonVersion");
+    assert_eq!(version_error(&value), VersionError::Missing);
+}
+
+/// 模拟其他 1.x 版本保存的格式
+#[test]
+fn compatible_format() {
+    let mut value = sample();
+    value["jsonVersion"] = Value::from("1.5.3");
+    let layer = &mut value["defs"]["layers"][4];
+    layer["__type"] = Value::from("AutoLayer");
+    layer["autoTilesetDefUid"] = layer["tilesetDefUid"].take();
+    for tileset in value["defs"]["tilesets"].as_array_mut().unwrap() {
+        tileset.as_object_mut().unwrap().remove("savedSelections");
+    }
+    let tileset = &mut value["defs"]["tilesets"][0];
+    tileset["relPath"] = Value::Null;
+    tileset["embedAtlas"] = Value::from("LDTK_ICONS");
+    let layer = &mut value["levels"][0]["layerInstances"][4];
+    layer["autoLayerTiles"] = layer["gridTiles"].take();
+    layer["gridTiles"] = Value::Array(vec![]);
+
+    let project = parse(&value).unwrap();
+    let layer = &project.defs.layers[4];
+    assert!(matches!(layer.type_, LayerType::AutoLayer));
+    assert_eq!(layer.tileset_def_uid, Some(12));
+    assert_eq!(project.defs.tilesets[0].rel_path, "<embed>/LDTK_ICONS");
+    assert!(project.defs.tilesets[3].saved_selections.is_empty());
+    let layer = &project.levels[0].layer_instances[4];
+    assert!(layer.grid_tiles.is_empty());
+    assert_eq!(layer.tiles().count(), 655);
+}
+
+#[test]
+fn version_boundaries() {
+    let mut value = sample();
+    value["jsonVersion"] = Value::from("1.0.0");
+    let project = parse(&value).unwrap();
+    assert_eq!(project.version(), Some(MIN_SUPPORTED_VERSION));
+
+    value["jsonVersion"] = Value::from("1.99.99");
+    let project = parse(&value).unwrap();
+    assert_eq!(project.version(), Some(Version::new(1, 99, 99)));
+    assert!(project.version().unwrap() < UNSUPPORTED_VERSION);
+}
+
+/// 旧版本保存的 external level project，project 文件中的 defs 也需要转换
+#[test]
+fn external_levels_shim() {
+    let project = Project::from("tests/external_1_0.ldtk").unwrap();
+    assert_eq!(project.version(), Some(MIN_SUPPORTED_VERSION));
+    assert!(project.external_levels);
+    assert_eq!(project.defs.layers[0].tileset_def_uid, Some(2));
+    let tileset = &project.defs.tilesets[0];
+    assert_eq!(tileset.rel_path, "<embed>/LDTK_ICONS");
+    assert!(tileset.saved_selections.is_empty());
+    // external level 的内容也正常读取
+    let level = &project.levels[0];
+    assert_eq!(level.layer_instances[0].grid_tiles[0].src, [64, 0]);
+}
@@ -0,0 +1,438 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+mod tmx;
+
+/// gid 的高 4 位用来表示翻转和旋转，取 tile 时需要去掉
+pub const FLIPPED_FLAGS: u32 = 0xF0000000;
+
+/// 一个 tiled 的 map，支持 `.tmj`(json) 和 `.tmx`(xml) 两种格式
+///
+/// 目前 tile layer 只支持不压缩的 csv 格式的 data
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Map {
+    pub width: u32,
+    pub height: u32,
+    #[serde(rename = "tilewidth")]
+    pub tile_width: u32,
+    #[serde(rename = "tileheight")]
+    pub tile_height: u32,
+    #[serde(default)]
+    pub infinite: bool,
+    pub layers: Vec<Layer>,
+    pub tilesets: Vec<Tileset>,
+    #[serde(default)]
+    pub properties: Vec<Property>,
+}
+
+impl Map {
+    /// 根据扩展名读取 map，并把所有外部 tileset 一起加载进来
+    ///
+    /// 加载之后 tileset 中的 image 都是相对 map 所在目录的路径
+    pub fn from<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        let mut map = if is_xml(path) {
+            tmx::parse_map(&content)?
+        } else {
+            serde_json::from_str::<Map>(&content)?
+        };
+        if map.infinite {
+            return Err(format!("infinite map {} is not supported", path.display()).into());
+        }
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for tileset in map.tilesets.iter_mut() {
+            tileset.resolve(dir)?;
+        }
+        Ok(map)
+    }
+    /// 根据 gid 找到对应的 tileset 和 tile 在 tileset 中的 id
+    pub fn find_tileset(&self, gid: u32) -> Option<(&Tileset, u32)> {
+        let gid = gid & !FLIPPED_FLAGS;
+        if gid == 0 {
+            return None;
+        }
+        self.tilesets
+            .iter()
+            .filter(|tileset| tileset.first_gid <= gid)
+            .max_by_key(|tileset| tileset.first_gid)
+            .map(|tileset| (tileset, gid - tileset.first_gid))
+    }
+    pub fn get_property(&self, name: &str) -> Option<PropertyValue> {
+        get_property(&self.properties, name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Tileset {
+    #[serde(rename = "firstgid")]
+    pub first_gid: u32,
+    /// 外部 tileset 的路径，加载之后会被清空
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub image: String,
+    #[serde(rename = "imagewidth", default)]
+    pub image_width: u32,
+    #[serde(rename = "imageheight", default)]
+    pub image_height: u32,
+    #[serde(rename = "tilewidth", default)]
+    pub tile_width: u32,
+    #[serde(rename = "tileheight", default)]
+    pub tile_height: u32,
+    #[serde(rename = "tilecount", default)]
+    pub tile_count: u32,
+    #[serde(default)]
+    pub columns: u32,
+    #[serde(default)]
+    pub margin: u32,
+    #[serde(default)]
+    pub spacing: u32,
+    #[serde(default)]
+    pub properties: Vec<Property>,
+    /// 单独配置过的 tile，比如动画和自定义属性
+    #[serde(default)]
+    pub tiles: Vec<TileDefinition>,
+}
+
+impl Tileset {
+    /// 加载外部 tileset，并把 image 转换成相对 `dir` 的路径
+    fn resolve(&mut self, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let base = if let Some(source) = self.source.take() {
+            let path = dir.join(&source);
+            let content = std::fs::read_to_string(&path)?;
+            let external = if is_xml(&path) {
+                tmx::parse_tileset(&content)?
+            } else {
+                serde_json::from_str::<Tileset>(&content)?
+            };
+            *self = Tileset {
+                first_gid: self.first_gid,
+                source: None,
+                ..external
+            };
+            Path::new(&source)
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default()
+        } else {
+            PathBuf::new()
+        };
+        if self.image.is_empty() {
+            return Err(format!("tileset {} has no image", self.name).into());
+        }
+        self.image = normalize(&base.join(&self.image));
+        Ok(())
+    }
+    pub fn rows(&self) -> u32 {
+        self.tile_count
+            .checked_div(self.columns)
+            .unwrap_or_default()
+    }
+    /// tile 在 tileset 图片中的位置，根据 tile 的左上角定位
+    pub fn tile_src(&self, id: u32) -> [u32; 2] {
+        let columns = self.columns.max(1);
+        [
+            self.margin + (id % columns) * (self.tile_width + self.spacing),
+            self.margin + (id / columns) * (self.tile_height + self.spacing),
+        ]
+    }
+    pub fn get_property(&self, name: &str) -> Option<PropertyValue> {
+        get_property(&self.properties, name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct TileDefinition {
+    pub id: u32,
+    #[serde(default)]
+    pub animation: Vec<Frame>,
+    #[serde(default)]
+    pub properties: Vec<Property>,
+}
+
+impl TileDefinition {
+    pub fn get_property(&self, name: &str) -> Option<PropertyValue> {
+        get_property(&self.properties, name)
+    }
+}
+
+/// tile 动画中的一帧
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Frame {
+    #[serde(rename = "tileid")]
+    pub tile_id: u32,
+    /// 单位是毫秒
+    pub duration: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum LayerType {
+    #[serde(rename = "tilelayer")]
+    TileLayer,
+    #[serde(rename = "objectgroup")]
+    ObjectGroup,
+    #[serde(rename = "imagelayer")]
+    ImageLayer,
+    #[serde(rename = "group")]
+    Group,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Layer {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_: LayerType,
+    #[serde(default)]
+    pub width: u32,
+    #[serde(default)]
+    pub height: u32,
+    #[serde(default = "default_visible")]
+    pub visible: bool,
+    /// 只有 tile layer 有，按行从左上角开始排列的 gid，0 表示空
+    #[serde(default)]
+    pub data: Vec<u32>,
+    /// 只有 object group 有
+    #[serde(default)]
+    pub objects: Vec<Object>,
+    /// 只有 group 有
+    #[serde(default)]
+    pub layers: Vec<Layer>,
+    #[serde(default)]
+    pub properties: Vec<Property>,
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+impl Layer {
+    /// 遍历 tile layer 中所有非空的 tile，返回 `(grid_x, grid_y, gid)`
+    ///
+    /// grid 坐标是以 layer 左上角为原点的
+    pub fn tiles(&self) -> impl Iterator<Item = (u32, u32, u32)> + '_ {
+        let width = self.width.max(1);
+        self.data
+            .iter()
+            .enumerate()
+            .filter(|(_, gid)| **gid & !FLIPPED_FLAGS != 0)
+            .map(move |(i, gid)| (i as u32 % width, i as u32 / width, *gid))
+    }
+    pub fn get_property(&self, name: &str) -> Option<PropertyValue> {
+        get_property(&self.properties, name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Object {
+    pub id: u32,
+    #[serde(default)]
+    pub name: String,
+    /// tiled 1.9 把 type 改名成了 class，1.10 又改了回来
+    #[serde(rename = "type", alias = "class", default)]
+    pub type_: String,
+    pub x: f32,
+    pub y: f32,
+    #[serde(default)]
+    pub width: f32,
+    #[serde(default)]
+    pub height: f32,
+    #[serde(default)]
+    pub point: bool,
+    #[serde(default)]
+    pub gid: Option<u32>,
+    #[serde(default)]
+    pub properties: Vec<Property>,
+}
+
+impl Object {
+    /// object 的标识，优先使用 type(class)，没有的话使用 name
+    pub fn identifier(&self) -> &str {
+        if self.type_.is_empty() {
+            &self.name
+        } else {
+            &self.type_
+        }
+    }
+    pub fn get_property(&self, name: &str) -> Option<PropertyValue> {
+        get_property(&self.properties, name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub enum PropertyType {
+    #[serde(rename = "string")]
+    #[default]
+    String,
+    #[serde(rename = "int")]
+    Int,
+    #[serde(rename = "float")]
+    Float,
+    #[serde(rename = "bool")]
+    Bool,
+    #[serde(rename = "color")]
+    Color,
+    #[serde(rename = "file")]
+    File,
+    #[serde(rename = "object")]
+    Object,
+    #[serde(rename = "class")]
+    Class,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Property {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub type_: PropertyType,
+    pub value: serde_json::Value,
+}
+
+impl Property {
+    pub fn get_value(&self) -> PropertyValue {
+        match self.type_ {
+            PropertyType::String => {
+                PropertyValue::String(self.value.as_str().unwrap_or_default().to_string())
+            }
+            PropertyType::Int => PropertyValue::Int(self.value.as_i64().unwrap_or_default()),
+            PropertyType::Float => {
+                PropertyValue::Float(self.value.as_f64().unwrap_or_default() as f32)
+            }
+            PropertyType::Bool => PropertyValue::Bool(self.value.as_bool().unwrap_or_default()),
+            PropertyType::Color => {
+                // tiled 中的颜色是 #AARRGGBB 或者 #RRGGBB
+                let hex = self
+                    .value
+                    .as_str()
+                    .unwrap_or_default()
+                    .trim_start_matches('#');
+                let channel = |i: usize| -> u8 {
+                    hex.get(i..i + 2)
+                        .and_then(|c| u8::from_str_radix(c, 16).ok())
+                        .unwrap_or_default()
+                };
+                match hex.len() {
+                    8 => PropertyValue::Color {
+                        r: channel(2),
+                        g: channel(4),
+                        b: channel(6),
+                        a: channel(0),
+                    },
+                    6 => PropertyValue::Color {
+                        r: channel(0),
+                        g: channel(2),
+                        b: channel(4),
+                        a: 255,
+                    },
+                    _ => PropertyValue::Color {
+                        r: 0,
+                        g: 0,
+                        b: 0,
+                        a: 0,
+                    },
+                }
+            }
+            PropertyType::File => {
+                PropertyValue::File(self.value.as_str().unwrap_or_default().to_string())
+            }
+            PropertyType::Object => {
+                PropertyValue::Object(self.value.as_u64().unwrap_or_default() as u32)
+            }
+            PropertyType::Class => PropertyValue::Class(self.value.clone()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub enum PropertyValue {
+    String(String),
+    Int(i64),
+    Float(f32),
+    Bool(bool),
+    Color { r: u8, g: u8, b: u8, a: u8 },
+    File(String),
+    Object(u32),
+    Class(serde_json::Value),
+}
+
+fn get_property(properties: &[Property], name: &str) -> Option<PropertyValue> {
+    properties
+        .iter()
+        .find(|property| property.name == name)
+        .map(Property::get_value)
+}
+
+/// tiled 的 `.world` 文件，用来把多个 map 摆放在同一个世界中
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct World {
+    pub maps: Vec<WorldMap>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct WorldMap {
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    pub x: i32,
+    pub y: i32,
+    /// 对应的 map 内容，加载 world 时一起加载
+    #[serde(skip)]
+    pub map: Map,
+}
+
+impl World {
+    /// 读取 world 以及其中引用的所有 map
+    pub fn from<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let mut world = serde_json::from_str::<World>(std::fs::read_to_string(path)?.as_str())?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for world_map in world.maps.iter_mut() {
+            world_map.map = Map::from(dir.join(&world_map.file_name))?;
+            // 让 map 中 tileset 的 image 路径也相对 world 所在目录
+            if let Some(map_dir) = Path::new(&world_map.file_name).parent() {
+                for tileset in world_map.map.tilesets.iter_mut() {
+                    tileset.image = normalize(&map_dir.join(&tileset.image));
+                }
+            }
+        }
+        Ok(world)
+    }
+    /// 只有单个 map 时，把它当成位于原点的 world 处理
+    pub fn single(file_name: &str, map: Map) -> Self {
+        Self {
+            maps: vec![WorldMap {
+                file_name: file_name.to_string(),
+                x: 0,
+                y: 0,
+                map,
+            }],
+        }
+    }
+}
+
+fn is_xml(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("tmx") | Some("tsx")
+    )
+}
+
+/// 去掉路径中的 `.` 和 `..`，并统一使用 `/` 分隔
+fn normalize(path: &Path) -> String {
+    let mut parts: Vec<String> = vec![];
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => match parts.last() {
+                Some(last) if last != ".." => {
+                    parts.pop();
+                }
+                _ => parts.push(String::from("..")),
+            },
+            other => parts.push(other.as_os_str().to_string_lossy().to_string()),
+        }
+    }
+    parts.join("/")
+}
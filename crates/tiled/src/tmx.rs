@@ -0,0 +1,259 @@
+//! `.tmx` 和 `.tsx` 是 xml 格式，这里把它们解析成和 json 格式相同的结构
+
+use roxmltree::{Document, Node};
+
+use crate::{
+    Frame, Layer, LayerType, Map, Object, Property, PropertyType, TileDefinition, Tileset,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+pub fn parse_map(content: &str) -> Result<Map> {
+    let document = Document::parse(content)?;
+    let root = document.root_element();
+    if !root.has_tag_name("map") {
+        return Err(format!("expect <map> but found <{}>", root.tag_name().name()).into());
+    }
+    let mut map = Map {
+        width: attr(&root, "width")?,
+        height: attr(&root, "height")?,
+        tile_width: attr(&root, "tilewidth")?,
+        tile_height: attr(&root, "tileheight")?,
+        infinite: attr_or(&root, "infinite", 0)? == 1,
+        ..Default::default()
+    };
+    for child in root.children().filter(Node::is_element) {
+        match child.tag_name().name() {
+            "tileset" => {
+                let first_gid = attr(&child, "firstgid")?;
+                let tileset = if let Some(source) = child.attribute("source") {
+                    Tileset {
+                        source: Some(source.to_string()),
+                        ..Default::default()
+                    }
+                } else {
+                    parse_tileset_node(&child)?
+                };
+                map.tilesets.push(Tileset {
+                    first_gid,
+                    ..tileset
+                });
+            }
+            "properties" => map.properties = parse_properties(&child)?,
+            _ => {
+                if let Some(layer) = parse_layer(&child)? {
+                    map.layers.push(layer);
+                }
+            }
+        }
+    }
+    Ok(map)
+}
+
+pub fn parse_tileset(content: &str) -> Result<Tileset> {
+    let document = Document::parse(content)?;
+    let root = document.root_element();
+    if !root.has_tag_name("tileset") {
+        return Err(format!("expect <tileset> but found <{}>", root.tag_name().name()).into());
+    }
+    parse_tileset_node(&root)
+}
+
+fn parse_tileset_node(node: &Node) -> Result<Tileset> {
+    let mut tileset = Tileset {
+        name: node.attribute("name").unwrap_or_default().to_string(),
+        tile_width: attr(node, "tilewidth")?,
+        tile_height: attr(node, "tileheight")?,
+        tile_count: attr_or(node, "tilecount", 0)?,
+        columns: attr_or(node, "columns", 0)?,
+        margin: attr_or(node, "margin", 0)?,
+        spacing: attr_or(node, "spacing", 0)?,
+        ..Default::default()
+    };
+    for child in node.children().filter(Node::is_element) {
+        match child.tag_name().name() {
+            "image" => {
+                tileset.image = child.attribute("source").unwrap_or_default().to_string();
+                tileset.image_width = attr_or(&child, "width", 0)?;
+                tileset.image_height = attr_or(&child, "height", 0)?;
+            }
+            "properties" => tileset.properties = parse_properties(&child)?,
+            "tile" => tileset.tiles.push(parse_tile(&child)?),
+            _ => {}
+        }
+    }
+    Ok(tileset)
+}
+
+fn parse_tile(node: &Node) -> Result<TileDefinition> {
+    let mut tile = TileDefinition {
+        id: attr(node, "id")?,
+        ..Default::default()
+    };
+    for child in node.children().filter(Node::is_element) {
+        match child.tag_name().name() {
+            "animation" => {
+                for frame in child.children().filter(|c| c.has_tag_name("frame")) {
+                    tile.animation.push(Frame {
+                        tile_id: attr(&frame, "tileid")?,
+                        duration: attr(&frame, "duration")?,
+                    });
+                }
+            }
+            "properties" => tile.properties = parse_properties(&child)?,
+            _ => {}
+        }
+    }
+    Ok(tile)
+}
+
+/// 不是 layer 的节点返回 None
+fn parse_layer(node: &Node) -> Result<Option<Layer>> {
+    let type_ = match node.tag_name().name() {
+        "layer" => LayerType::TileLayer,
+        "objectgroup" => LayerType::ObjectGroup,
+        "imagelayer" => LayerType::ImageLayer,
+        "group" => LayerType::Group,
+        _ => return Ok(None),
+    };
+    let mut layer = Layer {
+        name: node.attribute("name").unwrap_or_default().to_string(),
+        type_,
+        width: attr_or(node, "width", 0)?,
+        height: attr_or(node, "height", 0)?,
+        visible: attr_or(node, "visible", 1)? == 1,
+        data: vec![],
+        objects: vec![],
+        layers: vec![],
+        properties: vec![],
+    };
+    for child in node.children().filter(Node::is_element) {
+        match child.tag_name().name() {
+            "properties" => layer.properties = parse_properties(&child)?,
+            "data" => layer.data = parse_data(&child)?,
+            "object" => layer.objects.push(parse_object(&child)?),
+            _ => {
+                if let Some(sub_layer) = parse_layer(&child)? {
+                    layer.layers.push(sub_layer);
+                }
+            }
+        }
+    }
+    Ok(Some(layer))
+}
+
+fn parse_data(node: &Node) -> Result<Vec<u32>> {
+    match (node.attribute("encoding"), node.attribute("compression")) {
+        (Some("csv"), None) => node
+            .text()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|gid| !gid.is_empty())
+            .map(|gid| Ok(gid.parse::<u32>()?))
+            .collect(),
+        (None, None) => node
+            .children()
+            .filter(|child| child.has_tag_name("tile"))
+            .map(|tile| attr_or(&tile, "gid", 0))
+            .collect(),
+        (encoding, compression) => Err(format!(
+            "unsupported layer data encoding {encoding:?} with compression {compression:?}, please use csv"
+        )
+        .into()),
+    }
+}
+
+fn parse_object(node: &Node) -> Result<Object> {
+    let mut object = Object {
+        id: attr_or(node, "id", 0)?,
+        name: node.attribute("name").unwrap_or_default().to_string(),
+        type_: node
+            .attribute("type")
+            .or(node.attribute("class"))
+            .unwrap_or_default()
+            .to_string(),
+        x: attr_or(node, "x", 0.0)?,
+        y: attr_or(node, "y", 0.0)?,
+        width: attr_or(node, "width", 0.0)?,
+        height: attr_or(node, "height", 0.0)?,
+        gid: node.attribute("gid").map(str::parse).transpose()?,
+        ..Default::default()
+    };
+    for child in node.children().filter(Node::is_element) {
+        match child.tag_name().name() {
+            "point" => object.point = true,
+            "properties" => object.properties = parse_properties(&child)?,
+            _ => {}
+        }
+    }
+    Ok(object)
+}
+
+fn parse_properties(node: &Node) -> Result<Vec<Property>> {
+    let mut properties = vec![];
+    for child in node
+        .children()
+        .filter(|child| child.has_tag_name("property"))
+    {
+        let type_ = match child.attribute("type").unwrap_or("string") {
+            "int" => PropertyType::Int,
+            "float" => PropertyType::Float,
+            "bool" => PropertyType::Bool,
+            "color" => PropertyType::Color,
+            "file" => PropertyType::File,
+            "object" => PropertyType::Object,
+            "class" => PropertyType::Class,
+            _ => PropertyType::String,
+        };
+        // 多行的字符串会直接写在节点内容中
+        let raw = child
+            .attribute("value")
+            .or(child.text())
+            .unwrap_or_default();
+        let value = match type_ {
+            PropertyType::Int | PropertyType::Object => {
+                serde_json::Value::from(raw.parse::<i64>()?)
+            }
+            PropertyType::Float => serde_json::Value::from(raw.parse::<f64>()?),
+            PropertyType::Bool => serde_json::Value::from(raw == "true"),
+            PropertyType::Class => serde_json::Value::Object(
+                child
+                    .children()
+                    .filter(|c| c.has_tag_name("properties"))
+                    .flat_map(|c| parse_properties(&c).unwrap_or_default())
+                    .map(|property| (property.name, property.value))
+                    .collect(),
+            ),
+            _ => serde_json::Value::from(raw),
+        };
+        properties.push(Property {
+            name: child.attribute("name").unwrap_or_default().to_string(),
+            type_,
+            value,
+        });
+    }
+    Ok(properties)
+}
+
+fn attr<T>(node: &Node, name: &str) -> Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::error::Error + 'static,
+{
+    match node.attribute(name) {
+        Some(value) => Ok(value.parse::<T>()?),
+        None => Err(format!("missing attribute {name} in <{}>", node.tag_name().name()).into()),
+    }
+}
+
+fn attr_or<T>(node: &Node, name: &str, default: T) -> Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::error::Error + 'static,
+{
+    match node.attribute(name) {
+        Some(value) => Ok(value.parse::<T>()?),
+        None => Ok(default),
+    }
+}
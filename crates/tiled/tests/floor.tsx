@@ -0,0 +1,10 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" tiledversion="1.10.1" name="Floor" tilewidth="16" tileheight="16" tilecount="66" columns="6">
+ <image source="../art/floor.png" width="96" height="176"/>
+ <tile id="7">
+  <animation>
+   <frame tileid="7" duration="200"/>
+   <frame tileid="8" duration="200"/>
+  </animation>
+ </tile>
+</tileset>
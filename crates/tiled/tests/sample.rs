@@ -0,0 +1,65 @@
+use tiled::{LayerType, Map, PropertyValue, World};
+
+fn check_map(map: &Map) {
+    assert_eq!((map.width, map.height), (4, 3));
+    assert_eq!(
+        map.get_property("name"),
+        Some(PropertyValue::String(String::from("Sample_Room")))
+    );
+    // 外部的 tileset 会被加载进来，并且 image 路径相对 map 所在目录
+    assert_eq!(map.tilesets[0].name, "Floor");
+    assert_eq!(map.tilesets[0].image, "../art/floor.png");
+    assert_eq!(map.tilesets[0].first_gid, 1);
+    assert_eq!(map.tilesets[1].rows(), 2);
+
+    let floor = &map.layers[1];
+    assert!(matches!(floor.type_, LayerType::TileLayer));
+    let tiles = floor.tiles().collect::<Vec<_>>();
+    assert_eq!(tiles.len(), 8);
+    assert_eq!(tiles[2], (2, 1, 8));
+    // 翻转的 tile 也能找到对应的 tileset
+    let (tileset, id) = map.find_tileset(tiles[7].2).unwrap();
+    assert_eq!((tileset.name.as_str(), id), ("Floor", 1));
+    let (tileset, id) = map.find_tileset(8).unwrap();
+    assert_eq!(tileset.tile_src(id), [16, 16]);
+    // tile 动画在 tsx 和内嵌的 tileset 中都能读取到
+    assert_eq!(tileset.tiles[0].id, 7);
+    assert_eq!(tileset.tiles[0].animation.len(), 2);
+    assert_eq!(map.tilesets[1].tiles[0].animation[1].tile_id, 1);
+    assert_eq!(map.tilesets[1].tiles[0].animation[1].duration, 100);
+
+    let entity = &map.layers[2];
+    assert!(matches!(entity.type_, LayerType::ObjectGroup));
+    let light = &entity.objects[0];
+    assert_eq!(light.identifier(), "Light");
+    assert_eq!(
+        light.get_property("Color"),
+        Some(PropertyValue::Color {
+            r: 255,
+            g: 136,
+            b: 0,
+            a: 255
+        })
+    );
+    assert_eq!(light.get_property("Alpha"), Some(PropertyValue::Int(200)));
+    assert_eq!(light.get_property("Inner"), Some(PropertyValue::Bool(true)));
+    assert_eq!(entity.objects[1].identifier(), "BrithPoint");
+}
+
+#[test]
+fn check_tmx() {
+    check_map(&Map::from("tests/sample.tmx").unwrap());
+}
+
+#[test]
+fn check_tmj() {
+    check_map(&Map::from("tests/sample.tmj").unwrap());
+}
+
+#[test]
+fn check_world() {
+    let world = World::from("tests/sample.world").unwrap();
+    assert_eq!(world.maps.len(), 2);
+    assert_eq!(world.maps[1].x, 64);
+    assert_eq!(world.maps[1].map.tilesets[1].image, "wall.png");
+}
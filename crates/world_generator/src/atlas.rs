@@ -0,0 +1,172 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use image::{GenericImage, GenericImageView, RgbaImage};
+
+use crate::model::{LevelModel, TileAtlas};
+
+/// 每个 tile 四周复制出来的边缘像素宽度
+pub const ATLAS_PADDING: u32 = 1;
+
+impl LevelModel {
+    /// 把每个 tileset 中被 level 用到的 tile 打包成一张 atlas
+    ///
+    /// tileset 的图片从 `assets_dir` 中读取，atlas 保存到 `assets_dir/atlas_dir/{name}.png`，
+    /// name 由 tileset 图片的路径得到，同样的输入每次生成的文件名都一样，
+    /// 写入前会删除 `atlas_dir` 中之前生成的 png，
+    /// 内容相同的 tile 只会保存一份，没有被用到的 tileset 不会生成 atlas
+    pub fn pack_atlas<P: AsRef<Path>>(
+        &mut self,
+        assets_dir: P,
+        atlas_dir: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let assets_dir = assets_dir.as_ref();
+        let output_dir = assets_dir.join(atlas_dir);
+        std::fs::create_dir_all(&output_dir)?;
+        for entry in std::fs::read_dir(&output_dir)? {
+            let path = entry?.path();
+            if path.extension().filter(|ext| *ext == "png").is_some() {
+                std::fs::remove_file(path)?;
+            }
+        }
+        let mut names = HashSet::new();
+        let mut used_tiles = self.used_tiles();
+        // 动态 tile 的每一帧也需要放进 atlas
+        for tileset in self.tilesets.iter() {
+            if let Some(used) = used_tiles.get_mut(&tileset.uuid) {
+                let frames = used
+                    .iter()
+                    .filter_map(|index| tileset.animations.get(index))
+                    .flat_map(|animation| animation.frames.iter().map(|frame| frame.index))
+                    .collect::<Vec<_>>();
+                used.extend(frames);
+            }
+        }
+        for tileset in self.tilesets.iter_mut() {
+            let Some(used) = used_tiles.get(&tileset.uuid) else {
+                tileset.atlas = None;
+                continue;
+            };
+            let mut used = used.iter().copied().collect::<Vec<_>>();
+            used.sort();
+            let source = image::open(assets_dir.join(&tileset.src))?.into_rgba8();
+
+            // 按照内容去重
+            let mut slot_images: Vec<RgbaImage> = vec![];
+            let mut slot_by_pixels: HashMap<Vec<u8>, u16> = HashMap::new();
+            let mut remap = HashMap::new();
+            for index in used {
+                let Some((min, size)) = tileset.tiles.get(&index) else {
+                    continue;
+                };
+                if min[0] + size[0] > source.width() || min[1] + size[1] > source.height() {
+                    return Err(format!(
+                        "tile {index} of {} is out of the image bounds",
+                        tileset.src
+                    )
+                    .into());
+                }
+                let tile = source.view(min[0], min[1], size[0], size[1]).to_image();
+                let slot = *slot_by_pixels
+                    .entry(tile.as_raw().clone())
+                    .or_insert_with(|| {
+                        slot_images.push(tile);
+                        (slot_images.len() - 1) as u16
+                    });
+                remap.insert(index, slot);
+            }
+            if slot_images.is_empty() {
+                tileset.atlas = None;
+                continue;
+            }
+
+            // 同一个 tileset 中的 tile 尺寸都是一样的，按照接近正方形的网格排列
+            let tile_size = [slot_images[0].width(), slot_images[0].height()];
+            let cell = [
+                tile_size[0] + ATLAS_PADDING * 2,
+                tile_size[1] + ATLAS_PADDING * 2,
+            ];
+            let columns = (slot_images.len() as f32).sqrt().ceil() as u32;
+            let rows = (slot_images.len() as f32 / columns as f32).ceil() as u32;
+            let mut atlas = RgbaImage::new(columns * cell[0], rows * cell[1]);
+            let mut slots = vec![];
+            for (i, tile) in slot_images.iter().enumerate() {
+                let min = [
+                    (i as u32 % columns) * cell[0] + ATLAS_PADDING,
+                    (i as u32 / columns) * cell[1] + ATLAS_PADDING,
+                ];
+                atlas.copy_from(tile, min[0], min[1])?;
+                extrude(&mut atlas, min, tile_size);
+                slots.push(min);
+            }
+
+            let name = atlas_name(&tileset.src, &mut names);
+            let src = format!("{}/{name}.png", atlas_dir.trim_end_matches('/'));
+            atlas.save(assets_dir.join(&src))?;
+            tileset.atlas = Some(TileAtlas {
+                src,
+                size: [atlas.width(), atlas.height()],
+                padding: ATLAS_PADDING,
+                remap,
+                slots,
+            });
+        }
+        Ok(())
+    }
+    /// 所有 room 中用到的 tile，根据 tileset 的 uuid 分组
+    fn used_tiles(&self) -> HashMap<String, HashSet<u8>> {
+        let mut used: HashMap<String, HashSet<u8>> = HashMap::new();
+        for room in self.rooms.iter() {
+            for tile_group in room
+                .floors
+                .iter()
+                .chain(room.walls.iter())
+                .chain(room.roofs.iter())
+            {
+                let indexes = used.entry(tile_group.tileset_uuid.clone()).or_default();
+                for col in tile_group.tiles.values() {
+                    indexes.extend(col.values());
+                }
+            }
+        }
+        used
+    }
+}
+
+/// 把 tileset 图片的路径转换成文件名，比如 `art/floor.png` 是 `art_floor`，
+/// 多个 tileset 使用同一张图片时依次加上 `_1`、`_2` 后缀
+fn atlas_name(src: &str, names: &mut HashSet<String>) -> String {
+    let stem = src.strip_suffix(".png").unwrap_or(src);
+    let base = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    let mut name = base.clone();
+    let mut suffix = 0;
+    while !names.insert(name.clone()) {
+        suffix += 1;
+        name = format!("{base}_{suffix}");
+    }
+    name
+}
+
+/// 把 tile 最外一圈的像素向外复制 [`ATLAS_PADDING`] 像素
+fn extrude(atlas: &mut RgbaImage, min: [u32; 2], size: [u32; 2]) {
+    let [x0, y0] = min;
+    let [x1, y1] = [min[0] + size[0] - 1, min[1] + size[1] - 1];
+    for p in 1..=ATLAS_PADDING {
+        for x in x0 - p..=x1 + p {
+            let clamped_x = x.clamp(x0, x1);
+            let top = *atlas.get_pixel(clamped_x, y0);
+            let bottom = *atlas.get_pixel(clamped_x, y1);
+            atlas.put_pixel(x, y0 - p, top);
+            atlas.put_pixel(x, y1 + p, bottom);
+        }
+        for y in y0..=y1 {
+            let left = *atlas.get_pixel(x0, y);
+            let right = *atlas.get_pixel(x1, y);
+            atlas.put_pixel(x0 - p, y, left);
+            atlas.put_pixel(x1 + p, y, right);
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use ldtk::{EntityInstance, FieldError, FromEntity};
+
+use crate::convert::{
+    entity_grid_pos, find_tile_index, flip_grid_y, new_animation, new_light, new_tileset,
+    room_grid_offset, EntityRole, LayerRole, GRID_SIZE,
+};
+use crate::{CollisionShape, LevelModel, RoomModel, TileGroup};
+
+/// ldtk 中 tile 的 custom data 按照 json 解析，比如
+/// `{"animation": [{"tileId": 12, "duration": 150}, {"tileId": 13, "duration": 150}], "collision": "full"}`
+/// 其中 duration 的单位是毫秒，collision 的写法见 [`CollisionShape`]，不是 json 的 custom data 会被忽略
+#[derive(serde::Deserialize, Default)]
+struct CustomData {
+    #[serde(default)]
+    animation: Vec<CustomFrame>,
+    #[serde(default)]
+    collision: Option<CollisionShape>,
+}
+
+#[derive(serde::Deserialize)]
+struct CustomFrame {
+    #[serde(rename = "tileId")]
+    tile_id: u32,
+    duration: u32,
+}
+
+/// `Light` entity 的 field
+struct LightFields {
+    color: [u8; 3],
+    alpha: u8,
+    inner: bool,
+}
+
+impl FromEntity for LightFields {
+    fn from_entity(entity: &EntityInstance) -> Result<Self, FieldError> {
+        Ok(Self {
+            color: entity.field("Color")?,
+            alpha: entity.field("Alpha")?,
+            inner: entity.field("Inner")?,
+        })
+    }
+}
+
+impl LevelModel {
+    /// 将 ldtk 的 project 转换成 level，其中每个 ldtk level 对应一个 room
+    ///
+    /// 保存在外部文件中的 level 需要先通过 [`ldtk::Project::from`] 加载进来
+    ///
+    /// 所有 world 和 depth 中的 level 都放在同一个坐标空间中，有重叠的 level 时返回错误
+    pub fn from_ldtk(project: &ldtk::Project) -> Result<Self, Box<dyn std::error::Error>> {
+        check_overlapping_levels(project)?;
+        let mut level_model = LevelModel::default();
+        // 将ldtk中的tileset定义提取转化成需要的结构
+        let mut tilesets_ref = HashMap::new();
+        for tileset_def in project.defs.tilesets.iter() {
+            let uuid = Uuid::new_v4().to_string();
+            tilesets_ref.insert(tileset_def.uid, level_model.tilesets.len());
+            let mut tileset = new_tileset(
+                &tileset_def.identifier,
+                &uuid,
+                &tileset_def.rel_path,
+                tileset_def.tile_grid_size,
+                tileset_def.c_wid,
+                tileset_def.c_hei,
+            );
+            for enum_tag in tileset_def.enum_tags.iter() {
+                for tile_id in enum_tag.tile_ids.iter() {
+                    if let Some(index) = find_tile_index(&tileset, tileset_def.tile_src(*tile_id)) {
+                        tileset
+                            .tags
+                            .entry(index)
+                            .or_default()
+                            .push(enum_tag.enum_value_id.clone());
+                    }
+                }
+            }
+            // tile 的动画和碰撞形状配置在 ldtk 的 custom data 中
+            for custom_data in tileset_def.custom_data.iter() {
+                let Some(index) =
+                    find_tile_index(&tileset, tileset_def.tile_src(custom_data.tile_id))
+                else {
+                    continue;
+                };
+                tileset.custom_data.insert(index, custom_data.data.clone());
+                let Ok(data) = serde_json::from_str::<CustomData>(&custom_data.data) else {
+                    continue;
+                };
+                if let Some(collision) = data.collision {
+                    tileset.collisions.insert(index, collision);
+                }
+                let frames = data
+                    .animation
+                    .iter()
+                    .map(|frame| (tileset_def.tile_src(frame.tile_id), frame.duration))
+                    .collect::<Vec<_>>();
+                if let Some(animation) = new_animation(&tileset, &frames) {
+                    tileset.animations.insert(index, animation);
+                }
+            }
+            level_model.tilesets.push(tileset);
+        }
+        // 处理得到layer直接关联的tileset的关系
+        let layer_to_tileset = project
+            .defs
+            .layers
+            .iter()
+            .filter_map(|layer| {
+                let tileset = tilesets_ref.get(&layer.tileset_def_uid?)?;
+                Some((layer.uid, *tileset))
+            })
+            .collect::<HashMap<usize, usize>>();
+        // 转换所有 world 中的 room，多个 world 的 level 会放在同一个 level model 中，前面已经确认没有重叠
+        for level in project.all_levels() {
+            let size = [level.px_wid, level.px_hei];
+            let grid_size = [size[0] / GRID_SIZE, size[1] / GRID_SIZE];
+            let grid_offset = room_grid_offset([level.world_x, level.world_y], grid_size);
+            let mut room = RoomModel {
+                display_name: level.identifier.clone(),
+                world_pos: grid_offset,
+                size: grid_size,
+                ..Default::default()
+            };
+            for layer in level.layer_instances.iter() {
+                match LayerRole::from_identifier(&layer.identifier) {
+                    Some(LayerRole::Entity) => {
+                        for entity in layer.entity_instances.iter() {
+                            let grid_pos = entity_grid_pos([entity.px.0, entity.px.1], size[1]);
+                            match EntityRole::from_identifier(&entity.identifier) {
+                                Some(EntityRole::Light) => {
+                                    let light = LightFields::from_entity(entity)?;
+                                    room.lights.push(new_light(
+                                        grid_pos,
+                                        light.color,
+                                        light.alpha,
+                                        light.inner,
+                                    ));
+                                }
+                                Some(EntityRole::BrithPoint) => {
+                                    level_model.brith_point = [
+                                        grid_pos[0] as i32 + grid_offset[0],
+                                        grid_pos[1] as i32 + grid_offset[1],
+                                    ];
+                                }
+                                None => {}
+                            }
+                        }
+                    }
+                    Some(role) => {
+                        if let Some(tileset) = layer_to_tileset.get(&layer.layer_def_uid) {
+                            let used_tileset = &level_model.tilesets[*tileset];
+                            let mut tile_group = TileGroup {
+                                tileset_uuid: used_tileset.uuid.clone(),
+                                ..Default::default()
+                            };
+                            for tile in layer.tiles() {
+                                if let Some(index) = find_tile_index(used_tileset, tile.src) {
+                                    let grid_x = tile.px[0] / GRID_SIZE;
+                                    let grid_y = flip_grid_y(tile.px[1] / GRID_SIZE, grid_size[1]);
+                                    tile_group.insert(grid_x, grid_y, index);
+                                }
+                            }
+                            room.push_tile_group(role, tile_group);
+                        }
+                    }
+                    None => {}
+                }
+            }
+            level_model.rooms.push(room);
+        }
+        Ok(level_model)
+    }
+}
+
+/// level 在 project 中的位置，用于报错
+fn level_location(world: Option<&ldtk::World>, level: &ldtk::Level) -> String {
+    match world {
+        Some(world) => format!(
+            "{}/{} (depth {})",
+            world.identifier, level.identifier, level.world_depth
+        ),
+        None => format!("{} (depth {})", level.identifier, level.world_depth),
+    }
+}
+
+/// 不同 world 或者不同 depth 的 level 在 ldtk 中可以使用相同的坐标，
+/// 转换成 [`LevelModel`] 之后会放在同一个平面上互相重叠，所以直接返回错误
+fn check_overlapping_levels(project: &ldtk::Project) -> Result<(), Box<dyn std::error::Error>> {
+    let levels = project
+        .levels
+        .iter()
+        .map(|level| (None, level))
+        .chain(
+            project
+                .worlds
+                .iter()
+                .flat_map(|world| world.levels.iter().map(move |level| (Some(world), level))),
+        )
+        .collect::<Vec<_>>();
+    for (i, (world_a, a)) in levels.iter().enumerate() {
+        for (world_b, b) in levels.iter().skip(i + 1) {
+            let overlap = a.world_x < b.world_x + b.px_wid as i32
+                && b.world_x < a.world_x + a.px_wid as i32
+                && a.world_y < b.world_y + b.px_hei as i32
+                && b.world_y < a.world_y + a.px_hei as i32;
+            if overlap {
+                return Err(format!(
+                    "level {} overlaps level {}, convert them into different levels",
+                    level_location(*world_a, a),
+                    level_location(*world_b, b)
+                )
+                .into());
+            }
+        }
+    }
+    Ok(())
+}
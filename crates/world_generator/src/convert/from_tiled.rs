@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use tiled::{LayerType, PropertyValue};
+
+use crate::convert::{
+    entity_grid_pos, find_tile_index, flip_grid_y, new_animation, new_light, new_tileset,
+    room_grid_offset, EntityRole, LayerRole, GRID_SIZE,
+};
+use crate::{CollisionShape, LevelModel, RoomModel, TileGroup};
+
+impl LevelModel {
+    /// 将 tiled 的 world 转换成 level，其中每个 map 对应一个 room
+    ///
+    /// light 的颜色、透明度和是否在室内分别读取 `Color`、`Alpha`、`Inner` 这几个自定义属性
+    ///
+    /// 不支持翻转和旋转过的 tile，遇到时给出警告并按照没有翻转处理
+    pub fn from_tiled(world: &tiled::World) -> Self {
+        let mut level_model = LevelModel::default();
+        // 不同的 map 引用同一个 tileset 时共用同一个 uuid
+        let mut tilesets_ref = HashMap::new();
+        for world_map in world.maps.iter() {
+            let map = &world_map.map;
+            for tileset in map.tilesets.iter() {
+                let key = (tileset.name.clone(), tileset.image.clone());
+                if tilesets_ref.contains_key(&key) {
+                    continue;
+                }
+                let uuid = Uuid::new_v4().to_string();
+                tilesets_ref.insert(key, level_model.tilesets.len());
+                let mut used_tileset = new_tileset(
+                    &tileset.name,
+                    &uuid,
+                    &tileset.image,
+                    tileset.tile_width,
+                    tileset.columns,
+                    tileset.rows(),
+                )
+                .with_spacing(tileset.margin, tileset.spacing);
+                for tile in tileset.tiles.iter() {
+                    let Some(index) = find_tile_index(&used_tileset, tileset.tile_src(tile.id))
+                    else {
+                        continue;
+                    };
+                    // 碰撞形状读取 tile 的 `collision` 属性，写法见 [`CollisionShape`]
+                    if let Some(PropertyValue::String(collision)) = tile.get_property("collision") {
+                        if let Some(collision) = CollisionShape::parse(&collision) {
+                            used_tileset.collisions.insert(index, collision);
+                        }
+                    }
+                    let frames = tile
+                        .animation
+                        .iter()
+                        .map(|frame| (tileset.tile_src(frame.tile_id), frame.duration))
+                        .collect::<Vec<_>>();
+                    if let Some(animation) = new_animation(&used_tileset, &frames) {
+                        used_tileset.animations.insert(index, animation);
+                    }
+                }
+                level_model.tilesets.push(used_tileset);
+            }
+        }
+        for world_map in world.maps.iter() {
+            let map = &world_map.map;
+            let size = [map.width * map.tile_width, map.height * map.tile_height];
+            let grid_size = [size[0] / GRID_SIZE, size[1] / GRID_SIZE];
+            let grid_offset = room_grid_offset([world_map.x, world_map.y], grid_size);
+            let display_name = match map.get_property("name") {
+                Some(PropertyValue::String(name)) => name,
+                _ => std::path::Path::new(&world_map.file_name)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            };
+            let mut room = RoomModel {
+                display_name,
+                world_pos: grid_offset,
+                size: grid_size,
+                ..Default::default()
+            };
+            let mut layers = vec![];
+            flatten_layers(&map.layers, None, &mut layers);
+            for (role, layer) in layers {
+                match (role, &layer.type_) {
+                    (LayerRole::Entity, LayerType::ObjectGroup) => {
+                        for object in layer.objects.iter() {
+                            let grid_pos = entity_grid_pos(
+                                [object.x.max(0.0) as u32, object.y.max(0.0) as u32],
+                                size[1],
+                            );
+                            match EntityRole::from_identifier(object.identifier()) {
+                                Some(EntityRole::Light) => {
+                                    let color = match object.get_property("Color") {
+                                        Some(PropertyValue::Color { r, g, b, .. }) => [r, g, b],
+                                        _ => [255, 255, 255],
+                                    };
+                                    let alpha = match object.get_property("Alpha") {
+                                        Some(PropertyValue::Int(alpha)) => alpha as u8,
+                                        _ => 255,
+                                    };
+                                    let inner = matches!(
+                                        object.get_property("Inner"),
+                                        Some(PropertyValue::Bool(true))
+                                    );
+                                    room.lights.push(new_light(grid_pos, color, alpha, inner));
+                                }
+                                Some(EntityRole::BrithPoint) => {
+                                    level_model.brith_point = [
+                                        grid_pos[0] as i32 + grid_offset[0],
+                                        grid_pos[1] as i32 + grid_offset[1],
+                                    ];
+                                }
+                                None => {}
+                            }
+                        }
+                    }
+                    (role, LayerType::TileLayer) => {
+                        // tiled 的一个 layer 可以使用多个 tileset，每个 tileset 单独一个 group
+                        let mut tile_groups: HashMap<usize, TileGroup> = HashMap::new();
+                        for (x, y, gid) in layer.tiles() {
+                            if gid & tiled::FLIPPED_FLAGS != 0 {
+                                eprintln!(
+                                    "warning: tile ({x}, {y}) of layer {} in map {} is flipped or rotated, which is not supported and will be ignored",
+                                    layer.name, world_map.file_name
+                                );
+                            }
+                            let Some((tileset, id)) = map.find_tileset(gid) else {
+                                continue;
+                            };
+                            let key = (tileset.name.clone(), tileset.image.clone());
+                            let Some(used) = tilesets_ref.get(&key) else {
+                                continue;
+                            };
+                            let used_tileset = &level_model.tilesets[*used];
+                            let src = tileset.tile_src(id);
+                            if let Some(index) = find_tile_index(used_tileset, src) {
+                                // 和 ldtk 一样，这里假设 map 的 tile 尺寸就是 GRID_SIZE
+                                let grid_x = x * map.tile_width / GRID_SIZE;
+                                let grid_y = y * map.tile_height / GRID_SIZE;
+                                let grid_y = flip_grid_y(grid_y, grid_size[1]);
+                                tile_groups
+                                    .entry(*used)
+                                    .or_insert_with(|| TileGroup {
+                                        tileset_uuid: used_tileset.uuid.clone(),
+                                        ..Default::default()
+                                    })
+                                    .insert(grid_x, grid_y, index);
+                            }
+                        }
+                        let mut tile_groups = tile_groups.into_iter().collect::<Vec<_>>();
+                        tile_groups.sort_by_key(|(used, _)| *used);
+                        for (_, tile_group) in tile_groups {
+                            room.push_tile_group(role, tile_group);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            level_model.rooms.push(room);
+        }
+        level_model
+    }
+}
+
+/// 展开 group layer，没有约定名称的 layer 沿用所在 group 的用途
+fn flatten_layers<'a>(
+    layers: &'a [tiled::Layer],
+    parent: Option<LayerRole>,
+    result: &mut Vec<(LayerRole, &'a tiled::Layer)>,
+) {
+    for layer in layers.iter().filter(|layer| layer.visible) {
+        let role = LayerRole::from_identifier(&layer.name).or(parent);
+        match layer.type_ {
+            LayerType::Group => flatten_layers(&layer.layers, role, result),
+            _ => {
+                if let Some(role) = role {
+                    result.push((role, layer));
+                }
+            }
+        }
+    }
+}
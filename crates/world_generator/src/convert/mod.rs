@@ -0,0 +1,147 @@
+//! 把编辑器导出的关卡转换成 [`LevelModel`]
+//!
+//! 不管是 ldtk 还是 tiled，layer 和 entity 都按照同一套命名约定来区分用途：
+//! - 名为 `Floor`/`Wall`/`Roof` 的 tile layer 分别对应 room 的地板、墙壁和天花板，
+//!   同一用途有多个 layer 时可以加上数字后缀，比如 `Floor_1`
+//! - 名为 `Entity` 的 layer 中，`Light` 是灯光，`BrithPoint` 是出生点
+//! - 名为 `Wall` 的 tileset 中的 tile 是倾斜显示的，高度是两个 cell
+
+use crate::{Light, RoomModel, TileAnimation, TileFrame, TileGroup, Tileset};
+
+pub use profile::{ConversionProfile, ProfileAnimation};
+
+mod from_ldtk;
+mod from_tiled;
+mod profile;
+mod to_ldtk;
+
+/// 关卡中一个 grid 的像素尺寸
+pub const GRID_SIZE: u32 = 16;
+
+/// layer 在 room 中的用途
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum LayerRole {
+    Entity,
+    Floor,
+    Wall,
+    Roof,
+}
+
+impl LayerRole {
+    pub fn from_identifier(identifier: &str) -> Option<Self> {
+        let identifier = match identifier.rsplit_once('_') {
+            Some((role, suffix)) if suffix.parse::<u32>().is_ok() => role,
+            _ => identifier,
+        };
+        match identifier {
+            "Entity" => Some(Self::Entity),
+            "Floor" => Some(Self::Floor),
+            "Wall" => Some(Self::Wall),
+            "Roof" => Some(Self::Roof),
+            _ => None,
+        }
+    }
+}
+
+/// entity 的用途
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum EntityRole {
+    Light,
+    BrithPoint,
+}
+
+impl EntityRole {
+    pub fn from_identifier(identifier: &str) -> Option<Self> {
+        match identifier {
+            "Light" => Some(Self::Light),
+            "BrithPoint" => Some(Self::BrithPoint),
+            _ => None,
+        }
+    }
+}
+
+/// 根据 tileset 的名称创建 [`Tileset`]，针对 wall 做一下特殊处理
+fn new_tileset(
+    identifier: &str,
+    uuid: &str,
+    src: &str,
+    tile_size: u32,
+    columns: u32,
+    rows: u32,
+) -> Tileset {
+    match identifier {
+        "Wall" => Tileset::from_tilt_grid(uuid, src, tile_size, columns, rows),
+        _ => Tileset::from_grid(uuid, src, tile_size, columns, rows),
+    }
+}
+
+/// 根据 tile 在 tileset 图片中的位置找到对应的 index
+fn find_tile_index(tileset: &Tileset, src: [u32; 2]) -> Option<u8> {
+    tileset
+        .tiles
+        .iter()
+        .find(|(_, rect)| rect.0 == src)
+        .map(|(index, _)| *index)
+}
+
+/// 动画的每一帧用 tile 在 tileset 图片中的位置和持续的毫秒数描述，有找不到的 tile 时返回 None
+fn new_animation(tileset: &Tileset, frames: &[([u32; 2], u32)]) -> Option<TileAnimation> {
+    if frames.is_empty() {
+        return None;
+    }
+    let frames = frames
+        .iter()
+        .map(|(src, duration)| {
+            Some(TileFrame {
+                index: find_tile_index(tileset, *src)?,
+                duration: *duration as f32 / 1000.0,
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(TileAnimation { frames })
+}
+
+/// 编辑器中的 y 轴是向下的，room 的位置需要转换成以左下角为原点的 grid 坐标
+fn room_grid_offset(world_px: [i32; 2], grid_size: [u32; 2]) -> [i32; 2] {
+    [
+        world_px[0] / GRID_SIZE as i32,
+        (-world_px[1] / GRID_SIZE as i32) - grid_size[1] as i32,
+    ]
+}
+
+/// entity 在编辑器中的像素位置转换成 room 内以左下角为原点的 grid 坐标
+fn entity_grid_pos(px: [u32; 2], room_px_height: u32) -> [u32; 2] {
+    let y = room_px_height.saturating_sub(px[1]);
+    [px[0] / GRID_SIZE, y / GRID_SIZE]
+}
+
+/// tile 在编辑器中的 grid 坐标是从上往下数的，转换成从下往上数
+///
+/// 比如room高5行，当前是从上往下数第4行，grid_y就是3
+/// 那么转化后就会变成从下往上数第2行，grid_y就是2
+fn flip_grid_y(grid_y: u32, room_grid_height: u32) -> u32 {
+    room_grid_height - grid_y - 1
+}
+
+fn new_light(grid_pos: [u32; 2], color: [u8; 3], alpha: u8, inner: bool) -> Light {
+    Light {
+        // TODO 这里的问题是如何让灯光低于roof但是能够让光扩散的足够开
+        pos: [grid_pos[0], grid_pos[1], if inner { 32 } else { 0 }],
+        color: [color[0], color[1], color[2], alpha],
+    }
+}
+
+impl RoomModel {
+    /// 按照 layer 的用途放入 room，空的 tile group 会被忽略
+    fn push_tile_group(&mut self, role: LayerRole, tile_group: TileGroup) {
+        if tile_group.tiles.is_empty() {
+            return;
+        }
+        match role {
+            LayerRole::Floor => self.floors.push(tile_group),
+            LayerRole::Wall => self.walls.push(tile_group),
+            LayerRole::Roof => self.roofs.push(tile_group),
+            LayerRole::Entity => {}
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+
+use crate::{CollisionShape, LevelModel, TileAnimation, TileFrame};
+
+/// 转换关卡时额外的配置，用来补充编辑器中不方便配置的内容
+///
+/// ```json
+/// {
+///   "animations": {
+///     "art/floor.png": [
+///       { "tile": 3, "frames": [{ "index": 3, "duration": 0.15 }, { "index": 4, "duration": 0.15 }] }
+///     ]
+///   },
+///   "collisions": {
+///     "art/wall.png": { "0": { "half": "bottom" }, "1": "none" }
+///   }
+/// }
+/// ```
+#[derive(serde::Serialize, serde::Deserialize, Default, Debug)]
+pub struct ConversionProfile {
+    /// key 是 tileset 图片的路径，和 [`crate::Tileset::src`] 一致
+    #[serde(default)]
+    pub animations: HashMap<String, Vec<ProfileAnimation>>,
+    /// key 和 animations 一样是 tileset 图片的路径，value 是 tile 的 index 到碰撞形状的映射
+    #[serde(default)]
+    pub collisions: HashMap<String, HashMap<u8, CollisionShape>>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct ProfileAnimation {
+    /// 放在 level 中的 tile 的 index
+    pub tile: u8,
+    pub frames: Vec<TileFrame>,
+}
+
+impl ConversionProfile {
+    pub fn from<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(serde_json::from_str(
+            std::fs::read_to_string(path)?.as_str(),
+        )?)
+    }
+}
+
+impl LevelModel {
+    /// 把 profile 中的配置应用到 level 上，会覆盖编辑器中配置的同一个 tile 的动画和碰撞形状
+    pub fn apply_profile(&mut self, profile: &ConversionProfile) {
+        for tileset in self.tilesets.iter_mut() {
+            if let Some(animations) = profile.animations.get(&tileset.src) {
+                for animation in animations.iter() {
+                    tileset.animations.insert(
+                        animation.tile,
+                        TileAnimation {
+                            frames: animation.frames.clone(),
+                        },
+                    );
+                }
+            }
+            if let Some(collisions) = profile.collisions.get(&tileset.src) {
+                tileset.collisions.extend(collisions.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,725 @@
+use std::collections::{BTreeSet, HashMap};
+
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::convert::{EntityRole, LayerRole, GRID_SIZE};
+use crate::{LevelModel, RoomModel, TileGroup, Tileset};
+
+/// 导出的 project 使用的 ldtk 版本
+const LDTK_VERSION: &str = "1.3.3";
+/// tileset 的标签导出成这个 enum 的 value
+const TILE_TAG_ENUM: &str = "TileTag";
+
+/// 导出时各个 definition 的 uid
+struct Uids {
+    next: usize,
+}
+
+impl Uids {
+    fn next(&mut self) -> usize {
+        self.next += 1;
+        self.next - 1
+    }
+}
+
+/// 导出的 tile layer，每种用途和 tileset 的组合对应一个 layer
+struct TileLayer {
+    uid: usize,
+    identifier: String,
+    role: LayerRole,
+    tileset: usize,
+    /// 同一个 room 中同一用途、同一 tileset 的第几个 tile group
+    occurrence: usize,
+}
+
+struct EntityDefs {
+    layer: usize,
+    light: usize,
+    color: usize,
+    alpha: usize,
+    inner: usize,
+    brith_point: usize,
+}
+
+impl LevelModel {
+    /// 将 level 导出成 ldtk 的 project，方便在编辑器中查看和修改生成的关卡
+    ///
+    /// 导出的 project 遵守 [`LevelModel::from_ldtk`] 使用的命名约定，重新转换后可以得到相同的 room：
+    /// - 每个 room 对应一个 level，位置和尺寸不变
+    /// - tile layer 直接使用 tileset 的原图（[`Tileset::src`]），project 需要保存在 assets 目录下才能找到图片；
+    ///   同一种用途使用多个 tileset 时，layer 依次命名为 `Floor`、`Floor_1`……
+    /// - 灯光和出生点放在 `Entity` layer 中
+    /// - tileset 的 custom data 原样写回，由 custom data 配置的动画和碰撞形状也会保留
+    /// - tile 的标签都放在 `TileTag` enum 中，每个 tileset 用它来标记 tile
+    pub fn to_ldtk(&self) -> Value {
+        let mut uids = Uids { next: 1 };
+        let tileset_uids = self
+            .tilesets
+            .iter()
+            .map(|_| uids.next())
+            .collect::<Vec<_>>();
+        let tile_layers = self.tile_layers(&mut uids);
+        let entity_defs = EntityDefs {
+            layer: uids.next(),
+            light: uids.next(),
+            color: uids.next(),
+            alpha: uids.next(),
+            inner: uids.next(),
+            brith_point: uids.next(),
+        };
+        let tag_values = self
+            .tilesets
+            .iter()
+            .flat_map(|tileset| tileset.tags.values().flatten())
+            .collect::<BTreeSet<_>>();
+        let tag_enum_uid = (!tag_values.is_empty()).then(|| uids.next());
+        let enum_defs = tag_enum_uid
+            .map(|uid| vec![enum_def(uid, TILE_TAG_ENUM, tag_values.iter().copied())])
+            .unwrap_or_default();
+
+        let mut tileset_identifiers = Identifiers::default();
+        let tileset_defs = self
+            .tilesets
+            .iter()
+            .zip(tileset_uids.iter())
+            .map(|(tileset, uid)| {
+                let identifier = match tileset.tilt {
+                    // 从 ldtk 转换时通过名称区分倾斜显示的 tileset
+                    true => tileset_identifiers.unique("Wall"),
+                    false => tileset_identifiers.unique(file_stem(&tileset.src)),
+                };
+                tileset_def(tileset, *uid, &identifier, tag_enum_uid)
+            })
+            .collect::<Vec<_>>();
+        // ldtk 中 layer 的顺序是从上往下显示的顺序
+        let mut layer_defs = vec![layer_def(entity_defs.layer, "Entity", "Entities", None)];
+        for role in [LayerRole::Roof, LayerRole::Wall, LayerRole::Floor] {
+            for layer in tile_layers.iter().filter(|layer| layer.role == role) {
+                layer_defs.push(layer_def(
+                    layer.uid,
+                    &layer.identifier,
+                    "Tiles",
+                    Some(tileset_uids[layer.tileset]),
+                ));
+            }
+        }
+
+        let brith_room = self.room_index_at(self.brith_point);
+        let mut level_identifiers = Identifiers::default();
+        let levels = self
+            .rooms
+            .iter()
+            .enumerate()
+            .map(|(i, room)| {
+                // 出生点不在 room 中时偏移为负数，这时不导出出生点
+                let brith_point = match brith_room == Some(i) {
+                    true => {
+                        let offset = |axis: usize| {
+                            u32::try_from(self.brith_point[axis] - room.world_pos[axis]).ok()
+                        };
+                        offset(0).zip(offset(1)).map(|(x, y)| [x, y])
+                    }
+                    false => None,
+                };
+                let layer_instances = layer_defs
+                    .iter()
+                    .map(|def| {
+                        let uid = def["uid"].as_u64().unwrap_or_default() as usize;
+                        match tile_layers.iter().find(|layer| layer.uid == uid) {
+                            Some(layer) => self.tile_layer_instance(room, i, layer, &tileset_defs),
+                            None => entity_layer_instance(room, i, &entity_defs, brith_point),
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                json!({
+                    "identifier": level_identifiers.unique(&room.display_name),
+                    "iid": iid(i + 1, 0, 0),
+                    "uid": uids.next(),
+                    // room 的位置是左下角的 grid 坐标，ldtk 中是左上角的像素坐标，y 轴向下
+                    "worldX": room.world_pos[0] * GRID_SIZE as i32,
+                    "worldY": -(room.world_pos[1] + room.size[1] as i32) * GRID_SIZE as i32,
+                    "worldDepth": 0,
+                    "pxWid": room.size[0] * GRID_SIZE,
+                    "pxHei": room.size[1] * GRID_SIZE,
+                    "__bgColor": "#696A79",
+                    "bgColor": null,
+                    "useAutoIdentifier": false,
+                    "bgRelPath": null,
+                    "bgPos": null,
+                    "bgPivotX": 0.5,
+                    "bgPivotY": 0.5,
+                    "__smartColor": "#ADADB5",
+                    "__bgPos": null,
+                    "externalRelPath": null,
+                    "fieldInstances": [],
+                    "layerInstances": layer_instances,
+                    "__neighbours": [],
+                })
+            })
+            .collect::<Vec<_>>();
+
+        json!({
+            "iid": iid(0, 0, 0),
+            "jsonVersion": LDTK_VERSION,
+            "appBuildId": 0,
+            "nextUid": uids.next,
+            "identifierStyle": "Capitalize",
+            "toc": [],
+            "worldLayout": "Free",
+            "worldGridWidth": 256,
+            "worldGridHeight": 256,
+            "defaultLevelWidth": 256,
+            "defaultLevelHeight": 256,
+            "defaultPivotX": 0,
+            "defaultPivotY": 0,
+            "defaultGridSize": GRID_SIZE,
+            "bgColor": "#40465B",
+            "defaultLevelBgColor": "#696A79",
+            "minifyJson": false,
+            "externalLevels": false,
+            "exportTiled": false,
+            "simplifiedExport": false,
+            "imageExportMode": "None",
+            "exportLevelBg": true,
+            "pngFilePattern": null,
+            "backupOnSave": false,
+            "backupLimit": 10,
+            "levelNamePattern": "Level_%idx",
+            "tutorialDesc": null,
+            "customCommands": [],
+            "flags": [],
+            "defs": {
+                "layers": layer_defs,
+                "entities": entity_defs.to_json(),
+                "tilesets": tileset_defs,
+                "enums": enum_defs,
+                "externalEnums": [],
+                "levelFields": [],
+            },
+            "levels": levels,
+            "worlds": [],
+        })
+    }
+    /// 统计所有 room 中用到的用途和 tileset 的组合
+    fn tile_layers(&self, uids: &mut Uids) -> Vec<TileLayer> {
+        let mut layers: Vec<TileLayer> = vec![];
+        let mut role_counts = HashMap::new();
+        for room in self.rooms.iter() {
+            let mut occurrences = HashMap::new();
+            for (role, tile_group) in room_tile_groups(room) {
+                let Some(tileset) = self.tileset_index(tile_group) else {
+                    continue;
+                };
+                let occurrence = occurrences.entry((role, tileset)).or_insert(0);
+                let exists = layers.iter().any(|layer| {
+                    layer.role == role
+                        && layer.tileset == tileset
+                        && layer.occurrence == *occurrence
+                });
+                if !exists {
+                    let count = role_counts.entry(role).or_insert(0);
+                    let identifier = match *count {
+                        0 => role_identifier(role).to_string(),
+                        n => format!("{}_{n}", role_identifier(role)),
+                    };
+                    *count += 1;
+                    layers.push(TileLayer {
+                        uid: uids.next(),
+                        identifier,
+                        role,
+                        tileset,
+                        occurrence: *occurrence,
+                    });
+                }
+                *occurrence += 1;
+            }
+        }
+        layers
+    }
+    fn tileset_index(&self, tile_group: &TileGroup) -> Option<usize> {
+        self.tilesets
+            .iter()
+            .position(|tileset| tileset.uuid == tile_group.tileset_uuid)
+    }
+    fn tile_layer_instance(
+        &self,
+        room: &RoomModel,
+        level_id: usize,
+        layer: &TileLayer,
+        tileset_defs: &[Value],
+    ) -> Value {
+        let tileset = &self.tilesets[layer.tileset];
+        let tileset_def = &tileset_defs[layer.tileset];
+        let c_wid = tileset_def["__cWid"].as_u64().unwrap_or(1).max(1) as u32;
+        let mut occurrences = HashMap::new();
+        let tile_group = room_tile_groups(room).find(|(role, tile_group)| {
+            if *role != layer.role || self.tileset_index(tile_group) != Some(layer.tileset) {
+                return false;
+            }
+            let occurrence = occurrences.entry(*role).or_insert(0);
+            *occurrence += 1;
+            *occurrence - 1 == layer.occurrence
+        });
+        let mut grid_tiles = vec![];
+        if let Some((_, tile_group)) = tile_group {
+            let mut tiles = tile_group
+                .tiles
+                .iter()
+                .flat_map(|(x, col)| col.iter().map(move |(y, index)| ([*x, *y], *index)))
+                .collect::<Vec<_>>();
+            // 按照 ldtk 的习惯从左上角开始逐行排列，方便比较导出的结果
+            tiles.sort_by_key(|([x, y], _)| (std::cmp::Reverse(*y), *x));
+            for ([grid_x, grid_y], index) in tiles {
+                let Some((src, _)) = tileset.tiles.get(&index) else {
+                    continue;
+                };
+                let editor_y = room.size[1] - grid_y - 1;
+                grid_tiles.push(json!({
+                    "px": [grid_x * GRID_SIZE, editor_y * GRID_SIZE],
+                    "src": src,
+                    "f": 0,
+                    "t": tile_id(*src, c_wid),
+                    "d": [grid_x + editor_y * room.size[0]],
+                }));
+            }
+        }
+        layer_instance(
+            room,
+            level_id,
+            &layer.identifier,
+            "Tiles",
+            layer.uid,
+            Some(tileset_def),
+            grid_tiles,
+            vec![],
+        )
+    }
+}
+
+impl EntityDefs {
+    fn to_json(&self) -> Value {
+        json!([
+            entity_def(
+                self.light,
+                "Light",
+                "#FEE761",
+                "Ellipse",
+                vec![
+                    field_def(
+                        self.color,
+                        "Color",
+                        "Color",
+                        json!({"id": "V_Int", "params": [0xffffff]})
+                    ),
+                    field_def(
+                        self.alpha,
+                        "Alpha",
+                        "Int",
+                        json!({"id": "V_Int", "params": [255]})
+                    ),
+                    field_def(self.inner, "Inner", "Bool", Value::Null),
+                ],
+            ),
+            entity_def(
+                self.brith_point,
+                "BrithPoint",
+                "#BE4A2F",
+                "Rectangle",
+                vec![]
+            ),
+        ])
+    }
+}
+
+fn entity_layer_instance(
+    room: &RoomModel,
+    level_id: usize,
+    defs: &EntityDefs,
+    brith_point: Option<[u32; 2]>,
+) -> Value {
+    let mut entities = vec![];
+    for light in room.lights.iter() {
+        let [r, g, b, a] = light.color;
+        let color = (r as u32) << 16 | (g as u32) << 8 | b as u32;
+        let inner = light.pos[2] > 0;
+        entities.push(entity_instance(
+            room,
+            iid(level_id + 1, defs.layer, entities.len() + 1),
+            defs.light,
+            EntityRole::Light,
+            [light.pos[0], light.pos[1]],
+            vec![
+                json!({
+                    "__identifier": "Color",
+                    "__type": "Color",
+                    "__value": format!("#{color:06X}"),
+                    "__tile": null,
+                    "defUid": defs.color,
+                    "realEditorValues": [{"id": "V_Int", "params": [color]}],
+                }),
+                json!({
+                    "__identifier": "Alpha",
+                    "__type": "Int",
+                    "__value": a,
+                    "__tile": null,
+                    "defUid": defs.alpha,
+                    "realEditorValues": [{"id": "V_Int", "params": [a]}],
+                }),
+                json!({
+                    "__identifier": "Inner",
+                    "__type": "Bool",
+                    "__value": inner,
+                    "__tile": null,
+                    "defUid": defs.inner,
+                    "realEditorValues": [{"id": "V_Bool", "params": [inner]}],
+                }),
+            ],
+        ));
+    }
+    if let Some(grid_pos) = brith_point {
+        entities.push(entity_instance(
+            room,
+            iid(level_id + 1, defs.layer, entities.len() + 1),
+            defs.brith_point,
+            EntityRole::BrithPoint,
+            grid_pos,
+            vec![],
+        ));
+    }
+    layer_instance(
+        room,
+        level_id,
+        "Entity",
+        "Entities",
+        defs.layer,
+        None,
+        vec![],
+        entities,
+    )
+}
+
+/// entity 的 pivot 在中心，放在 grid 的中心
+fn entity_instance(
+    room: &RoomModel,
+    iid: String,
+    def_uid: usize,
+    role: EntityRole,
+    grid_pos: [u32; 2],
+    field_instances: Vec<Value>,
+) -> Value {
+    let editor_grid = [grid_pos[0], room.size[1].saturating_sub(grid_pos[1] + 1)];
+    let half = GRID_SIZE / 2;
+    json!({
+        "__identifier": entity_identifier(role),
+        "__grid": editor_grid,
+        "__pivot": [0.5, 0.5],
+        "__tags": [],
+        "__tile": null,
+        "__smartColor": "#FFFFFF",
+        "iid": iid,
+        "width": GRID_SIZE,
+        "height": GRID_SIZE,
+        "defUid": def_uid,
+        "px": [editor_grid[0] * GRID_SIZE + half, editor_grid[1] * GRID_SIZE + half],
+        "fieldInstances": field_instances,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn layer_instance(
+    room: &RoomModel,
+    level_id: usize,
+    identifier: &str,
+    type_: &str,
+    layer_def_uid: usize,
+    tileset_def: Option<&Value>,
+    grid_tiles: Vec<Value>,
+    entity_instances: Vec<Value>,
+) -> Value {
+    json!({
+        "__identifier": identifier,
+        "__type": type_,
+        "__cWid": room.size[0],
+        "__cHei": room.size[1],
+        "__gridSize": GRID_SIZE,
+        "__opacity": 1,
+        "__pxTotalOffsetX": 0,
+        "__pxTotalOffsetY": 0,
+        "__tilesetDefUid": tileset_def.map(|def| &def["uid"]),
+        "__tilesetRelPath": tileset_def.map(|def| &def["relPath"]),
+        "iid": iid(level_id + 1, layer_def_uid, 0),
+        "levelId": level_id,
+        "layerDefUid": layer_def_uid,
+        "pxOffsetX": 0,
+        "pxOffsetY": 0,
+        "visible": true,
+        "optionalRules": [],
+        "intGridCsv": [],
+        "autoLayerTiles": [],
+        "seed": 0,
+        "overrideTilesetUid": null,
+        "gridTiles": grid_tiles,
+        "entityInstances": entity_instances,
+    })
+}
+
+fn layer_def(uid: usize, identifier: &str, type_: &str, tileset_def_uid: Option<usize>) -> Value {
+    json!({
+        "__type": type_,
+        "identifier": identifier,
+        "type": type_,
+        "uid": uid,
+        "doc": null,
+        "gridSize": GRID_SIZE,
+        "guideGridWid": 0,
+        "guideGridHei": 0,
+        "displayOpacity": 1,
+        "inactiveOpacity": 1,
+        "hideInList": false,
+        "hideFieldsWhenInactive": tileset_def_uid.is_none(),
+        "canSelectWhenInactive": true,
+        "pxOffsetX": 0,
+        "pxOffsetY": 0,
+        "parallaxFactorX": 0,
+        "parallaxFactorY": 0,
+        "parallaxScaling": true,
+        "requiredTags": [],
+        "excludedTags": [],
+        "intGridValues": [],
+        "autoRuleGroups": [],
+        "autoSourceLayerDefUid": null,
+        "tilesetDefUid": tileset_def_uid,
+        "tilePivotX": 0,
+        "tilePivotY": 0,
+    })
+}
+
+/// tileset 的尺寸根据用到的 tile 的范围计算
+fn tileset_def(
+    tileset: &Tileset,
+    uid: usize,
+    identifier: &str,
+    tag_enum_uid: Option<usize>,
+) -> Value {
+    let (px_wid, px_hei) = tileset.tiles.values().fold((0, 0), |(w, h), (min, size)| {
+        (w.max(min[0] + size[0]), h.max(min[1] + size[1]))
+    });
+    let c_wid = px_wid / GRID_SIZE;
+    let mut custom_data = tileset
+        .custom_data
+        .iter()
+        .filter_map(|(index, data)| {
+            let (src, _) = tileset.tiles.get(index)?;
+            Some((tile_id(*src, c_wid), data))
+        })
+        .collect::<Vec<_>>();
+    custom_data.sort_by_key(|(tile_id, _)| *tile_id);
+    let custom_data = custom_data
+        .into_iter()
+        .map(|(tile_id, data)| json!({"tileId": tile_id, "data": data}))
+        .collect::<Vec<_>>();
+    let mut enum_tags: Vec<(&String, Vec<u32>)> = vec![];
+    for (index, tags) in tileset.tags.iter() {
+        let Some((src, _)) = tileset.tiles.get(index) else {
+            continue;
+        };
+        for tag in tags.iter() {
+            match enum_tags.iter_mut().find(|(value, _)| *value == tag) {
+                Some((_, tile_ids)) => tile_ids.push(tile_id(*src, c_wid)),
+                None => enum_tags.push((tag, vec![tile_id(*src, c_wid)])),
+            }
+        }
+    }
+    enum_tags.sort();
+    let enum_tags = enum_tags
+        .into_iter()
+        .map(|(value, mut tile_ids)| {
+            tile_ids.sort();
+            json!({"enumValueId": value, "tileIds": tile_ids})
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "__cWid": c_wid,
+        "__cHei": px_hei / GRID_SIZE,
+        "identifier": identifier,
+        "uid": uid,
+        "relPath": tileset.src,
+        "embedAtlas": null,
+        "pxWid": px_wid,
+        "pxHei": px_hei,
+        "tileGridSize": GRID_SIZE,
+        "spacing": 0,
+        "padding": 0,
+        "tags": [],
+        "tagsSourceEnumUid": tag_enum_uid,
+        "enumTags": enum_tags,
+        "customData": custom_data,
+        "savedSelections": [],
+    })
+}
+
+fn enum_def<'a>(uid: usize, identifier: &str, values: impl Iterator<Item = &'a String>) -> Value {
+    let values = values
+        .map(|id| json!({"id": id, "tileRect": null, "color": 0}))
+        .collect::<Vec<_>>();
+    json!({
+        "identifier": identifier,
+        "uid": uid,
+        "values": values,
+        "iconTilesetUid": null,
+        "externalRelPath": null,
+        "externalFileChecksum": null,
+        "tags": [],
+    })
+}
+
+fn entity_def(
+    uid: usize,
+    identifier: &str,
+    color: &str,
+    render_mode: &str,
+    field_defs: Vec<Value>,
+) -> Value {
+    json!({
+        "identifier": identifier,
+        "uid": uid,
+        "tags": [],
+        "exportToToc": false,
+        "doc": null,
+        "width": GRID_SIZE,
+        "height": GRID_SIZE,
+        "resizableX": false,
+        "resizableY": false,
+        "minWidth": null,
+        "maxWidth": null,
+        "minHeight": null,
+        "maxHeight": null,
+        "keepAspectRatio": false,
+        "tileOpacity": 1,
+        "fillOpacity": 1,
+        "lineOpacity": 1,
+        "hollow": false,
+        "color": color,
+        "renderMode": render_mode,
+        "showName": true,
+        "tilesetId": null,
+        "tileRenderMode": "FitInside",
+        "tileRect": null,
+        "nineSliceBorders": [],
+        "maxCount": 0,
+        "limitScope": "PerLevel",
+        "limitBehavior": "MoveLastOne",
+        "pivotX": 0.5,
+        "pivotY": 0.5,
+        "fieldDefs": field_defs,
+    })
+}
+
+fn field_def(uid: usize, identifier: &str, type_: &str, default: Value) -> Value {
+    json!({
+        "identifier": identifier,
+        "doc": null,
+        "__type": type_,
+        "uid": uid,
+        "type": format!("F_{type_}"),
+        "isArray": false,
+        "canBeNull": false,
+        "arrayMinLength": null,
+        "arrayMaxLength": null,
+        "editorDisplayMode": "Hidden",
+        "editorDisplayScale": 1,
+        "editorDisplayPos": "Above",
+        "editorLinkStyle": "StraightArrow",
+        "editorAlwaysShow": false,
+        "editorShowInWorld": true,
+        "editorCutLongValues": true,
+        "editorTextSuffix": null,
+        "editorTextPrefix": null,
+        "useForSmartColor": identifier == "Color",
+        "min": null,
+        "max": null,
+        "regex": null,
+        "acceptFileTypes": null,
+        "defaultOverride": default,
+        "textLanguageMode": null,
+        "symmetricalRef": false,
+        "autoChainRef": true,
+        "allowOutOfLevelRef": true,
+        "allowedRefs": "OnlySame",
+        "allowedRefsEntityUid": null,
+        "allowedRefTags": [],
+        "tilesetUid": null,
+    })
+}
+
+fn room_tile_groups(room: &RoomModel) -> impl Iterator<Item = (LayerRole, &TileGroup)> {
+    let floors = room.floors.iter().map(|group| (LayerRole::Floor, group));
+    let walls = room.walls.iter().map(|group| (LayerRole::Wall, group));
+    let roofs = room.roofs.iter().map(|group| (LayerRole::Roof, group));
+    floors.chain(walls).chain(roofs)
+}
+
+fn role_identifier(role: LayerRole) -> &'static str {
+    match role {
+        LayerRole::Entity => "Entity",
+        LayerRole::Floor => "Floor",
+        LayerRole::Wall => "Wall",
+        LayerRole::Roof => "Roof",
+    }
+}
+
+fn entity_identifier(role: EntityRole) -> &'static str {
+    match role {
+        EntityRole::Light => "Light",
+        EntityRole::BrithPoint => "BrithPoint",
+    }
+}
+
+/// ldtk 中的 iid，由 level 的序号（从1开始）、layer 的 uid 和 entity 的序号（从1开始）组成，
+/// 为0时表示上一级，这样同一个 level 每次导出的 iid 都相同，导出的文件可以直接比较
+fn iid(level: usize, layer: usize, entity: usize) -> String {
+    let value = (level as u128) << 64 | (layer as u128) << 32 | entity as u128;
+    Uuid::from_u128(value).to_string()
+}
+
+/// tile 在 tileset 中的 id，从左上角开始逐行递增
+fn tile_id(src: [u32; 2], c_wid: u32) -> u32 {
+    src[1] / GRID_SIZE * c_wid + src[0] / GRID_SIZE
+}
+
+fn file_stem(src: &str) -> &str {
+    std::path::Path::new(src)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(src)
+}
+
+/// ldtk 中的 identifier 必须唯一，并且只能包含字母、数字和下划线，不能以数字开头
+#[derive(Default)]
+struct Identifiers {
+    used: Vec<String>,
+}
+
+impl Identifiers {
+    fn unique(&mut self, name: &str) -> String {
+        let mut base = name
+            .chars()
+            .map(|c| match c.is_ascii_alphanumeric() {
+                true => c,
+                false => '_',
+            })
+            .collect::<String>();
+        if !base.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            base.insert(0, '_');
+        }
+        let mut identifier = base.clone();
+        let mut n = 1;
+        while self.used.contains(&identifier) {
+            identifier = format!("{base}_{n}");
+            n += 1;
+        }
+        self.used.push(identifier.clone());
+        identifier
+    }
+}
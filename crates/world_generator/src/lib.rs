@@ -1,5 +1,12 @@
-pub use model::{LevelModel, Light, RoomModel, TileGroup, TileType, Tileset};
+pub use atlas::ATLAS_PADDING;
+pub use convert::{ConversionProfile, EntityRole, LayerRole, ProfileAnimation, GRID_SIZE};
+pub use model::{
+    Collider, CollisionShape, Corner, LayerTile, LevelModel, LevelModelBuilder, Light, RoomBuilder,
+    RoomModel, Side, TileAnimation, TileAtlas, TileFrame, TileGroup, TileType, Tileset,
+};
 
+mod atlas;
+mod convert;
 mod model;
 
 impl LevelModel {
@@ -1,188 +1,60 @@
-use std::collections::HashMap;
-
-use uuid::Uuid;
-
-use ldtk::FieldValue;
-use world_generator::{LevelModel, Light, RoomModel, TileGroup};
+use world_generator::{ConversionProfile, LevelModel};
 
+/// 用法: `world_generator [input] [output]`
+///
+/// input 可以是 ldtk 的 project，也可以是 tiled 的 world 或者单个 map
+///
+/// output 是 `.ldtk` 文件时把转换后的 level 导出成 ldtk 的 project，而不是生成游戏使用的 level
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let project = serde_json::from_str::<ldtk::Project>(
-        std::fs::read_to_string("assets/level.ldtk")?.as_str(),
-    )?;
+    let mut args = std::env::args().skip(1);
+    let input = args.next().unwrap_or(String::from("assets/level.ldtk"));
+    let output = args
+        .next()
+        .unwrap_or(String::from("assets/levels/demo_output.json"));
 
-    let level_model = {
-        let mut level_model = LevelModel::default();
-        // 将ldtk中的tileset定义提取转化成需要的结构
-        let (tilesets, tilesets_ref) = {
-            let mut tilesets = vec![];
-            let mut tilesets_ref = HashMap::new();
-            for tileset_def in project.defs.tilesets.iter() {
-                let uuid = Uuid::new_v4().to_string();
-                tilesets_ref.insert(tileset_def.uid, uuid.clone());
-                let mut tileset = world_generator::Tileset {
-                    uuid: uuid.clone(),
-                    src: tileset_def.rel_path.clone(),
-                    ..Default::default()
-                };
-                let mut count = 0;
-                // 针对wall做一下特殊处理
-                let (size, y_range) = match tileset_def.identifier.as_str() {
-                    "Wall" => {
-                        tileset.tilt = true;
-                        (
-                            [tileset_def.tile_grid_size, (tileset_def.tile_grid_size * 2)],
-                            (0..tileset_def.c_hei).step_by(2),
-                        )
-                    }
-                    _ => (
-                        [tileset_def.tile_grid_size, tileset_def.tile_grid_size],
-                        (0..tileset_def.c_hei).step_by(1),
-                    ),
-                };
-                for y in y_range {
-                    for x in 0..tileset_def.c_wid {
-                        let min = [
-                            x * tileset_def.tile_grid_size,
-                            y * tileset_def.tile_grid_size,
-                        ];
-                        tileset.tiles.insert(count, (min, size));
-                        count += 1;
-                    }
-                }
-                tilesets.push(tileset);
-            }
-            (tilesets, tilesets_ref)
-        };
-        // 处理得到layer直接关联的tileset的uuid的关系
-        let layer_to_uuid = {
-            project
-                .defs
-                .layers
-                .iter()
-                .filter_map(|layer| {
-                    let mut result = None;
-                    if let Some(tileset_id) = layer.tileset_def_uid {
-                        if let Some(tileset) = tilesets_ref.get(&tileset_id) {
-                            result = Some((layer.uid, tileset.clone()))
-                        }
-                    }
-                    result
-                })
-                .collect::<HashMap<usize, String>>()
-        };
-        // 转换所有的room
-        let rooms = {
-            let mut rooms = vec![];
-            for level in project.levels.iter() {
-                let size = [level.px_wid, level.px_hei];
-                let grid_size = [size[0] / 16, size[1] / 16];
-                let grid_offset = [
-                    level.world_x / 16,
-                    (-level.world_y / 16) - grid_size[1] as i32,
-                ];
-                let mut room = RoomModel {
-                    display_name: level.identifier.clone(),
-                    world_pos: grid_offset,
-                    size: grid_size,
-                    ..Default::default()
-                };
-                for layer in level.layer_instances.iter() {
-                    match layer.identifier.as_str() {
-                        "Entity" => {
-                            for entity in layer.entity_instances.iter() {
-                                let x = entity.px.0;
-                                let y = size[1] - entity.px.1;
-                                let grid_x = x / 16;
-                                let grid_y = y / 16;
-                                match entity.identifier.as_str() {
-                                    "Light" => {
-                                        let mut color = None;
-                                        let mut alpha = None;
-                                        let mut inner = None;
-                                        for field in entity.field_instances.iter() {
-                                            match field.get_value() {
-                                                FieldValue::Color { r, g, b } => {
-                                                    color = Some([r, g, b])
-                                                }
-                                                FieldValue::Int(a) => alpha = Some(a as u8),
-                                                FieldValue::Bool(i) => inner = Some(i),
-                                                _ => {}
-                                            }
-                                        }
-                                        let color = color.unwrap();
-                                        let alpha = alpha.unwrap();
-                                        let inner = inner.unwrap();
-                                        room.lights.push(Light {
-                                            // TODO 这里的问题是如何让灯光低于roof但是能够让光扩散的足够开
-                                            pos: [grid_x, grid_y, if inner { 32 } else { 0 }],
-                                            color: [color[0], color[1], color[2], alpha],
-                                        })
-                                    }
-                                    "BrithPoint" => {
-                                        level_model.brith_point = [
-                                            grid_x as i32 + grid_offset[0],
-                                            grid_y as i32 + grid_offset[1],
-                                        ];
-                                    }
-                                    _ => {}
-                                }
-                            }
-                        }
-                        _ => {
-                            if let Some(uuid) = layer_to_uuid.get(&layer.layer_def_uid) {
-                                let mut tile_group = TileGroup {
-                                    tileset_uuid: uuid.clone(),
-                                    ..Default::default()
-                                };
-                                let used_tileset = tilesets
-                                    .iter()
-                                    .find(|tileset| tileset.uuid == *uuid)
-                                    .unwrap();
-                                for tile in layer.grid_tiles.iter() {
-                                    if let Some((index, _)) =
-                                        used_tileset.tiles.iter().find(|(_, rect)| {
-                                            rect.0[0] == tile.src[0] && rect.0[1] == tile.src[1]
-                                        })
-                                    {
-                                        let grid_x = tile.px[0] / 16;
-                                        let grid_y = tile.px[1] / 16;
-                                        // 比如room高5行，当前是从上往下数第4行，grid_y就是3
-                                        // 那么转化后就会变成从下往上数第2行，grid_y就是2
-                                        let grid_y = grid_size[1] - grid_y - 1;
-                                        tile_group.insert(grid_x, grid_y, *index);
-                                    }
-                                }
-                                match layer.identifier.as_str() {
-                                    "Roof" => {
-                                        if !tile_group.tiles.is_empty() {
-                                            room.roofs.push(tile_group);
-                                        }
-                                    }
-                                    "Floor" => {
-                                        if !tile_group.tiles.is_empty() {
-                                            room.floors.push(tile_group);
-                                        }
-                                    }
-                                    "Wall" => {
-                                        if !tile_group.tiles.is_empty() {
-                                            room.walls.push(tile_group);
-                                        }
-                                    }
-                                    _ => {}
-                                }
-                            }
-                        }
-                    }
-                }
-                rooms.push(room);
-            }
-            rooms
-        };
-        level_model.rooms = rooms;
-        level_model.tilesets = tilesets;
-        level_model
+    let input_path = std::path::Path::new(&input);
+    let mut level_model = match input_path.extension().and_then(|ext| ext.to_str()) {
+        Some("ldtk") => LevelModel::from_ldtk(&ldtk::Project::from(input_path)?)?,
+        Some("world") => LevelModel::from_tiled(&tiled::World::from(input_path)?),
+        Some("tmx") | Some("tmj") | Some("json") => {
+            let map = tiled::Map::from(input_path)?;
+            let file_name = input_path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            LevelModel::from_tiled(&tiled::World::single(&file_name, map))
+        }
+        _ => return Err(format!("unsupported level source {input}").into()),
     };
-    let buffer = std::fs::File::create("assets/levels/demo_output.json")?;
+    // 和 level source 同名的 profile 文件，比如 assets/level.profile.json
+    let profile_path = input_path.with_extension("profile.json");
+    if profile_path.exists() {
+        level_model.apply_profile(&ConversionProfile::from(profile_path)?);
+    }
+    let output_path = std::path::Path::new(&output);
+    if output_path.extension().and_then(|ext| ext.to_str()) == Some("ldtk") {
+        let buffer = std::fs::File::create(output_path)?;
+        serde_json::to_writer_pretty(buffer, &level_model.to_ldtk())?;
+        return Ok(());
+    }
+    // atlas 和 level 文件放在一起，比如 assets/levels/demo_output.json 对应 levels/demo_output_atlas
+    let atlas_dir = output_path
+        .with_file_name(format!(
+            "{}_atlas",
+            output_path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default()
+        ))
+        .strip_prefix("assets")
+        .map_err(|_| format!("output {output} should be inside assets"))?
+        .to_string_lossy()
+        .replace('\\', "/");
+    level_model.pack_atlas("assets", &atlas_dir)?;
+    if let Some(dir) = output_path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let buffer = std::fs::File::create(output)?;
     serde_json::to_writer(buffer, &level_model)?;
     Ok(())
 }
@@ -0,0 +1,204 @@
+use crate::model::{CollisionShape, LevelModel, Light, RoomModel, TileGroup, Tileset};
+
+/// 在代码中直接构建 [`LevelModel`]，主要用于测试时构造小而确定的关卡
+///
+/// ```
+/// use world_generator::{LevelModel, RoomBuilder, Tileset};
+///
+/// let level = LevelModel::builder()
+///     .brith_point([1, 1])
+///     .tileset(Tileset::from_grid("floor", "art/floor.png", 16, 4, 4))
+///     .room(
+///         RoomBuilder::new("Room", [0, 0], [4, 4])
+///             .floor_rect("floor", [0, 0], [4, 4], 0)
+///             .light([2, 2, 0], [255, 255, 255, 255]),
+///     )
+///     .build();
+/// assert!(level.contains_floor([1, 1]));
+/// ```
+#[derive(Default)]
+pub struct LevelModelBuilder {
+    level: LevelModel,
+}
+
+impl LevelModelBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// brith point 使用的是 grid 坐标，与 [`LevelModel::brith_point`] 一致
+    pub fn brith_point(mut self, grid_pos: [i32; 2]) -> Self {
+        self.level.brith_point = grid_pos;
+        self
+    }
+    pub fn tileset(mut self, tileset: Tileset) -> Self {
+        self.level.tilesets.push(tileset);
+        self
+    }
+    pub fn room(mut self, room: impl Into<RoomModel>) -> Self {
+        self.level.rooms.push(room.into());
+        self
+    }
+    pub fn build(self) -> LevelModel {
+        self.level
+    }
+}
+
+impl LevelModel {
+    pub fn builder() -> LevelModelBuilder {
+        LevelModelBuilder::new()
+    }
+}
+
+/// 构建单个 [`RoomModel`]
+///
+/// 所有 rect 的坐标都是相对 room 左下角的 grid 坐标
+pub struct RoomBuilder {
+    room: RoomModel,
+}
+
+impl RoomBuilder {
+    pub fn new(display_name: &str, world_pos: [i32; 2], size: [u32; 2]) -> Self {
+        Self {
+            room: RoomModel {
+                display_name: display_name.to_string(),
+                world_pos,
+                size,
+                ..Default::default()
+            },
+        }
+    }
+    /// 用 tileset 中的同一个 tile 填满 `min` 开始、尺寸为 `size` 的矩形
+    pub fn floor_rect(
+        mut self,
+        tileset_uuid: &str,
+        min: [u32; 2],
+        size: [u32; 2],
+        index: u8,
+    ) -> Self {
+        fill_rect(&mut self.room.floors, tileset_uuid, min, size, index);
+        self
+    }
+    pub fn wall_rect(
+        mut self,
+        tileset_uuid: &str,
+        min: [u32; 2],
+        size: [u32; 2],
+        index: u8,
+    ) -> Self {
+        fill_rect(&mut self.room.walls, tileset_uuid, min, size, index);
+        self
+    }
+    pub fn roof_rect(
+        mut self,
+        tileset_uuid: &str,
+        min: [u32; 2],
+        size: [u32; 2],
+        index: u8,
+    ) -> Self {
+        fill_rect(&mut self.room.roofs, tileset_uuid, min, size, index);
+        self
+    }
+    /// pos 的前两位是 room 内的 grid 坐标，第三位是高度
+    pub fn light(mut self, pos: [u32; 3], color: [u8; 4]) -> Self {
+        self.room.lights.push(Light { pos, color });
+        self
+    }
+    pub fn build(self) -> RoomModel {
+        self.room
+    }
+}
+
+impl From<RoomBuilder> for RoomModel {
+    fn from(builder: RoomBuilder) -> Self {
+        builder.build()
+    }
+}
+
+/// 同一个 tileset 的 tile 都放进同一个 [`TileGroup`] 中，和从 ldtk 转换时每个 layer 一个 group 保持一致
+fn fill_rect(
+    groups: &mut Vec<TileGroup>,
+    tileset_uuid: &str,
+    min: [u32; 2],
+    size: [u32; 2],
+    index: u8,
+) {
+    let group = match groups
+        .iter()
+        .position(|group| group.tileset_uuid == tileset_uuid)
+    {
+        Some(position) => &mut groups[position],
+        None => {
+            groups.push(TileGroup {
+                tileset_uuid: tileset_uuid.to_string(),
+                ..Default::default()
+            });
+            groups.last_mut().unwrap()
+        }
+    };
+    for grid_x in min[0]..min[0] + size[0] {
+        for grid_y in min[1]..min[1] + size[1] {
+            group.insert(grid_x, grid_y, index);
+        }
+    }
+}
+
+impl Tileset {
+    /// 按照 `columns` x `rows` 的网格切分 tileset，index 从左上角开始逐行递增
+    pub fn from_grid(uuid: &str, src: &str, tile_size: u32, columns: u32, rows: u32) -> Self {
+        Self::split_grid(
+            uuid,
+            src,
+            [tile_size, tile_size],
+            columns,
+            (0..rows).step_by(1),
+        )
+    }
+    /// wall 的 tile 是倾斜显示的，每个 tile 占两个 cell 的高度，和转换 ldtk 时的处理一致
+    pub fn from_tilt_grid(uuid: &str, src: &str, tile_size: u32, columns: u32, rows: u32) -> Self {
+        let mut tileset = Self::split_grid(
+            uuid,
+            src,
+            [tile_size, tile_size * 2],
+            columns,
+            (0..rows).step_by(2),
+        );
+        tileset.tilt = true;
+        tileset
+    }
+    /// 图片四周留有 `margin`、tile 之间留有 `spacing` 像素时，按照留白重新定位每个 tile
+    pub fn with_spacing(mut self, margin: u32, spacing: u32) -> Self {
+        for (pos, size) in self.tiles.values_mut() {
+            // 切分时 x 和 y 都是按照 tile 的宽度排列的
+            *pos = pos.map(|v| margin + v / size[0] * (size[0] + spacing));
+        }
+        self
+    }
+    /// 设置 tile 的碰撞形状
+    pub fn with_collision(mut self, index: u8, shape: CollisionShape) -> Self {
+        self.collisions.insert(index, shape);
+        self
+    }
+    fn split_grid(
+        uuid: &str,
+        src: &str,
+        size: [u32; 2],
+        columns: u32,
+        y_range: std::iter::StepBy<std::ops::Range<u32>>,
+    ) -> Self {
+        let mut tileset = Tileset {
+            uuid: uuid.to_string(),
+            src: src.to_string(),
+            ..Default::default()
+        };
+        let mut count = 0;
+        for y in y_range {
+            for x in 0..columns {
+                tileset
+                    .tiles
+                    .insert(count, ([x * size[0], y * size[0]], size));
+                count += 1;
+            }
+        }
+        tileset
+    }
+}
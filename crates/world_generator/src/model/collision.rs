@@ -0,0 +1,228 @@
+use crate::convert::GRID_SIZE;
+use crate::model::{LevelModel, TileGroup};
+
+/// tile 的碰撞形状，坐标是 tile 内以左下角为原点的像素坐标，y 轴向上
+///
+/// 在 json 中的写法：
+/// - `"full"`、`"none"`
+/// - `{"half": "bottom"}`，只有下半个 tile 不可通过
+/// - `{"slope": "bottom_left"}`，以左下角为直角的三角形
+/// - `{"polygon": [[0, 0], [16, 0], [16, 4]]}`，必须是凸多边形
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionShape {
+    Full,
+    None,
+    Half(Side),
+    Slope(Corner),
+    Polygon(Vec<[f32; 2]>),
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Copy, Clone, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Copy, Clone, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl CollisionShape {
+    /// 解析编辑器中配置的字符串，`full` 这种简单的形状可以省略引号
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        serde_json::from_str(value)
+            .or_else(|_| serde_json::from_value(serde_json::Value::from(value)))
+            .ok()
+    }
+    /// 转换成 tile 内的凸多边形，没有碰撞时返回 None
+    pub fn polygon(&self) -> Option<Vec<[f32; 2]>> {
+        let s = GRID_SIZE as f32;
+        let h = s / 2.0;
+        let polygon = match self {
+            Self::None => return None,
+            Self::Full => vec![[0.0, 0.0], [s, 0.0], [s, s], [0.0, s]],
+            Self::Half(Side::Bottom) => vec![[0.0, 0.0], [s, 0.0], [s, h], [0.0, h]],
+            Self::Half(Side::Top) => vec![[0.0, h], [s, h], [s, s], [0.0, s]],
+            Self::Half(Side::Left) => vec![[0.0, 0.0], [h, 0.0], [h, s], [0.0, s]],
+            Self::Half(Side::Right) => vec![[h, 0.0], [s, 0.0], [s, s], [h, s]],
+            Self::Slope(Corner::BottomLeft) => vec![[0.0, 0.0], [s, 0.0], [0.0, s]],
+            Self::Slope(Corner::BottomRight) => vec![[0.0, 0.0], [s, 0.0], [s, s]],
+            Self::Slope(Corner::TopLeft) => vec![[0.0, 0.0], [s, s], [0.0, s]],
+            Self::Slope(Corner::TopRight) => vec![[s, 0.0], [s, s], [0.0, s]],
+            Self::Polygon(points) if points.len() >= 3 => points.clone(),
+            Self::Polygon(_) => return None,
+        };
+        Some(polygon)
+    }
+}
+
+/// 用来和关卡做碰撞检测的形状，坐标是以像素为单位的关卡坐标
+///
+/// grid `[x, y]` 对应的 tile 占据 `[x * 16, y * 16]` 到 `[(x + 1) * 16, (y + 1) * 16]` 的范围
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Collider {
+    Rect { min: [f32; 2], max: [f32; 2] },
+    Circle { center: [f32; 2], radius: f32 },
+}
+
+impl Collider {
+    fn bounds(&self) -> ([f32; 2], [f32; 2]) {
+        match *self {
+            Self::Rect { min, max } => (min, max),
+            Self::Circle { center, radius } => (
+                [center[0] - radius, center[1] - radius],
+                [center[0] + radius, center[1] + radius],
+            ),
+        }
+    }
+    fn overlap_polygon(&self, polygon: &[[f32; 2]]) -> bool {
+        match *self {
+            Self::Rect { min, max } => {
+                let rect = [min, [max[0], min[1]], max, [min[0], max[1]]];
+                !has_separating_axis(&rect, polygon) && !has_separating_axis(polygon, &rect)
+            }
+            Self::Circle { center, radius } => {
+                if contains_point(polygon, center) {
+                    return true;
+                }
+                edges(polygon).any(|(a, b)| distance_squared(a, b, center) < radius * radius)
+            }
+        }
+    }
+}
+
+impl LevelModel {
+    /// 判断 collider 是否和关卡中不可通过的部分重叠
+    ///
+    /// 不可通过的部分包括：没有任何 tile 的 grid，以及 tile 的 [`CollisionShape`]。
+    /// tileset 中没有配置碰撞形状的 tile，floor 默认可以通过，wall 和 roof 默认不可通过
+    pub fn overlap(&self, collider: &Collider) -> bool {
+        let (min, max) = collider.bounds();
+        let size = GRID_SIZE as f32;
+        // 刚好落在 grid 边界上的 collider 不算进下一个 grid
+        let min_grid = [
+            (min[0] / size).floor() as i32,
+            (min[1] / size).floor() as i32,
+        ];
+        let max_grid = [
+            (max[0] / size).ceil() as i32 - 1,
+            (max[1] / size).ceil() as i32 - 1,
+        ];
+        for grid_x in min_grid[0]..=max_grid[0].max(min_grid[0]) {
+            for grid_y in min_grid[1]..=max_grid[1].max(min_grid[1]) {
+                let origin = [grid_x as f32 * size, grid_y as f32 * size];
+                for shape in self.grid_collisions([grid_x, grid_y]) {
+                    let Some(polygon) = shape.polygon() else {
+                        continue;
+                    };
+                    let polygon = polygon
+                        .iter()
+                        .map(|p| [p[0] + origin[0], p[1] + origin[1]])
+                        .collect::<Vec<_>>();
+                    if collider.overlap_polygon(&polygon) {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+    /// grid 中所有 tile 的碰撞形状，没有 tile 的 grid 不可通过
+    fn grid_collisions(&self, grid_pos: [i32; 2]) -> Vec<CollisionShape> {
+        let mut shapes = vec![];
+        for room in self.rooms.iter() {
+            let Some(rel_grid_pos) = room.rel_grid_pos(grid_pos) else {
+                continue;
+            };
+            let groups = [
+                (&room.floors, CollisionShape::None),
+                (&room.walls, CollisionShape::Full),
+                (&room.roofs, CollisionShape::Full),
+            ];
+            for (tile_groups, default) in groups {
+                for tile_group in tile_groups.iter() {
+                    if let Some(index) = tile_group.get(rel_grid_pos) {
+                        shapes.push(
+                            self.tile_collision(tile_group, index)
+                                .unwrap_or_else(|| default.clone()),
+                        );
+                    }
+                }
+            }
+        }
+        if shapes.is_empty() {
+            shapes.push(CollisionShape::Full);
+        }
+        shapes
+    }
+    fn tile_collision(&self, tile_group: &TileGroup, index: u8) -> Option<CollisionShape> {
+        self.tilesets
+            .iter()
+            .find(|tileset| tileset.uuid == tile_group.tileset_uuid)?
+            .collisions
+            .get(&index)
+            .cloned()
+    }
+}
+
+fn edges(polygon: &[[f32; 2]]) -> impl Iterator<Item = ([f32; 2], [f32; 2])> + '_ {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(a, b)| (*a, *b))
+}
+
+/// 以 a 的每条边的法线作为轴，判断两个凸多边形的投影是否分离
+fn has_separating_axis(a: &[[f32; 2]], b: &[[f32; 2]]) -> bool {
+    edges(a).any(|(p, q)| {
+        let axis = [q[1] - p[1], p[0] - q[0]];
+        let project = |polygon: &[[f32; 2]]| {
+            polygon
+                .iter()
+                .map(|v| v[0] * axis[0] + v[1] * axis[1])
+                .fold((f32::MAX, f32::MIN), |(min, max), d| {
+                    (min.min(d), max.max(d))
+                })
+        };
+        let (a_min, a_max) = project(a);
+        let (b_min, b_max) = project(b);
+        // 只是边贴在一起不算重叠
+        a_max <= b_min || b_max <= a_min
+    })
+}
+
+fn contains_point(polygon: &[[f32; 2]], point: [f32; 2]) -> bool {
+    let mut inside = false;
+    for (a, b) in edges(polygon) {
+        if (a[1] > point[1]) != (b[1] > point[1])
+            && point[0] < (b[0] - a[0]) * (point[1] - a[1]) / (b[1] - a[1]) + a[0]
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// 点到线段 ab 的距离的平方
+fn distance_squared(a: [f32; 2], b: [f32; 2], point: [f32; 2]) -> f32 {
+    let ab = [b[0] - a[0], b[1] - a[1]];
+    let ap = [point[0] - a[0], point[1] - a[1]];
+    let len = ab[0] * ab[0] + ab[1] * ab[1];
+    let t = if len > 0.0 {
+        ((ap[0] * ab[0] + ap[1] * ab[1]) / len).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let d = [ap[0] - ab[0] * t, ap[1] - ab[1] * t];
+    d[0] * d[0] + d[1] * d[1]
+}
@@ -1,5 +1,13 @@
 use std::collections::HashMap;
 
+pub use builder::{LevelModelBuilder, RoomBuilder};
+pub use collision::{Collider, CollisionShape, Corner, Side};
+pub use query::LayerTile;
+
+mod builder;
+mod collision;
+mod query;
+
 #[derive(serde::Serialize, serde::Deserialize, Clone, Default, Debug)]
 pub struct LevelModel {
     /// default brith point (in grid) in the level
@@ -31,7 +39,7 @@ impl LevelModel {
     }
 }
 
-#[derive(Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum TileType {
     Floor,
     Wall,
@@ -114,6 +122,10 @@ impl TileGroup {
         }
         false
     }
+    /// grid 上的 tile 的 index
+    pub fn get(&self, grid_pos: [u32; 2]) -> Option<u8> {
+        self.tiles.get(&grid_pos[0])?.get(&grid_pos[1]).copied()
+    }
 }
 
 impl TileGroup {
@@ -139,4 +151,94 @@ pub struct Tileset {
     pub tiles: HashMap<u8, ([u32; 2], [u32; 2])>,
     // 😔 因为wall的tile是倾斜显示的，所以创建对应的mesh时需要调整height的尺寸
     pub tilt: bool,
+    /// 由 world_generator 打包生成的 atlas，只包含 level 中用到的 tile
+    #[serde(default)]
+    pub atlas: Option<TileAtlas>,
+    /// 动态 tile，key 是放在 [`TileGroup`] 中的 index
+    #[serde(default)]
+    pub animations: HashMap<u8, TileAnimation>,
+    /// tile 的碰撞形状，没有配置的 tile 使用所在 layer 的默认形状，见 [`LevelModel::overlap`]
+    #[serde(default)]
+    pub collisions: HashMap<u8, CollisionShape>,
+    /// tile 的标签，比如 ldtk 中用 enum 标记的 `Water`、`Pit`
+    #[serde(default)]
+    pub tags: HashMap<u8, Vec<String>>,
+    /// 编辑器中给 tile 配置的自定义数据，原样保存
+    #[serde(default)]
+    pub custom_data: HashMap<u8, String>,
+}
+
+impl Tileset {
+    /// 运行时实际需要加载的贴图，有 atlas 时使用 atlas，否则使用整张 tileset
+    pub fn texture_src(&self) -> &str {
+        match &self.atlas {
+            Some(atlas) => &atlas.src,
+            None => &self.src,
+        }
+    }
+    pub fn has_tag(&self, index: u8, tag: &str) -> bool {
+        match self.tags.get(&index) {
+            Some(tags) => tags.iter().any(|t| t == tag),
+            None => false,
+        }
+    }
+    /// tile 在 [`Tileset::texture_src`] 中的位置和尺寸
+    pub fn texture_rect(&self, index: u8) -> Option<([u32; 2], [u32; 2])> {
+        let (min, size) = *self.tiles.get(&index)?;
+        match &self.atlas {
+            Some(atlas) => {
+                let slot = atlas.remap.get(&index)?;
+                Some((*atlas.slots.get(*slot as usize)?, size))
+            }
+            None => Some((min, size)),
+        }
+    }
+}
+
+/// 按顺序循环播放的一组 tile，同一个 tile 的所有实例都同步播放
+#[derive(serde::Serialize, serde::Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct TileAnimation {
+    pub frames: Vec<TileFrame>,
+}
+
+impl TileAnimation {
+    pub fn total_duration(&self) -> f32 {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+    /// 根据从开始播放到现在的时间得到当前帧在 frames 中的位置
+    pub fn frame_at(&self, elapsed: f32) -> usize {
+        let total = self.total_duration();
+        if total <= 0.0 {
+            return 0;
+        }
+        let mut time = elapsed.rem_euclid(total);
+        for (i, frame) in self.frames.iter().enumerate() {
+            if time < frame.duration {
+                return i;
+            }
+            time -= frame.duration;
+        }
+        self.frames.len() - 1
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Default, Debug, PartialEq)]
+pub struct TileFrame {
+    /// 该帧显示的 tile 在 tileset 中的 index
+    pub index: u8,
+    /// 单位是秒
+    pub duration: f32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Default, Debug)]
+pub struct TileAtlas {
+    /// atlas 图片相对 assets 的路径
+    pub src: String,
+    pub size: [u32; 2],
+    /// 每个 tile 四周额外复制出来的边缘像素，避免采样时和相邻 tile 混色
+    pub padding: u32,
+    /// tileset 中的 index 到 atlas 中 slot 的映射，内容相同的 tile 共用同一个 slot
+    pub remap: HashMap<u8, u16>,
+    /// 每个 slot 在 atlas 中的位置（不含 padding），根据左上角定位
+    pub slots: Vec<[u32; 2]>,
 }
@@ -0,0 +1,104 @@
+use crate::convert::GRID_SIZE;
+use crate::model::{LevelModel, RoomModel, TileGroup, TileType};
+
+/// 按照 layer 遍历时得到的 tile
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LayerTile<'a> {
+    /// 以 level 为原点的 grid 坐标
+    pub grid_pos: [i32; 2],
+    pub tileset_uuid: &'a str,
+    pub index: u8,
+}
+
+impl LevelModel {
+    /// grid 所在的 room，room 之间有重叠时返回排在前面的 room
+    pub fn room_at(&self, grid_pos: [i32; 2]) -> Option<&RoomModel> {
+        self.room_index_at(grid_pos).map(|index| &self.rooms[index])
+    }
+    /// grid 所在的 room 在 [`LevelModel::rooms`] 中的位置
+    pub fn room_index_at(&self, grid_pos: [i32; 2]) -> Option<usize> {
+        self.rooms.iter().position(|room| room.contains(grid_pos))
+    }
+    /// grid 上所有 layer 的 tile 的标签，见 [`crate::Tileset::tags`]
+    pub fn tile_tags(&self, grid_pos: [i32; 2]) -> Vec<&str> {
+        let mut tags = vec![];
+        for room in self.rooms.iter() {
+            let Some(rel_grid_pos) = room.rel_grid_pos(grid_pos) else {
+                continue;
+            };
+            for tile_group in room
+                .floors
+                .iter()
+                .chain(room.walls.iter())
+                .chain(room.roofs.iter())
+            {
+                let Some(index) = tile_group.get(rel_grid_pos) else {
+                    continue;
+                };
+                let tileset = self
+                    .tilesets
+                    .iter()
+                    .find(|tileset| tileset.uuid == tile_group.tileset_uuid);
+                if let Some(tile_tags) = tileset.and_then(|tileset| tileset.tags.get(&index)) {
+                    tags.extend(tile_tags.iter().map(String::as_str));
+                }
+            }
+        }
+        tags
+    }
+    /// 所有 room 中某一种 layer 的 tile，遍历的顺序是不固定的
+    pub fn tiles(&self, type_: TileType) -> impl Iterator<Item = LayerTile<'_>> {
+        self.rooms.iter().flat_map(move |room| room.tiles(type_))
+    }
+}
+
+impl RoomModel {
+    /// grid 是否在 room 的范围内
+    pub fn contains(&self, grid_pos: [i32; 2]) -> bool {
+        self.rel_grid_pos(grid_pos).is_some()
+    }
+    /// room 在 level 中的范围，单位是像素，返回左下角和右上角
+    pub fn world_bounds(&self) -> ([f32; 2], [f32; 2]) {
+        let size = GRID_SIZE as f32;
+        let min = [
+            self.world_pos[0] as f32 * size,
+            self.world_pos[1] as f32 * size,
+        ];
+        let max = [
+            min[0] + self.size[0] as f32 * size,
+            min[1] + self.size[1] as f32 * size,
+        ];
+        (min, max)
+    }
+    /// room 中某一种 layer 的 tile，遍历的顺序是不固定的
+    pub fn tiles(&self, type_: TileType) -> impl Iterator<Item = LayerTile<'_>> {
+        let tile_groups = match type_ {
+            TileType::Floor => &self.floors,
+            TileType::Wall => &self.walls,
+            TileType::Roof => &self.roofs,
+        };
+        let world_pos = self.world_pos;
+        tile_groups.iter().flat_map(move |tile_group: &TileGroup| {
+            tile_group.tiles.iter().flat_map(move |(grid_x, col)| {
+                col.iter().map(move |(grid_y, index)| LayerTile {
+                    grid_pos: [world_pos[0] + *grid_x as i32, world_pos[1] + *grid_y as i32],
+                    tileset_uuid: &tile_group.tileset_uuid,
+                    index: *index,
+                })
+            })
+        })
+    }
+    /// 转换成 room 内的 grid 坐标，不在 room 内时返回 None
+    pub(crate) fn rel_grid_pos(&self, grid_pos: [i32; 2]) -> Option<[u32; 2]> {
+        let rel_grid_x = grid_pos[0] - self.world_pos[0];
+        let rel_grid_y = grid_pos[1] - self.world_pos[1];
+        if rel_grid_x < 0
+            || rel_grid_y < 0
+            || rel_grid_x as u32 >= self.size[0]
+            || rel_grid_y as u32 >= self.size[1]
+        {
+            return None;
+        }
+        Some([rel_grid_x as u32, rel_grid_y as u32])
+    }
+}
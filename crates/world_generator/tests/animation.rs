@@ -0,0 +1,39 @@
+use world_generator::{ConversionProfile, LevelModel, TileAnimation, TileFrame, Tileset};
+
+fn frame(index: u8, duration: f32) -> TileFrame {
+    TileFrame { index, duration }
+}
+
+#[test]
+fn frame_at() {
+    let animation = TileAnimation {
+        frames: vec![frame(1, 0.1), frame(2, 0.2), frame(3, 0.1)],
+    };
+    assert_eq!(animation.frame_at(0.0), 0);
+    assert_eq!(animation.frame_at(0.15), 1);
+    assert_eq!(animation.frame_at(0.35), 2);
+    // 播放完之后从头循环
+    assert_eq!(animation.frame_at(0.45), 0);
+    assert_eq!(TileAnimation::default().frame_at(1.0), 0);
+}
+
+#[test]
+fn apply_profile() {
+    let mut level = LevelModel::builder()
+        .tileset(Tileset::from_grid("floor", "art/floor.png", 16, 4, 1))
+        .tileset(Tileset::from_grid("other", "art/other.png", 16, 4, 1))
+        .build();
+    let profile = serde_json::from_str::<ConversionProfile>(
+        r#"{
+            "animations": {
+                "art/floor.png": [
+                    { "tile": 0, "frames": [{ "index": 0, "duration": 0.2 }, { "index": 3, "duration": 0.2 }] }
+                ]
+            }
+        }"#,
+    )
+    .unwrap();
+    level.apply_profile(&profile);
+    assert_eq!(level.tilesets[0].animations[&0].frames[1].index, 3);
+    assert!(level.tilesets[1].animations.is_empty());
+}
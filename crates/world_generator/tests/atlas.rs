@@ -0,0 +1,111 @@
+use image::{Rgba, RgbaImage};
+
+use world_generator::{LevelModel, RoomBuilder, Tileset, ATLAS_PADDING};
+
+/// 生成一张 4x1 的 tileset，其中第 0 个和第 2 个 tile 内容相同
+fn prepare_assets(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    let colors = [
+        Rgba([255, 0, 0, 255]),
+        Rgba([0, 255, 0, 255]),
+        Rgba([255, 0, 0, 255]),
+        Rgba([0, 0, 255, 255]),
+    ];
+    let mut tileset = RgbaImage::new(64, 16);
+    for (x, y, pixel) in tileset.enumerate_pixels_mut() {
+        *pixel = colors[(x / 16) as usize];
+        if x % 16 == 0 && y == 0 {
+            *pixel = Rgba([255, 255, 255, 255]);
+        }
+    }
+    tileset.save(dir.join("tiles.png")).unwrap();
+    dir
+}
+
+#[test]
+fn pack_used_tiles() {
+    let assets = prepare_assets("world_generator_pack_used_tiles");
+    let mut level = LevelModel::builder()
+        .tileset(Tileset::from_grid("tiles", "tiles.png", 16, 4, 1))
+        .tileset(Tileset::from_grid("unused", "tiles.png", 16, 4, 1))
+        .room(
+            RoomBuilder::new("Room", [0, 0], [3, 1])
+                .floor_rect("tiles", [0, 0], [1, 1], 0)
+                .floor_rect("tiles", [1, 0], [1, 1], 1)
+                .floor_rect("tiles", [2, 0], [1, 1], 2),
+        )
+        .build();
+    level.pack_atlas(&assets, "atlas").unwrap();
+
+    assert!(level.tilesets[1].atlas.is_none());
+    let tileset = &level.tilesets[0];
+    let atlas = tileset.atlas.as_ref().unwrap();
+    assert_eq!(atlas.src, "atlas/tiles.png");
+    // 第 3 个 tile 没有被用到，第 0 和第 2 个内容相同
+    assert_eq!(atlas.slots.len(), 2);
+    assert_eq!(atlas.remap[&0], atlas.remap[&2]);
+    assert!(!atlas.remap.contains_key(&3));
+    assert_eq!(tileset.texture_src(), "atlas/tiles.png");
+    assert_eq!(tileset.texture_rect(3), None);
+
+    let image = image::open(assets.join(&atlas.src)).unwrap().into_rgba8();
+    assert_eq!([image.width(), image.height()], atlas.size);
+    let (min, size) = tileset.texture_rect(1).unwrap();
+    assert_eq!(size, [16, 16]);
+    assert_eq!(
+        *image.get_pixel(min[0] + 1, min[1] + 1),
+        Rgba([0, 255, 0, 255])
+    );
+    // padding 复制的是 tile 的边缘像素
+    let (min, _) = tileset.texture_rect(0).unwrap();
+    assert_eq!(
+        *image.get_pixel(min[0] - ATLAS_PADDING, min[1] - ATLAS_PADDING),
+        Rgba([255, 255, 255, 255])
+    );
+    assert_eq!(
+        *image.get_pixel(min[0] + 15 + ATLAS_PADDING, min[1]),
+        Rgba([255, 0, 0, 255])
+    );
+}
+
+#[test]
+fn pack_reproducible_names() {
+    let assets = prepare_assets("world_generator_pack_reproducible_names");
+    // 之前生成的 atlas 会被删除
+    std::fs::create_dir_all(assets.join("atlas")).unwrap();
+    std::fs::write(assets.join("atlas/stale.png"), []).unwrap();
+    let build = || {
+        LevelModel::builder()
+            .tileset(Tileset::from_grid("first", "tiles.png", 16, 4, 1))
+            .tileset(Tileset::from_grid("second", "tiles.png", 16, 4, 1))
+            .room(
+                RoomBuilder::new("Room", [0, 0], [2, 1])
+                    .floor_rect("first", [0, 0], [1, 1], 0)
+                    .floor_rect("second", [1, 0], [1, 1], 1),
+            )
+            .build()
+    };
+    let srcs = |level: &LevelModel| {
+        level
+            .tilesets
+            .iter()
+            .map(|tileset| tileset.atlas.as_ref().unwrap().src.clone())
+            .collect::<Vec<_>>()
+    };
+    let mut level = build();
+    level.pack_atlas(&assets, "atlas").unwrap();
+    // 同一张图片的两个 tileset 使用不同的文件名
+    assert_eq!(srcs(&level), ["atlas/tiles.png", "atlas/tiles_1.png"]);
+    assert!(!assets.join("atlas/stale.png").exists());
+
+    let mut again = build();
+    again.pack_atlas(&assets, "atlas").unwrap();
+    assert_eq!(srcs(&again), srcs(&level));
+    let mut files = std::fs::read_dir(assets.join("atlas"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    files.sort();
+    assert_eq!(files, ["tiles.png", "tiles_1.png"]);
+}
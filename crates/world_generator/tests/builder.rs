@@ -0,0 +1,51 @@
+use world_generator::{LevelModel, RoomBuilder, TileType, Tileset};
+
+fn sample_level() -> LevelModel {
+    LevelModel::builder()
+        .brith_point([2, 2])
+        .tileset(Tileset::from_grid("floor", "art/floor.png", 16, 4, 4))
+        .tileset(Tileset::from_tilt_grid("wall", "art/wall.png", 16, 4, 4))
+        .room(
+            RoomBuilder::new("Room A", [0, 0], [6, 6])
+                .floor_rect("floor", [0, 0], [6, 5], 0)
+                .wall_rect("wall", [0, 5], [6, 1], 1)
+                .light([3, 3, 0], [255, 200, 100, 255]),
+        )
+        .room(RoomBuilder::new("Room B", [-4, 0], [4, 2]).floor_rect("floor", [0, 0], [4, 2], 5))
+        .build()
+}
+
+#[test]
+fn build_level() {
+    let level = sample_level();
+    assert_eq!(level.brith_point, [2, 2]);
+    assert_eq!(level.rooms.len(), 2);
+    assert_eq!(level.tilesets.len(), 2);
+    assert_eq!(level.rooms[0].lights.len(), 1);
+    assert_eq!(level.rooms[0].floors.len(), 1);
+}
+
+#[test]
+fn build_tilesets() {
+    let level = sample_level();
+    let floor = &level.tilesets[0];
+    assert_eq!(floor.tiles.len(), 16);
+    assert_eq!(floor.tiles[&5], ([16, 16], [16, 16]));
+    assert!(!floor.tilt);
+    let wall = &level.tilesets[1];
+    assert_eq!(wall.tiles.len(), 8);
+    assert_eq!(wall.tiles[&4], ([0, 32], [16, 32]));
+    assert!(wall.tilt);
+}
+
+#[test]
+fn query_built_level() {
+    let level = sample_level();
+    assert!(level.contains_floor([0, 0]));
+    assert!(level.contains_floor([5, 4]));
+    assert!(level.contains_floor([-4, 1]));
+    assert!(!level.contains_floor([5, 5]));
+    assert!(!level.contains_floor([-5, 0]));
+    assert!(level.pos_tile([2, 5]) == Some(TileType::Wall));
+    assert!(level.pos_tile([2, 2]) == Some(TileType::Floor));
+}
@@ -0,0 +1,105 @@
+use world_generator::{
+    Collider, CollisionShape, ConversionProfile, Corner, LevelModel, RoomBuilder, Side, Tileset,
+};
+
+/// 4x3 的 room，最上面一行是 wall，wall 的 tile 1 只有下半部分不可通过，tile 2 可以穿过
+fn level() -> LevelModel {
+    LevelModel::builder()
+        .tileset(Tileset::from_grid("floor", "floor.png", 16, 4, 1))
+        .tileset(
+            Tileset::from_grid("wall", "wall.png", 16, 4, 1)
+                .with_collision(1, CollisionShape::Half(Side::Bottom))
+                .with_collision(2, CollisionShape::None),
+        )
+        .room(
+            RoomBuilder::new("Room", [0, 0], [4, 3])
+                .floor_rect("floor", [0, 0], [4, 2], 0)
+                .wall_rect("wall", [0, 2], [1, 1], 0)
+                .wall_rect("wall", [1, 2], [1, 1], 1)
+                .wall_rect("wall", [2, 2], [1, 1], 2),
+        )
+        .build()
+}
+
+fn rect(min: [f32; 2], max: [f32; 2]) -> Collider {
+    Collider::Rect { min, max }
+}
+
+#[test]
+fn overlap_tiles() {
+    let level = level();
+    // floor 上可以通过，贴着 floor 的边界也不算碰撞
+    assert!(!level.overlap(&rect([2.0, 2.0], [14.0, 14.0])));
+    assert!(!level.overlap(&rect([0.0, 0.0], [64.0, 32.0])));
+    // room 外面没有 tile
+    assert!(level.overlap(&rect([-1.0, 2.0], [4.0, 4.0])));
+    // 默认的 wall 整个 tile 都不可通过
+    assert!(level.overlap(&rect([2.0, 30.0], [6.0, 34.0])));
+    // 半个 tile 的 wall 只挡住下半部分
+    assert!(level.overlap(&rect([18.0, 38.0], [22.0, 39.0])));
+    assert!(!level.overlap(&rect([18.0, 41.0], [22.0, 47.0])));
+    // 配置成 none 的 wall 可以穿过
+    assert!(!level.overlap(&rect([34.0, 34.0], [46.0, 46.0])));
+    assert!(level.overlap(&Collider::Circle {
+        center: [24.0, 41.0],
+        radius: 2.0
+    }));
+    assert!(!level.overlap(&Collider::Circle {
+        center: [24.0, 44.0],
+        radius: 2.0
+    }));
+}
+
+#[test]
+fn slope_and_polygon() {
+    let slope = CollisionShape::Slope(Corner::BottomLeft).polygon().unwrap();
+    assert_eq!(slope, vec![[0.0, 0.0], [16.0, 0.0], [0.0, 16.0]]);
+    assert_eq!(CollisionShape::None.polygon(), None);
+
+    let level = LevelModel::builder()
+        .tileset(
+            Tileset::from_grid("floor", "floor.png", 16, 2, 1)
+                .with_collision(0, CollisionShape::Slope(Corner::BottomLeft))
+                .with_collision(
+                    1,
+                    CollisionShape::Polygon(vec![[0.0, 0.0], [16.0, 0.0], [16.0, 4.0]]),
+                ),
+        )
+        .room(
+            RoomBuilder::new("Room", [0, 0], [2, 1])
+                .floor_rect("floor", [0, 0], [1, 1], 0)
+                .floor_rect("floor", [1, 0], [1, 1], 1),
+        )
+        .build();
+    // 斜坡右上方的三角形可以通过
+    assert!(!level.overlap(&rect([12.0, 12.0], [15.0, 15.0])));
+    assert!(level.overlap(&rect([2.0, 2.0], [4.0, 4.0])));
+    assert!(level.overlap(&rect([28.0, 1.0], [30.0, 2.0])));
+    assert!(!level.overlap(&rect([18.0, 8.0], [30.0, 15.0])));
+}
+
+#[test]
+fn parse_collision() {
+    assert_eq!(CollisionShape::parse("full"), Some(CollisionShape::Full));
+    assert_eq!(
+        CollisionShape::parse("\"none\""),
+        Some(CollisionShape::None)
+    );
+    assert_eq!(
+        CollisionShape::parse(r#"{"half": "top"}"#),
+        Some(CollisionShape::Half(Side::Top))
+    );
+    assert_eq!(CollisionShape::parse("wall"), None);
+
+    let mut level = level();
+    let profile = serde_json::from_str::<ConversionProfile>(
+        r#"{ "collisions": { "wall.png": { "0": { "slope": "top_right" } } } }"#,
+    )
+    .unwrap();
+    level.apply_profile(&profile);
+    assert_eq!(
+        level.tilesets[1].collisions[&0],
+        CollisionShape::Slope(Corner::TopRight)
+    );
+    assert_eq!(level.tilesets[0].collisions.len(), 0);
+}
@@ -0,0 +1,221 @@
+use world_generator::{Collider, LevelModel, RoomBuilder, RoomModel, TileType, Tileset, GRID_SIZE};
+
+#[test]
+fn convert_ldtk() {
+    let project = ldtk::Project::from("../../assets/level.ldtk").unwrap();
+    let level = LevelModel::from_ldtk(&project).unwrap();
+    assert_eq!(level.rooms.len(), project.levels.len());
+    assert_eq!(level.tilesets.len(), project.defs.tilesets.len());
+    assert!(level.contains_floor(level.brith_point));
+    // 出生点所在的 grid 可以通过
+    let [x, y] = level.brith_point.map(|v| (v * GRID_SIZE as i32) as f32);
+    assert!(!level.overlap(&Collider::Rect {
+        min: [x + 1.0, y + 1.0],
+        max: [x + 15.0, y + 15.0],
+    }));
+}
+
+#[test]
+fn convert_ldtk_tile_tags() {
+    let project = ldtk::Project::from("../ldtk/tests/sample.ldtk").unwrap();
+    let level = LevelModel::from_ldtk(&project).unwrap();
+    let floor = &level.tilesets[3];
+    // tile id 和 index 都是从左上角开始逐行递增
+    assert!(floor.has_tag(11, "Stone"));
+    assert!(floor.has_tag(5, "Water"));
+    assert!(!floor.has_tag(5, "Stone"));
+    assert_eq!(floor.custom_data[&5], "slow");
+    // Floor layer 的第一个 tile 使用的是 tile 1
+    let room = &level.rooms[0];
+    let [x, y] = room.world_pos;
+    let tags = level.tile_tags([x + 5, y + room.size[1] as i32 - 1]);
+    assert_eq!(tags, vec!["Stone"]);
+}
+
+#[test]
+fn convert_ldtk_external_levels() {
+    let project = ldtk::Project::from("../ldtk/tests/external.ldtk").unwrap();
+    let level = LevelModel::from_ldtk(&project).unwrap();
+    assert_eq!(level.rooms.len(), 2);
+    assert_eq!(level.rooms[0].world_pos, [0, -3]);
+    assert_eq!(level.rooms[1].world_pos, [4, -2]);
+    let level =
+        LevelModel::from_ldtk(&ldtk::Project::from("../ldtk/tests/multi_world.ldtk").unwrap())
+            .unwrap();
+    assert_eq!(level.rooms.len(), 3);
+    assert_eq!(level.rooms[2].display_name, "Dungeon_1");
+}
+
+#[test]
+fn convert_ldtk_overlapping_worlds() {
+    let mut project = ldtk::Project::from("../ldtk/tests/multi_world.ldtk").unwrap();
+    // Surface 和 Dungeon 是两个 world，把 Surface_0 移到 Dungeon_0 的位置
+    let dungeon = &project.worlds[1].levels[0];
+    let (x, y) = (dungeon.world_x, dungeon.world_y);
+    let surface = &mut project.worlds[0].levels[0];
+    surface.world_x = x;
+    surface.world_y = y;
+    let error = LevelModel::from_ldtk(&project).unwrap_err().to_string();
+    assert!(error.contains("Surface/Surface_0"), "{error}");
+    assert!(error.contains("Dungeon/Dungeon_0"), "{error}");
+}
+
+#[test]
+fn convert_tiled() {
+    let world = tiled::World::from("../tiled/tests/sample.world").unwrap();
+    let level = LevelModel::from_tiled(&world);
+    // 两个 map 引用的是同一套 tileset
+    assert_eq!(level.tilesets.len(), 2);
+    assert!(!level.tilesets[0].tilt);
+    assert!(level.tilesets[1].tilt);
+
+    let room = &level.rooms[0];
+    assert_eq!(room.display_name, "Sample_Room");
+    assert_eq!(room.world_pos, [0, -3]);
+    assert_eq!(room.size, [4, 3]);
+    assert_eq!(room.lights.len(), 1);
+    assert_eq!(room.lights[0].pos, [2, 2, 32]);
+    assert_eq!(room.lights[0].color, [255, 136, 0, 200]);
+    assert_eq!(level.rooms[1].world_pos, [4, 0]);
+
+    assert!(level.pos_tile([0, -1]) == Some(TileType::Wall));
+    assert!(level.pos_tile([2, -2]) == Some(TileType::Floor));
+    assert!(level.contains_floor([3, -3]));
+    assert!(level.contains_floor([5, 1]));
+    assert_eq!(level.brith_point, [5, 0]);
+}
+
+#[test]
+fn convert_tiled_map() {
+    let map = tiled::Map::from("../tiled/tests/sample.tmj").unwrap();
+    let level = LevelModel::from_tiled(&tiled::World::single("sample.tmj", map));
+    assert_eq!(level.rooms.len(), 1);
+    assert_eq!(level.brith_point, [1, -3]);
+    let floor = &level.rooms[0].floors[0];
+    assert_eq!(floor.tiles[&2][&1], 7);
+    assert_eq!(floor.tiles[&0][&0], 1);
+    // floor.tsx 中 tile 7 配置了两帧的动画
+    let animation = &level.tilesets[0].animations[&7];
+    assert_eq!(animation.frames.len(), 2);
+    assert_eq!(animation.frames[1].index, 8);
+    assert!((animation.total_duration() - 0.4).abs() < f32::EPSILON);
+    assert!(level.tilesets[1].animations.contains_key(&0));
+}
+
+#[test]
+fn convert_tiled_spacing() {
+    let map = tiled::Map::from("../tiled/tests/spacing.tmx").unwrap();
+    let level = LevelModel::from_tiled(&tiled::World::single("spacing.tmx", map));
+    // tileset 四周留有 1 像素的 margin，tile 之间留有 1 像素的 spacing
+    let tileset = &level.tilesets[0];
+    assert_eq!(tileset.tiles[&0].0, [1, 1]);
+    assert_eq!(tileset.tiles[&4].0, [18, 18]);
+    let floor = &level.rooms[0].floors[0];
+    assert_eq!(floor.tiles[&0][&0], 0);
+    assert_eq!(floor.tiles[&1][&0], 4);
+    assert_eq!(tileset.animations[&4].frames[1].index, 5);
+}
+
+/// 按照 tileset 的图片比较两个 level 中 room 的 tile，tileset 的 uuid 在转换时会重新生成
+fn assert_same_rooms(a: &LevelModel, b: &LevelModel) {
+    assert_eq!(a.rooms.len(), b.rooms.len());
+    for (room_a, room_b) in a.rooms.iter().zip(b.rooms.iter()) {
+        assert_eq!(room_a.world_pos, room_b.world_pos);
+        assert_eq!(room_a.size, room_b.size);
+        assert_eq!(
+            room_a
+                .lights
+                .iter()
+                .map(|l| (l.pos, l.color))
+                .collect::<Vec<_>>(),
+            room_b
+                .lights
+                .iter()
+                .map(|l| (l.pos, l.color))
+                .collect::<Vec<_>>()
+        );
+        for type_ in [TileType::Floor, TileType::Wall, TileType::Roof] {
+            let tiles = |level: &LevelModel, room: &RoomModel| {
+                let mut tiles = room
+                    .tiles(type_)
+                    .map(|tile| {
+                        let tileset = level
+                            .tilesets
+                            .iter()
+                            .find(|tileset| tileset.uuid == tile.tileset_uuid)
+                            .unwrap();
+                        (tile.grid_pos, tileset.src.clone(), tile.index)
+                    })
+                    .collect::<Vec<_>>();
+                tiles.sort();
+                tiles
+            };
+            assert_eq!(tiles(a, room_a), tiles(b, room_b));
+        }
+    }
+}
+
+#[test]
+fn export_ldtk() {
+    let level = LevelModel::builder()
+        .brith_point([5, -1])
+        .tileset(Tileset::from_grid("floor", "art/floor.png", 16, 4, 2))
+        .tileset(Tileset::from_grid(
+            "decoration",
+            "art/floor-decorations.png",
+            16,
+            4,
+            2,
+        ))
+        .tileset(Tileset::from_tilt_grid("wall", "art/wall.png", 16, 4, 2))
+        .room(
+            RoomBuilder::new("Start Room", [0, 0], [4, 3])
+                .floor_rect("floor", [0, 0], [4, 2], 5)
+                .floor_rect("decoration", [1, 1], [1, 1], 2)
+                .wall_rect("wall", [0, 2], [4, 1], 1)
+                .light([1, 1, 32], [255, 136, 0, 200]),
+        )
+        .room(
+            RoomBuilder::new("Start Room", [4, -2], [3, 2])
+                .floor_rect("floor", [0, 0], [3, 2], 0)
+                .light([2, 0, 0], [0, 0, 255, 255]),
+        )
+        .build();
+    let content = serde_json::to_string(&level.to_ldtk()).unwrap();
+    let project = ldtk::Project::parse(&content).unwrap();
+    // 重名的 room 需要改成合法并且唯一的 identifier
+    assert_eq!(project.levels[0].identifier, "Start_Room");
+    assert_eq!(project.levels[1].identifier, "Start_Room_1");
+    assert_eq!(project.defs.tilesets[2].identifier, "Wall");
+    let exported = LevelModel::from_ldtk(&project).unwrap();
+    assert_same_rooms(&level, &exported);
+    assert_eq!(exported.brith_point, level.brith_point);
+    assert!(exported.tilesets[2].tilt);
+}
+
+#[test]
+fn export_ldtk_round_trip() {
+    for path in ["../../assets/level.ldtk", "../ldtk/tests/sample.ldtk"] {
+        let project = ldtk::Project::from(path).unwrap();
+        let level = LevelModel::from_ldtk(&project).unwrap();
+        let content = serde_json::to_string(&level.to_ldtk()).unwrap();
+        let exported = LevelModel::from_ldtk(&ldtk::Project::parse(&content).unwrap()).unwrap();
+        assert_same_rooms(&level, &exported);
+        assert_eq!(exported.brith_point, level.brith_point);
+        // tile 的标签导出成 enum，重新转换后标记在同样的 tile 上
+        for (a, b) in level.tilesets.iter().zip(exported.tilesets.iter()) {
+            assert_eq!(a.tags, b.tags);
+        }
+    }
+    let project = ldtk::Project::from("../ldtk/tests/sample.ldtk").unwrap();
+    let exported = LevelModel::from_ldtk(&project).unwrap().to_ldtk();
+    assert_eq!(exported["defs"]["enums"][0]["identifier"], "TileTag");
+}
+
+#[test]
+fn export_ldtk_reproducible() {
+    let project = ldtk::Project::from("../../assets/level.ldtk").unwrap();
+    let level = LevelModel::from_ldtk(&project).unwrap();
+    // 同一个 level 每次导出的内容相同，包括 iid
+    assert_eq!(level.to_ldtk(), level.to_ldtk());
+}
@@ -0,0 +1,49 @@
+use world_generator::{LevelModel, RoomBuilder, TileType, Tileset};
+
+fn level() -> LevelModel {
+    LevelModel::builder()
+        .tileset(Tileset::from_grid("floor", "floor.png", 16, 4, 1))
+        .room(
+            RoomBuilder::new("A", [0, 0], [4, 3])
+                .floor_rect("floor", [0, 0], [4, 2], 0)
+                .wall_rect("floor", [0, 2], [4, 1], 1),
+        )
+        .room(RoomBuilder::new("B", [4, -2], [2, 2]).floor_rect("floor", [0, 0], [2, 2], 2))
+        .build()
+}
+
+#[test]
+fn room_at() {
+    let level = level();
+    assert_eq!(level.room_at([0, 0]).unwrap().display_name, "A");
+    assert_eq!(level.room_at([3, 2]).unwrap().display_name, "A");
+    assert_eq!(level.room_index_at([4, -1]), Some(1));
+    // 右上角的边界不属于 room
+    assert!(level.room_at([4, 0]).is_none());
+    assert!(level.room_at([-1, 0]).is_none());
+}
+
+#[test]
+fn room_bounds() {
+    let level = level();
+    assert_eq!(level.rooms[0].world_bounds(), ([0.0, 0.0], [64.0, 48.0]));
+    assert_eq!(level.rooms[1].world_bounds(), ([64.0, -32.0], [96.0, 0.0]));
+}
+
+#[test]
+fn tiles_by_layer() {
+    let level = level();
+    assert_eq!(level.tiles(TileType::Floor).count(), 12);
+    assert_eq!(level.tiles(TileType::Roof).count(), 0);
+    let mut walls = level
+        .tiles(TileType::Wall)
+        .map(|tile| tile.grid_pos)
+        .collect::<Vec<_>>();
+    walls.sort();
+    assert_eq!(walls, vec![[0, 2], [1, 2], [2, 2], [3, 2]]);
+    // 返回的是以 level 为原点的 grid 坐标
+    let tile = level.rooms[1].tiles(TileType::Floor).next().unwrap();
+    assert_eq!(tile.index, 2);
+    assert_eq!(tile.tileset_uuid, "floor");
+    assert_eq!(level.room_index_at(tile.grid_pos), Some(1));
+}
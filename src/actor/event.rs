@@ -4,7 +4,7 @@ use bevy::pbr::NotShadowCaster;
 use bevy::prelude::*;
 
 use crate::animation::OnceSpriteAnimation;
-use crate::res::{Cache, ResActor, SCALE_RATIO};
+use crate::res::{Cache, LevelEntity, ResActor, SCALE_RATIO};
 
 pub struct CloudPuffEvent;
 
@@ -23,13 +23,14 @@ pub fn handle_cloud_puff_ev(
             Name::from("Cloud Puff"),
             PbrBundle {
                 mesh: cache.actor_caches.cloud_puff_mesh.clone(),
-                material: cache.actor_caches.cloud_puff_materials[0].clone(),
+                material: cache.get_cloud_puff_materials()[0].clone(),
                 transform: Transform::from_xyz(pos.x, pos.y, pos.z)
                     .with_rotation(Quat::from_rotation_x(-PI / 4.0)),
                 ..default()
             },
-            OnceSpriteAnimation::new(0.1, cache.actor_caches.cloud_puff_materials.clone()),
+            OnceSpriteAnimation::new(0.1, cache.get_cloud_puff_materials().clone()),
             NotShadowCaster::default(),
+            LevelEntity,
         ));
     }
 }
@@ -1,10 +1,10 @@
 use bevy::pbr::NotShadowCaster;
 use bevy::prelude::*;
 
-use world_generator::TileType;
+use world_generator::Collider;
 
 use crate::cursor::ResCursor;
-use crate::res::{Cache, ResActor, GRID_SIZE, SCALE_RATIO};
+use crate::res::{ActiveLevel, Cache, LevelEntity, ResActor, SCALE_RATIO};
 
 #[derive(Component)]
 pub struct Bullet {
@@ -21,8 +21,9 @@ pub fn fire_bullet(
     cursor: Res<ResCursor>,
     actor: Res<ResActor>,
 ) {
-    if buttons.just_pressed(MouseButton::Left) {
-        if let Some(fire_offset) = actor.get_fire_offset() {
+    // 翻滚时不能开火
+    if buttons.just_pressed(MouseButton::Left) && !actor.is_rolling() {
+        if let (Some(gun), Some(fire_offset)) = (actor.get_cur_gun(), actor.get_fire_offset()) {
             let actor_pos = actor.get_actual_pos();
 
             let velocity = {
@@ -32,7 +33,7 @@ pub fn fire_bullet(
 
             c.spawn(PbrBundle {
                 mesh: cache.get_bullet_mesh((5, 5)).clone(),
-                material: cache.get_bullet_material("Budget Revolver").clone(),
+                material: cache.get_bullet_material(&gun.name).clone(),
                 transform: Transform {
                     translation: actor_pos + fire_offset,
                     ..default()
@@ -40,6 +41,7 @@ pub fn fire_bullet(
                 ..default()
             })
             .insert(NotShadowCaster::default())
+            .insert(LevelEntity)
             .insert(Bullet {
                 origin: (actor_pos + fire_offset).truncate(),
                 velocity,
@@ -53,26 +55,19 @@ pub fn fire_bullet(
 pub fn bullet_move(
     mut c: Commands,
     time: Res<Time>,
-    cache: Res<Cache>,
+    level: Res<ActiveLevel>,
     mut query: Query<(&mut Transform, &Bullet, Entity)>,
 ) {
-    let to_grid_pos = |pos: [f32; 2]| -> [i32; 2] {
-        // TODO 目前不知道为什么整体偏移了(8,-24)
-        let pos = [pos[0] + 8.0, pos[1] + 8.0];
-        [
-            (pos[0] / GRID_SIZE).floor() as i32,
-            (pos[1] / GRID_SIZE).floor() as i32,
-        ]
-    };
     for (mut t, b, e) in query.iter_mut() {
         let pos = t.translation.truncate();
-        if let Some(tile_type) =
-            cache.levels[0].pos_tile(to_grid_pos([pos.x / SCALE_RATIO, pos.y / SCALE_RATIO]))
-        {
-            if tile_type == TileType::Roof || tile_type == TileType::Wall {
-                c.entity(e).despawn_recursive();
-                break;
-            }
+        // TODO 目前不知道为什么整体偏移了(8,-24)
+        let center = [pos.x / SCALE_RATIO + 8.0, pos.y / SCALE_RATIO + 8.0];
+        if level.overlap(&Collider::Circle {
+            center,
+            radius: 2.0,
+        }) {
+            c.entity(e).despawn_recursive();
+            break;
         }
         if (b.origin - pos).length() > b.max_distance * SCALE_RATIO {
             c.entity(e).despawn_recursive();
@@ -4,15 +4,20 @@ use bevy::pbr::NotShadowCaster;
 use bevy::prelude::*;
 use bevy_kira_audio::AudioControl;
 use rand::Rng;
+use world_generator::Collider;
 
 use crate::actor::event::CloudPuffEvent;
 use crate::cursor::ResCursor;
 use crate::res::{
-    ActorAction, ActorDirection, ActorGunHand, Cache, ResActor, GRID_SIZE, GRID_SIZE_HALF,
+    ActiveLevel, ActorAction, ActorDirection, ActorGunHand, Cache, LevelEntity, ResActor,
+    GRID_SIZE_HALF,
 };
 use crate::sprite_animation::{ActorMaterialSprite, ActorSpriteAnimation};
 use crate::utils::{MoveDirection, MoveDirectionX, MoveDirectionY};
 
+/// 角色出生时播放的动画
+pub const SPAWN_TAG: &str = "idle-f-2h";
+
 pub enum ActorTag {
     IdleF1h,
     IdleB1h,
@@ -22,9 +27,28 @@ pub enum ActorTag {
     WalkB1h,
     WalkFs1h,
     WalkBs0h,
+    RollF,
+    RollB,
+    RollFs,
+    RollBs,
 }
 
 impl ActorTag {
+    /// 所有会用到的 tag，aseprite 导出的 json 中必须都存在
+    pub const ALL: [ActorTag; 12] = [
+        Self::IdleF1h,
+        Self::IdleB1h,
+        Self::IdleFs1h,
+        Self::IdleBs0h,
+        Self::WalkF1h,
+        Self::WalkB1h,
+        Self::WalkFs1h,
+        Self::WalkBs0h,
+        Self::RollF,
+        Self::RollB,
+        Self::RollFs,
+        Self::RollBs,
+    ];
     pub fn tag(&self) -> &str {
         match self {
             Self::IdleF1h => "idle-f-1h",
@@ -35,6 +59,10 @@ impl ActorTag {
             Self::WalkB1h => "walk-b-1h",
             Self::WalkFs1h => "walk-fs-1h",
             Self::WalkBs0h => "walk-bs-0h",
+            Self::RollF => "roll-f",
+            Self::RollB => "roll-b",
+            Self::RollFs => "roll-fs",
+            Self::RollBs => "roll-bs",
         }
     }
 }
@@ -71,10 +99,9 @@ pub fn setup(mut c: Commands, cache: Res<Cache>, actor: ResMut<ResActor>) {
         // ));
         if let Some(actor_gun) = actor.get_cur_gun() {
             let name = actor_gun.name.clone();
-            let size = actor_gun.size;
 
             p.spawn(PbrBundle {
-                mesh: cache.get_gun_mesh((size[0], size[1]), false).clone(),
+                mesh: cache.get_gun_mesh(&name, 0, false).clone(),
                 material: cache.get_gun_material(&name, 0).clone(),
                 transform: Transform {
                     translation: actor_gun.get_gun_offset(false),
@@ -83,15 +110,15 @@ pub fn setup(mut c: Commands, cache: Res<Cache>, actor: ResMut<ResActor>) {
                 ..default()
             })
             .insert(CopGun {
-                mesh: cache.get_gun_mesh((size[0], size[1]), false).clone(),
-                mesh_flip: cache.get_gun_mesh((size[0], size[1]), true).clone(),
+                mesh: cache.get_gun_mesh(&name, 0, false).clone(),
+                mesh_flip: cache.get_gun_mesh(&name, 0, true).clone(),
             })
             .insert(NotShadowCaster::default())
             .insert(Name::new(name));
 
             p.spawn(PbrBundle {
                 mesh: cache.char_hand_mesh.clone(),
-                material: cache.char_hand_material.clone(),
+                material: cache.get_char_hand_material(actor.get_hand()).clone(),
                 transform: Transform {
                     translation: actor_gun.get_hand_offset(false),
                     ..default()
@@ -104,19 +131,34 @@ pub fn setup(mut c: Commands, cache: Res<Cache>, actor: ResMut<ResActor>) {
         }
     })
     .insert(ActorMaterialSprite::default())
-    .insert(ActorSpriteAnimation::from_loop("Convict", "idle-f-2h", 0.1))
+    .insert(ActorSpriteAnimation::from_loop(
+        actor.get_sprite(),
+        SPAWN_TAG,
+        0.1,
+    ))
     .insert(CopActor)
     .insert(NotShadowCaster::default())
-    .insert(Name::new("Character"));
+    .insert(Name::new("Character"))
+    .insert(LevelEntity);
 }
 
 pub fn update_gun_direction(
     actor: Res<ResActor>,
-    mut gun_query: Query<(&mut Transform, &mut Handle<Mesh>, &CopGun), Without<CopHand>>,
-    mut hand_query: Query<(&mut Transform, &CopHand), Without<CopGun>>,
+    mut gun_query: Query<
+        (&mut Transform, &mut Handle<Mesh>, &mut Visibility, &CopGun),
+        Without<CopHand>,
+    >,
+    mut hand_query: Query<(&mut Transform, &mut Visibility, &CopHand), Without<CopGun>>,
 ) {
+    // 翻滚时收起gun和hand
+    let visibility = if actor.is_rolling() {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
     if let Some(actor_gun) = actor.get_cur_gun() {
-        for (mut t, mut mesh, cop_gun) in gun_query.iter_mut() {
+        for (mut t, mut mesh, mut v, cop_gun) in gun_query.iter_mut() {
+            *v = visibility;
             match actor.get_gun_hand() {
                 ActorGunHand::Left => {
                     *t = Transform::default();
@@ -138,7 +180,8 @@ pub fn update_gun_direction(
                 }
             }
         }
-        for (mut t, _) in hand_query.iter_mut() {
+        for (mut t, mut v, _) in hand_query.iter_mut() {
+            *v = visibility;
             match actor.get_gun_hand() {
                 ActorGunHand::Left => {
                     t.translation = actor_gun.get_hand_offset(true);
@@ -221,164 +264,110 @@ pub fn update_character_sprite(
                     sprite.flip_x = false;
                 }
             },
+            ActorAction::Rolling => {
+                // 按照翻滚的方向而不是cursor的方向选择动画
+                let direction = actor.get_roll_direction();
+                let side = direction.x.abs() > 0.3;
+                let back = direction.y > 0.3;
+                let tag = match (back, side) {
+                    (false, false) => ActorTag::RollF,
+                    (true, false) => ActorTag::RollB,
+                    (false, true) => ActorTag::RollFs,
+                    (true, true) => ActorTag::RollBs,
+                };
+                anima.update(tag.tag());
+                sprite.flip_x = direction.x < 0.0;
+            }
         }
     }
 }
 
 pub fn character_move(
     keyboard: Res<Input<KeyCode>>,
+    buttons: Res<Input<MouseButton>>,
     time: Res<Time>,
     mut actor: ResMut<ResActor>,
-    cache: Res<Cache>,
+    level: Res<ActiveLevel>,
     cursor: Res<ResCursor>,
+    mut ev: EventWriter<CloudPuffEvent>,
 ) {
     let mut move_direction = MoveDirection::default();
     move_direction.detect_key(&keyboard);
     let mut old_pos = actor.get_tilemap_pos();
     let speed = time.delta_seconds() * actor.get_move_speed();
-    let to_grid_pos = |pos: [f32; 2]| -> [i32; 2] {
+    let can_move_to = |pos: [f32; 2]| -> bool {
         // ltdk存储的是tile左下角的位置信息
         // 但是实际加载时是当作中心点来加载的
         // 所以判断碰撞时需要整体偏移(8,8)
         let pos = [pos[0] + GRID_SIZE_HALF, pos[1] + GRID_SIZE_HALF];
-        [
-            (pos[0] / GRID_SIZE).floor() as i32,
-            (pos[1] / GRID_SIZE).floor() as i32,
-        ]
+        // 只用脚下的一小块区域判断碰撞
+        !level.overlap(&Collider::Rect {
+            min: [pos[0] - 7.0, pos[1] - 1.0],
+            max: [pos[0] + 7.0, pos[1] + 1.0],
+        })
     };
-    let mut walking = true;
-    // TODO 这里暂时没有抽象好，回头有空可以再回来改
-    match (move_direction.x, move_direction.y) {
-        (MoveDirectionX::None, MoveDirectionY::Up) => {
-            let new_pos = [old_pos[0], old_pos[1] + speed];
-            let need_detect_left_pos = to_grid_pos([new_pos[0] - 7.0, new_pos[1]]);
-            let need_detect_right_pos = to_grid_pos([new_pos[0] + 7.0, new_pos[1]]);
-            if cache.levels[0].contains_floor(need_detect_left_pos)
-                && cache.levels[0].contains_floor(need_detect_right_pos)
-            {
-                actor.set_tilemap_pos(new_pos);
-            }
-        }
-        (MoveDirectionX::None, MoveDirectionY::Down) => {
-            let new_pos = [old_pos[0], old_pos[1] - speed];
-            let need_detect_left_pos = to_grid_pos([new_pos[0] - 7.0, new_pos[1]]);
-            let need_detect_right_pos = to_grid_pos([new_pos[0] + 7.0, new_pos[1]]);
-            if cache.levels[0].contains_floor(need_detect_left_pos)
-                && cache.levels[0].contains_floor(need_detect_right_pos)
-            {
-                actor.set_tilemap_pos(new_pos);
-            }
-        }
-        (MoveDirectionX::Left, MoveDirectionY::None) => {
-            let new_pos = [old_pos[0] - speed, old_pos[1]];
-            let need_detect_top_pos = to_grid_pos([new_pos[0] - 7.0, new_pos[1]]);
-            let need_detect_bottom_pos = to_grid_pos([new_pos[0] - 7.0, new_pos[1]]);
-            if cache.levels[0].contains_floor(need_detect_top_pos)
-                && cache.levels[0].contains_floor(need_detect_bottom_pos)
-            {
-                actor.set_tilemap_pos(new_pos);
-            }
-        }
-        (MoveDirectionX::Right, MoveDirectionY::None) => {
-            let new_pos = [old_pos[0] + speed, old_pos[1]];
-            let need_detect_top_pos = to_grid_pos([new_pos[0] + 7.0, new_pos[1]]);
-            let need_detect_bottom_pos = to_grid_pos([new_pos[0] + 7.0, new_pos[1]]);
-            if cache.levels[0].contains_floor(need_detect_top_pos)
-                && cache.levels[0].contains_floor(need_detect_bottom_pos)
-            {
-                actor.set_tilemap_pos(new_pos);
-            }
-        }
-        (MoveDirectionX::Left, MoveDirectionY::Up) => {
-            let ratio = SQRT_2 / 2.0;
-            let new_pos = [old_pos[0], old_pos[1] + speed * ratio];
-            let need_detect_left_pos = to_grid_pos([new_pos[0] - 7.0, new_pos[1]]);
-            let need_detect_right_pos = to_grid_pos([new_pos[0] + 7.0, new_pos[1]]);
-            if cache.levels[0].contains_floor(need_detect_left_pos)
-                && cache.levels[0].contains_floor(need_detect_right_pos)
-            {
-                actor.set_tilemap_pos(new_pos);
-                old_pos = new_pos;
-            }
-            let new_pos = [old_pos[0] - speed * ratio, old_pos[1]];
-            let need_detect_top_pos = to_grid_pos([new_pos[0] - 7.0, new_pos[1]]);
-            let need_detect_bottom_pos = to_grid_pos([new_pos[0] - 7.0, new_pos[1]]);
-            if cache.levels[0].contains_floor(need_detect_top_pos)
-                && cache.levels[0].contains_floor(need_detect_bottom_pos)
-            {
-                actor.set_tilemap_pos(new_pos);
-            }
-        }
-        (MoveDirectionX::Left, MoveDirectionY::Down) => {
-            let ratio = SQRT_2 / 2.0;
-            let new_pos = [old_pos[0], old_pos[1] - speed * ratio];
-            let need_detect_left_pos = to_grid_pos([new_pos[0] - 7.0, new_pos[1]]);
-            let need_detect_right_pos = to_grid_pos([new_pos[0] + 7.0, new_pos[1]]);
-            if cache.levels[0].contains_floor(need_detect_left_pos)
-                && cache.levels[0].contains_floor(need_detect_right_pos)
-            {
-                actor.set_tilemap_pos(new_pos);
-                old_pos = new_pos;
-            }
-            let new_pos = [old_pos[0] - speed * ratio, old_pos[1]];
-            let need_detect_top_pos = to_grid_pos([new_pos[0] - 7.0, new_pos[1]]);
-            let need_detect_bottom_pos = to_grid_pos([new_pos[0] - 7.0, new_pos[1]]);
-            if cache.levels[0].contains_floor(need_detect_top_pos)
-                && cache.levels[0].contains_floor(need_detect_bottom_pos)
-            {
-                actor.set_tilemap_pos(new_pos);
-            }
+    // 斜着走的时候分别在两个方向上移动，这样贴着墙的时候还可以沿着墙走
+    let ratio = match (move_direction.x, move_direction.y) {
+        (MoveDirectionX::None, _) | (_, MoveDirectionY::None) => 1.0,
+        _ => SQRT_2 / 2.0,
+    };
+    let mut delta_y = match move_direction.y {
+        MoveDirectionY::Up => speed * ratio,
+        MoveDirectionY::Down => -speed * ratio,
+        MoveDirectionY::None => 0.0,
+    };
+    let mut delta_x = match move_direction.x {
+        MoveDirectionX::Right => speed * ratio,
+        MoveDirectionX::Left => -speed * ratio,
+        MoveDirectionX::None => 0.0,
+    };
+    let walking = delta_x != 0.0 || delta_y != 0.0;
+
+    // 翻滚沿着移动的方向，没有移动时朝着cursor的方向
+    if keyboard.just_pressed(KeyCode::Space) || buttons.just_pressed(MouseButton::Right) {
+        let direction = if walking {
+            Vec2::new(delta_x, delta_y)
+        } else {
+            let cursor_pos = cursor.get_tilemap_pos();
+            Vec2::new(cursor_pos[0] - old_pos[0], cursor_pos[1] - old_pos[1])
+        };
+        if actor.try_roll(direction) {
+            ev.send(CloudPuffEvent);
         }
-        (MoveDirectionX::Right, MoveDirectionY::Up) => {
-            let ratio = SQRT_2 / 2.0;
-            let new_pos = [old_pos[0], old_pos[1] + speed * ratio];
-            let need_detect_left_pos = to_grid_pos([new_pos[0] - 7.0, new_pos[1]]);
-            let need_detect_right_pos = to_grid_pos([new_pos[0] + 7.0, new_pos[1]]);
-            if cache.levels[0].contains_floor(need_detect_left_pos)
-                && cache.levels[0].contains_floor(need_detect_right_pos)
-            {
-                actor.set_tilemap_pos(new_pos);
-                old_pos = new_pos;
-            }
-            let new_pos = [old_pos[0] + speed * ratio, old_pos[1]];
-            let need_detect_top_pos = to_grid_pos([new_pos[0] + 7.0, new_pos[1]]);
-            let need_detect_bottom_pos = to_grid_pos([new_pos[0] + 7.0, new_pos[1]]);
-            if cache.levels[0].contains_floor(need_detect_top_pos)
-                && cache.levels[0].contains_floor(need_detect_bottom_pos)
-            {
-                actor.set_tilemap_pos(new_pos);
-            }
+    }
+    let rolling = actor.is_rolling();
+    if rolling {
+        let offset = actor.roll_step(time.delta_seconds());
+        delta_x = offset.x;
+        delta_y = offset.y;
+        // 落地的时候再扬起一次灰尘
+        if !actor.is_rolling() {
+            ev.send(CloudPuffEvent);
         }
-        (MoveDirectionX::Right, MoveDirectionY::Down) => {
-            let ratio = SQRT_2 / 2.0;
-            let new_pos = [old_pos[0], old_pos[1] - speed * ratio];
-            let need_detect_left_pos = to_grid_pos([new_pos[0] - 7.0, new_pos[1]]);
-            let need_detect_right_pos = to_grid_pos([new_pos[0] + 7.0, new_pos[1]]);
-            if cache.levels[0].contains_floor(need_detect_left_pos)
-                && cache.levels[0].contains_floor(need_detect_right_pos)
-            {
-                actor.set_tilemap_pos(new_pos);
-                old_pos = new_pos;
-            }
-            let new_pos = [old_pos[0] + speed * ratio, old_pos[1]];
-            let need_detect_top_pos = to_grid_pos([new_pos[0] + 7.0, new_pos[1]]);
-            let need_detect_bottom_pos = to_grid_pos([new_pos[0] + 7.0, new_pos[1]]);
-            if cache.levels[0].contains_floor(need_detect_top_pos)
-                && cache.levels[0].contains_floor(need_detect_bottom_pos)
-            {
-                actor.set_tilemap_pos(new_pos);
-            }
+    } else {
+        actor.tick_roll_cooldown(time.delta_seconds());
+    }
+    if delta_y != 0.0 {
+        let new_pos = [old_pos[0], old_pos[1] + delta_y];
+        if can_move_to(new_pos) {
+            actor.set_tilemap_pos(new_pos);
+            old_pos = new_pos;
         }
-        _ => {
-            walking = false;
+    }
+    if delta_x != 0.0 {
+        let new_pos = [old_pos[0] + delta_x, old_pos[1]];
+        if can_move_to(new_pos) {
+            actor.set_tilemap_pos(new_pos);
         }
     }
 
-    // 判断actor当前状态
-    if walking {
-        actor.active_walking()
-    } else {
-        actor.active_idle()
+    // 判断actor当前状态，翻滚中的状态在翻滚结束时切换
+    if !rolling {
+        if walking {
+            actor.active_walking()
+        } else {
+            actor.active_idle()
+        }
     }
 
     // 判断actor当前朝向
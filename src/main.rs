@@ -5,8 +5,9 @@ use bevy::prelude::*;
 use bevy::window::PresentMode;
 use bevy_kira_audio::prelude::*;
 
-use bevy_task_queue::TaskQueue;
-use res::{Cache, ResActor};
+use bevy_task_queue::{poll_compute_tasks, ComputeTaskPlugin, TaskQueuePlugin};
+use res::{Cache, LoadedRes, LoadingTask, ResActor, SelectedCharacter};
+use ui::character_select::CharacterSelect;
 
 mod actor;
 mod animation;
@@ -21,6 +22,9 @@ mod ui;
 mod ui_image_animation;
 mod utils;
 
+#[cfg(test)]
+mod test;
+
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[derive(States, PartialEq, Eq, Debug, Clone, Hash, Default, Reflect)]
@@ -31,28 +35,6 @@ pub enum AppState {
     InGame,
 }
 
-impl AppState {
-    pub fn next_state(&self) -> Option<Self> {
-        match self {
-            AppState::Loading => Some(AppState::InGame),
-            _ => None,
-        }
-    }
-}
-
-pub fn auto_next_state(
-    app_state: Res<State<AppState>>,
-    mut next_state: ResMut<NextState<AppState>>,
-    task_queue: Res<TaskQueue>,
-) {
-    if let Some(next) = app_state.0.next_state() {
-        if task_queue.is_empty() {
-            debug!("task queue is empty, auto change to {next:?}");
-            next_state.set(next);
-        }
-    }
-}
-
 fn main() {
     let mut app = App::new();
 
@@ -73,25 +55,62 @@ fn main() {
     )
     .add_plugin(AudioPlugin)
     .add_plugin(debug::DebugPlugin)
-    .add_plugin(cursor::CursorDetectPlugin);
+    .add_plugin(cursor::CursorDetectPlugin)
+    .add_plugin(TaskQueuePlugin::<AppState, LoadingTask>::new(
+        AppState::Loading,
+        AppState::InGame,
+    ))
+    .add_plugin(ComputeTaskPlugin::<AppState, LoadingTask, LoadedRes>::new(
+        AppState::Loading,
+    ));
     app.insert_resource(ClearColor(Color::rgba_u8(3, 12, 14, 255)));
     app.add_state::<AppState>();
     app.insert_resource(Cache::default());
-    app.insert_resource(ResActor::convict().with_budget_revolver());
-    app.insert_resource(TaskQueue::new());
+    app.insert_resource(ResActor::default());
+    app.init_resource::<SelectedCharacter>();
     app.add_event::<actor::event::CloudPuffEvent>();
+    app.add_event::<tilemap::RoomEnterEvent>();
+    app.add_event::<tilemap::RoomExitEvent>();
     app.add_startup_system(setup_camera);
     app.add_startup_system(res::initial_res);
-    app.add_system(auto_next_state);
     app.add_system(sprite_animation::update_sprite);
     app.add_system(sprite_animation::sprite_animation);
     app.add_system(ui_image_animation::update);
 
     app.add_system((ui::title::setup).in_schedule(OnEnter(AppState::Title)));
-    app.add_system((ui::title::detect_start).in_set(OnUpdate(AppState::Title)));
+    app.add_system(
+        (ui::character_select::open)
+            .run_if(not(resource_exists::<CharacterSelect>()))
+            .in_set(OnUpdate(AppState::Title)),
+    );
+    app.add_systems(
+        (ui::character_select::navigate, ui::character_select::update)
+            .chain()
+            .distributive_run_if(resource_exists::<CharacterSelect>())
+            .in_set(OnUpdate(AppState::Title)),
+    );
     app.add_system((ui::title::dismount).in_schedule(OnExit(AppState::Title)));
 
-    app.add_system((res::reset_res).in_schedule(OnEnter(AppState::Loading)));
+    app.add_systems(
+        (ui::loading::setup, res::reset_res, res::reset_actor)
+            .in_schedule(OnEnter(AppState::Loading)),
+    );
+    app.add_systems(
+        (
+            ui::loading::update,
+            res::insert_loaded_res.after(poll_compute_tasks::<LoadingTask, LoadedRes>),
+        )
+            .in_set(OnUpdate(AppState::Loading)),
+    );
+    app.add_system(
+        res::leave_level
+            .run_if(ui::loading::failed)
+            .in_set(OnUpdate(AppState::Loading)),
+    );
+    app.add_systems(
+        (ui::loading::dismount, res::replace_failed_images).in_schedule(OnExit(AppState::Loading)),
+    );
+    app.add_system(ui::loading::fade_out);
 
     app.add_systems(
         (
@@ -105,6 +124,8 @@ fn main() {
         )
             .in_schedule(OnEnter(AppState::InGame)),
     );
+    app.add_system((res::unload_level).in_schedule(OnExit(AppState::InGame)));
+    app.add_system((res::leave_level).in_set(OnUpdate(AppState::InGame)));
     app.add_systems(
         (
             character::update_character_sprite,
@@ -114,6 +135,8 @@ fn main() {
             res::update_actor,
             ui::cursor::update,
             ui::status::update,
+            tilemap::animate_tiles,
+            tilemap::detect_room_change,
             bullet::fire_bullet,
             bullet::bullet_move,
             actor::event::handle_cloud_puff_ev,
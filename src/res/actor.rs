@@ -2,10 +2,24 @@ use std::f32::consts::{PI, SQRT_2};
 
 use bevy::prelude::*;
 
+use crate::res::registry::CharacterDefinition;
 use crate::res::SCALE_RATIO;
 
+/// 翻滚持续的时间，和 roll 动画的9帧一致
+pub const ROLL_DURATION: f32 = 0.9;
+/// 翻滚结束后需要等待的时间
+pub const ROLL_COOLDOWN: f32 = 0.3;
+/// 翻滚移动的总距离，像素单位
+pub const ROLL_DISTANCE: f32 = 80.0;
+/// 翻滚开始后无敌的时间，之后的部分可以被击中
+pub const ROLL_INVULNERABLE: f32 = 0.5;
+
 #[derive(Resource, Reflect, Default)]
 pub struct ResActor {
+    /// 角色贴图在 Cache 中的名称
+    sprite: String,
+    /// hand 贴图的路径
+    hand: String,
     /// position in a virtual 2d world which used to calculate collision
     pos: [f32; 2],
     action: ActorAction,
@@ -14,37 +28,43 @@ pub struct ResActor {
     gun_hand: ActorGunHand,
     move_speed: f32,
     status: ActorStatus,
+    roll: ActorRoll,
     #[reflect(ignore)]
     gun: Option<ResGun>,
 }
 
-impl ResActor {
-    /// 只是一个开发阶段直接使用制定配置的方法
-    pub fn convict() -> Self {
-        let default_hp = 6;
+/// 按照角色定义重新开始，位置等状态都会重置
+impl From<&CharacterDefinition> for ResActor {
+    fn from(character: &CharacterDefinition) -> Self {
         Self {
-            move_speed: 100.0,
+            sprite: character.sprite.name.clone(),
+            hand: character.hand.clone(),
+            move_speed: character.stats.move_speed,
             status: ActorStatus {
-                hp_full: default_hp,
-                hp: default_hp,
-                blanks: 2,
-                keys: 10,
-                money: 10,
+                hp_full: character.stats.hp,
+                hp: character.stats.hp,
+                blanks: character.items.blanks,
+                keys: character.items.keys,
+                money: character.items.money,
             },
+            gun: character.guns.first().map(|gun| ResGun {
+                name: gun.name.clone(),
+                offset: gun.offset,
+                hand_offset: gun.hand_offset,
+                cursor_angle: 0.0,
+                fire_offset: gun.fire_offset,
+            }),
             ..Default::default()
         }
     }
-    /// 只是一个开发阶段直接使用制定配置的方法
-    pub fn with_budget_revolver(mut self) -> Self {
-        self.gun = Some(ResGun {
-            name: String::from("Budget Revolver"),
-            size: [16, 16],
-            offset: [11.0, -1.0],
-            hand_offset: [-4.0, -3.0],
-            cursor_angle: 0.0,
-            fire_offset: [7.0, 2.0],
-        });
-        self
+}
+
+impl ResActor {
+    pub fn get_sprite(&self) -> &str {
+        &self.sprite
+    }
+    pub fn get_hand(&self) -> &str {
+        &self.hand
     }
     pub fn set_tilemap_pos(&mut self, pos: [f32; 2]) {
         self.pos = pos;
@@ -159,6 +179,54 @@ impl ResActor {
     pub fn active_walking(&mut self) {
         self.action = ActorAction::Walking
     }
+    pub fn is_rolling(&self) -> bool {
+        self.action == ActorAction::Rolling
+    }
+    /// 翻滚前半段的无敌帧，之后受到伤害的判定需要先检查这里
+    #[allow(dead_code)]
+    pub fn is_invulnerable(&self) -> bool {
+        self.is_rolling() && self.roll.elapsed < ROLL_INVULNERABLE
+    }
+    /// 翻滚的方向，和tilemap的坐标一致，y向上
+    pub fn get_roll_direction(&self) -> Vec2 {
+        self.roll.direction
+    }
+    /// 不在翻滚中并且冷却结束时才能开始翻滚
+    pub fn try_roll(&mut self, direction: Vec2) -> bool {
+        if self.is_rolling() || self.roll.cooldown > 0.0 || direction == Vec2::ZERO {
+            return false;
+        }
+        self.action = ActorAction::Rolling;
+        self.roll = ActorRoll {
+            direction: direction.normalize(),
+            elapsed: 0.0,
+            cooldown: 0.0,
+        };
+        true
+    }
+    /// 推进翻滚的时间，返回这一帧的位移（像素单位）
+    ///
+    /// 开始时最快然后逐渐减速，翻滚结束后回到idle并开始冷却
+    pub fn roll_step(&mut self, delta: f32) -> Vec2 {
+        if !self.is_rolling() {
+            return Vec2::ZERO;
+        }
+        let ease = |t: f32| 1.0 - (1.0 - t / ROLL_DURATION).powi(2);
+        let start = self.roll.elapsed;
+        self.roll.elapsed = (start + delta).min(ROLL_DURATION);
+        let offset = self.roll.direction * ROLL_DISTANCE * (ease(self.roll.elapsed) - ease(start));
+        if self.roll.elapsed >= ROLL_DURATION {
+            self.action = ActorAction::Idle;
+            self.roll.cooldown = ROLL_COOLDOWN;
+        }
+        offset
+    }
+    /// 推进翻滚结束后的冷却时间，翻滚中不会冷却
+    pub fn tick_roll_cooldown(&mut self, delta: f32) {
+        if !self.is_rolling() {
+            self.roll.cooldown = (self.roll.cooldown - delta).max(0.0);
+        }
+    }
     fn turn_up(&mut self) {
         self.direction = ActorDirection::Up
     }
@@ -210,6 +278,16 @@ pub enum ActorAction {
     #[default]
     Idle,
     Walking,
+    Rolling,
+}
+
+/// 翻滚的状态
+#[derive(Reflect, Default)]
+struct ActorRoll {
+    direction: Vec2,
+    elapsed: f32,
+    /// 剩余的冷却时间
+    cooldown: f32,
 }
 
 #[derive(PartialEq, Reflect, Default, Copy, Clone)]
@@ -232,7 +310,6 @@ pub enum ActorGunHand {
 
 pub struct ResGun {
     pub name: String,
-    pub size: [u32; 2],
     /// 枪械相对角色的位置偏移
     offset: [f32; 2],
     /// 手的位置相对枪械的偏移，也就是手握在枪械哪里的信息
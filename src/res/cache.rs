@@ -1,37 +1,45 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
 
 use bevy::asset::Asset;
 use bevy::prelude::*;
 
-use world_generator::LevelModel;
+use world_generator::TileAnimation;
+
+use crate::res::{GRID_SIZE, SCALE_RATIO};
+use crate::utils;
 
 #[derive(Resource, Default)]
 pub struct Cache {
-    pub levels: Vec<LevelModel>,
     // 下面4个都是tilemap会用到的material和mesh
-    pub tile_images: HashMap<String, HashMap<u8, Handle<Image>>>,
-    pub tile_materials: HashMap<String, HashMap<u8, Handle<StandardMaterial>>>,
-    // 主要是tilemap使用mesh
-    pub tile_meshes: HashMap<(u32, u32), Handle<Mesh>>,
-    pub tile_meshes_sqrt2: HashMap<(u32, u32), Handle<Mesh>>,
-    // 每个actor分一个group，每组frame再分group
-    pub actors_images: HashMap<String, ActorAssets<Image>>,
+    // 每个tileset只有一张贴图和一个material
+    pub tile_images: HashMap<String, Handle<Image>>,
+    pub tile_materials: HashMap<String, Handle<StandardMaterial>>,
+    // 每个tile的mesh的uv对应该tile在贴图中的位置
+    pub tile_meshes: HashMap<String, HashMap<u8, Handle<Mesh>>>,
+    pub tile_meshes_sqrt2: HashMap<String, HashMap<u8, Handle<Mesh>>>,
+    // 动态tile的mesh和每一帧的uv
+    pub tile_animations: Vec<TileAnimationCache>,
+    // 每个actor只有一张贴图和一个material，每组frame再分group
+    // 每一帧的mesh的uv对应该帧在贴图中的位置
+    pub actors_images: HashMap<String, Handle<Image>>,
     pub actors_materials: HashMap<String, ActorAssets<StandardMaterial>>,
-    // char的hand相关的material和mesh
-    pub char_hand_image: Handle<Image>,
-    pub char_hand_material: Handle<StandardMaterial>,
+    pub actors_meshes: HashMap<String, ActorAssets<Mesh>>,
+    pub actors_meshes_flip: HashMap<String, ActorAssets<Mesh>>,
+    // char的hand相关的material和mesh，image和material按照贴图路径保存
+    pub char_hand_images: HashMap<String, Handle<Image>>,
+    pub char_hand_materials: HashMap<String, Handle<StandardMaterial>>,
     pub char_hand_mesh: Handle<Mesh>,
-    // gun相关的material和mesh
+    // gun相关的material和mesh，和actor一样每把gun只有一个material
+    // gun_images是每一帧单独的贴图，给ui使用
     pub gun_images: HashMap<String, HashMap<u8, Handle<Image>>>,
     pub gun_materials: HashMap<String, HashMap<u8, Handle<StandardMaterial>>>,
-    pub gun_meshes: HashMap<(u32, u32), Handle<Mesh>>,
-    pub gun_meshes_flip: HashMap<(u32, u32), Handle<Mesh>>,
+    pub gun_meshes: HashMap<String, HashMap<u8, Handle<Mesh>>>,
+    pub gun_meshes_flip: HashMap<String, HashMap<u8, Handle<Mesh>>>,
     // bullet相关的material和mesh
     pub bullet_images: HashMap<String, Handle<Image>>,
     pub bullet_materials: HashMap<String, Handle<StandardMaterial>>,
     pub bullet_meshes: HashMap<(u32, u32), Handle<Mesh>>,
-    // TODO need to des
-    pub old_meshes: HashMap<String, Handle<Mesh>>,
     // ui相关的image
     pub ui_hp_images: HashMap<u8, Handle<Image>>,
     pub ui_blank_image: Handle<Image>,
@@ -42,64 +50,223 @@ pub struct Cache {
     pub ui_ammo_border: Handle<Image>,
     pub ui_ascii_font: AsciiFontTable,
     pub ui_title_dragon: Vec<Handle<Image>>,
+    // 选择角色时预览的待机动画，按照角色贴图的名称保存
+    pub ui_character_previews: HashMap<String, Vec<Handle<Image>>>,
     pub tile_debug_mesh: Handle<Mesh>,
     pub light_debug_mesh: Handle<Mesh>,
     pub tile_world_debug_material: Handle<StandardMaterial>,
     pub tile_room_debug_material: Handle<StandardMaterial>,
     pub light_debug_material: Handle<StandardMaterial>,
     pub actor_caches: ActorCache,
+    // sound目录下的音效，保存handle避免加载完之后被释放
+    pub sounds: Vec<HandleUntyped>,
+    // 资源缺失时代替使用的资源
+    pub placeholder: Placeholder,
+}
+
+/// 一个动态 tile 用到的 mesh，以及每一帧在贴图中的 uv 范围
+#[derive(Default)]
+pub struct TileAnimationCache {
+    pub meshes: Vec<Handle<Mesh>>,
+    pub animation: TileAnimation,
+    pub uv_rects: Vec<Rect>,
 }
 
 #[allow(dead_code)]
 impl Cache {
-    pub fn get_tile_mesh(&self, key: (u32, u32)) -> &Handle<Mesh> {
-        self.tile_meshes.get(&key).unwrap()
+    /// 释放当前关卡的 tileset 相关资源，没有被其他地方引用的 handle 会在之后被 bevy 回收
+    pub fn clear_level(&mut self) {
+        self.tile_images.clear();
+        self.tile_materials.clear();
+        self.tile_meshes.clear();
+        self.tile_meshes_sqrt2.clear();
+        self.tile_animations.clear();
+    }
+    /// 找不到资源时返回占位资源，同一个资源只输出一次警告
+    fn or_placeholder<'a, T>(
+        &self,
+        found: Option<&'a T>,
+        placeholder: &'a T,
+        key: impl FnOnce() -> String,
+    ) -> &'a T {
+        found.unwrap_or_else(|| {
+            self.placeholder.report(key());
+            placeholder
+        })
+    }
+    pub fn get_tile_mesh(&self, tag: &str, index: u8) -> &Handle<Mesh> {
+        let found = self.tile_meshes.get(tag).and_then(|m| m.get(&index));
+        self.or_placeholder(found, &self.placeholder.mesh, || {
+            format!("tile mesh {tag}[{index}]")
+        })
     }
-    pub fn get_tile_mesh_sqrt2(&self, key: (u32, u32)) -> &Handle<Mesh> {
-        self.tile_meshes_sqrt2.get(&key).unwrap()
+    pub fn get_tile_mesh_sqrt2(&self, tag: &str, index: u8) -> &Handle<Mesh> {
+        let found = self.tile_meshes_sqrt2.get(tag).and_then(|m| m.get(&index));
+        self.or_placeholder(found, &self.placeholder.mesh, || {
+            format!("tile mesh sqrt2 {tag}[{index}]")
+        })
     }
+    /// 同一个tileset中的tile共用一个material，index只用来确认tile存在
     pub fn get_tile_material(&self, tag: &str, index: u8) -> &Handle<StandardMaterial> {
-        self.tile_materials.get(tag).unwrap().get(&index).unwrap()
+        let tile = self.tile_meshes.get(tag).and_then(|m| m.get(&index));
+        let found = self.tile_materials.get(tag).filter(|_| tile.is_some());
+        self.or_placeholder(found, &self.placeholder.material, || {
+            format!("tile material {tag}[{index}]")
+        })
     }
+    /// 同一个actor的所有帧共用一个material
     pub fn get_actor_materials(&self, name: &str, tag: &str) -> &Vec<Handle<StandardMaterial>> {
-        self.actors_materials.get(name).unwrap().get_frames(tag)
+        let found = self
+            .actors_materials
+            .get(name)
+            .and_then(|a| a.get_frames(tag));
+        self.or_placeholder(found, &self.placeholder.materials, || {
+            format!("actor materials {name}/{tag}")
+        })
     }
-    pub fn get_character_mesh(&self) -> &Handle<Mesh> {
-        self.old_meshes.get("Tile28").unwrap()
+    /// 每一帧的mesh，和 [`Cache::get_actor_materials`] 的帧一一对应
+    pub fn get_actor_meshes(&self, name: &str, tag: &str, flip: bool) -> &Vec<Handle<Mesh>> {
+        let meshes = if flip {
+            &self.actors_meshes_flip
+        } else {
+            &self.actors_meshes
+        };
+        let found = meshes.get(name).and_then(|a| a.get_frames(tag));
+        self.or_placeholder(found, &self.placeholder.meshes, || {
+            format!("actor meshes {name}/{tag}")
+        })
     }
-    pub fn get_character_mesh_flip(&self) -> &Handle<Mesh> {
-        self.old_meshes.get("Tile28Flip").unwrap()
+    /// path是角色定义中hand贴图的路径
+    pub fn get_char_hand_material(&self, path: &str) -> &Handle<StandardMaterial> {
+        let found = self.char_hand_materials.get(path);
+        self.or_placeholder(found, &self.placeholder.material, || {
+            format!("hand material {path}")
+        })
     }
-    pub fn get_gun_mesh(&self, key: (u32, u32), flip: bool) -> &Handle<Mesh> {
-        if flip {
-            self.gun_meshes_flip.get(&key).unwrap()
+    pub fn get_gun_mesh(&self, tag: &str, index: u8, flip: bool) -> &Handle<Mesh> {
+        let meshes = if flip {
+            &self.gun_meshes_flip
         } else {
-            self.gun_meshes.get(&key).unwrap()
-        }
+            &self.gun_meshes
+        };
+        let found = meshes.get(tag).and_then(|m| m.get(&index));
+        self.or_placeholder(found, &self.placeholder.mesh, || {
+            format!("gun mesh {tag}[{index}]")
+        })
     }
     pub fn get_gun_image(&self, tag: &str, index: u8) -> &Handle<Image> {
-        self.gun_images.get(tag).unwrap().get(&index).unwrap()
+        let found = self.gun_images.get(tag).and_then(|m| m.get(&index));
+        self.or_placeholder(found, &self.placeholder.image, || {
+            format!("gun image {tag}[{index}]")
+        })
     }
     pub fn get_gun_material(&self, tag: &str, index: u8) -> &Handle<StandardMaterial> {
-        self.gun_materials.get(tag).unwrap().get(&index).unwrap()
+        let found = self.gun_materials.get(tag).and_then(|m| m.get(&index));
+        self.or_placeholder(found, &self.placeholder.material, || {
+            format!("gun material {tag}[{index}]")
+        })
     }
     pub fn get_bullet_mesh(&self, key: (u32, u32)) -> &Handle<Mesh> {
-        self.bullet_meshes.get(&key).unwrap()
+        let found = self.bullet_meshes.get(&key);
+        self.or_placeholder(found, &self.placeholder.mesh, || {
+            format!("bullet mesh {key:?}")
+        })
     }
     pub fn get_bullet_material(&self, tag: &str) -> &Handle<StandardMaterial> {
-        self.bullet_materials.get(tag).unwrap()
+        let found = self.bullet_materials.get(tag);
+        self.or_placeholder(found, &self.placeholder.material, || {
+            format!("bullet material {tag}")
+        })
     }
     /// 角色hp显示，0是满的一颗心，1是半颗心，2是空的心
     pub fn get_hp_image(&self, index: u8) -> &Handle<Image> {
-        self.ui_hp_images.get(&index).unwrap()
+        let found = self.ui_hp_images.get(&index);
+        self.or_placeholder(found, &self.placeholder.image, || {
+            format!("hp image {index}")
+        })
     }
     /// index表示card的叠加数量，从1开始
     pub fn get_card_image(&self, index: u8) -> &Handle<Image> {
-        self.ui_card_image.get(&index).unwrap()
+        let found = self.ui_card_image.get(&index);
+        self.or_placeholder(found, &self.placeholder.image, || {
+            format!("card image {index}")
+        })
     }
-    /// name是枪械的名称，index==0是空弹药，index==1是有弹药
+    /// name是枪械的名称，index==0是有弹药，index==1是空弹药
     pub fn get_ui_ammo_images(&self, name: &str) -> &(Handle<Image>, Handle<Image>) {
-        self.ui_ammo_images.get(name).unwrap()
+        let found = self.ui_ammo_images.get(name);
+        self.or_placeholder(found, &self.placeholder.image_pair, || {
+            format!("ammo images {name}")
+        })
+    }
+    /// name是角色贴图的名称，返回待机动画的每一帧
+    pub fn get_character_preview(&self, name: &str) -> &Vec<Handle<Image>> {
+        let found = self
+            .ui_character_previews
+            .get(name)
+            .filter(|frames| !frames.is_empty());
+        self.or_placeholder(found, &self.placeholder.images, || {
+            format!("character preview {name}")
+        })
+    }
+    /// cloud puff每一帧的material
+    pub fn get_cloud_puff_materials(&self) -> &Vec<Handle<StandardMaterial>> {
+        let materials = &self.actor_caches.cloud_puff_materials;
+        let found = Some(materials).filter(|m| !m.is_empty());
+        self.or_placeholder(found, &self.placeholder.materials, || {
+            String::from("cloud puff materials")
+        })
+    }
+}
+
+/// 资源缺失时使用的占位资源，都使用同一张品红和黑色的棋盘格贴图
+#[derive(Default)]
+pub struct Placeholder {
+    pub image: Handle<Image>,
+    pub material: Handle<StandardMaterial>,
+    pub mesh: Handle<Mesh>,
+    /// 下面几个只是为了能够返回引用
+    image_pair: (Handle<Image>, Handle<Image>),
+    images: Vec<Handle<Image>>,
+    materials: Vec<Handle<StandardMaterial>>,
+    meshes: Vec<Handle<Mesh>>,
+    /// 已经输出过警告的资源
+    reported: Mutex<HashSet<String>>,
+}
+
+impl Placeholder {
+    pub fn new(
+        images: &mut Assets<Image>,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<StandardMaterial>,
+    ) -> Self {
+        let image = images.add(utils::placeholder_image());
+        let material = materials.add(StandardMaterial {
+            base_color_texture: Some(image.clone()),
+            unlit: true,
+            ..default()
+        });
+        let mesh = meshes.add(Mesh::from(shape::Quad::new(Vec2::splat(
+            GRID_SIZE * SCALE_RATIO,
+        ))));
+        Self {
+            image_pair: (image.clone(), image.clone()),
+            images: vec![image.clone()],
+            materials: vec![material.clone()],
+            meshes: vec![mesh.clone()],
+            image,
+            material,
+            mesh,
+            reported: Mutex::default(),
+        }
+    }
+    /// 每个缺失的资源只输出一次，`key` 是资源在 [`Cache`] 中的位置
+    pub fn report(&self, key: String) {
+        let mut reported = self.reported.lock().unwrap();
+        if !reported.contains(&key) {
+            warn!("missing {key}, using the placeholder");
+            reported.insert(key);
+        }
     }
 }
 
@@ -120,8 +287,8 @@ impl<T: Asset> ActorAssets<T> {
             0
         }
     }
-    pub fn get_frames(&self, tag: &str) -> &Vec<Handle<T>> {
-        self.assets.get(tag).unwrap()
+    pub fn get_frames(&self, tag: &str) -> Option<&Vec<Handle<T>>> {
+        self.assets.get(tag)
     }
 }
 
@@ -130,20 +297,31 @@ impl<T: Asset> ActorAssets<T> {
 /// 当语言为英文时，理论上只需要显示26个英文字母大小写+10个数字
 /// 都使用等宽像素字体并以贴图的形式加载进来
 /// TODO 实际上起码数字用等宽不好看，需要改成非等宽字体
+///
+/// 找不到的字符显示成 `?`，`?` 也没有时显示占位贴图
 #[derive(Default)]
 pub struct AsciiFontTable {
     table: HashMap<char, Handle<Image>>,
+    fallback: Handle<Image>,
+    /// 已经输出过警告的字符
+    reported: Mutex<HashSet<char>>,
 }
 
 impl AsciiFontTable {
     pub fn set(&mut self, key: char, value: Handle<Image>) {
         self.table.insert(key, value);
     }
+    pub fn set_fallback(&mut self, fallback: Handle<Image>) {
+        self.fallback = fallback;
+    }
     pub fn get(&self, key: char) -> &Handle<Image> {
-        match self.table.get(&key) {
-            Some(r) => return r,
-            None => panic!("unable to find font with {}", key),
+        if let Some(r) = self.table.get(&key) {
+            return r;
+        }
+        if self.reported.lock().unwrap().insert(key) {
+            warn!("missing glyph {key:?} in ascii font, using the placeholder");
         }
+        self.table.get(&'?').unwrap_or(&self.fallback)
     }
 }
 
@@ -0,0 +1,51 @@
+use std::fmt::{Display, Formatter};
+
+use bevy::prelude::*;
+
+/// 读取资源文件时的错误，都带有出错的文件路径
+#[derive(Debug)]
+pub enum AssetError {
+    /// 文件不存在或者无法读取
+    Io { path: String, error: std::io::Error },
+    /// 图片无法解码
+    Image {
+        path: String,
+        error: image::ImageError,
+    },
+    /// 配置文件（json）的格式不对
+    Parse { path: String, error: String },
+    /// 切割的范围超出了贴图
+    OutOfBounds {
+        path: String,
+        rect: Rect,
+        size: Vec2,
+    },
+    /// 清单中的设置不能组合在一起使用
+    Manifest { path: String, message: String },
+}
+
+impl Display for AssetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssetError::Io { path, error } => write!(f, "failed to read {path}: {error}"),
+            AssetError::Image { path, error } => write!(f, "failed to decode {path}: {error}"),
+            AssetError::Parse { path, error } => write!(f, "failed to parse {path}: {error}"),
+            AssetError::OutOfBounds { path, rect, size } => write!(
+                f,
+                "frame {:?}..{:?} is outside of {path} ({}x{})",
+                rect.min, rect.max, size.x, size.y
+            ),
+            AssetError::Manifest { path, message } => write!(f, "{path}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AssetError::Io { error, .. } => Some(error),
+            AssetError::Image { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
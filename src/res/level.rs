@@ -0,0 +1,59 @@
+use std::ops::Deref;
+
+use bevy::prelude::*;
+use world_generator::LevelModel;
+
+use crate::res::Cache;
+use crate::AppState;
+
+/// 当前正在进行的关卡
+///
+/// 关卡加载完成后插入，离开 `InGame` 时由 [`unload_level`] 移除，同时释放关卡用到的资源
+#[derive(Resource)]
+pub struct ActiveLevel {
+    /// 关卡文件相对 `assets` 目录的路径
+    pub path: String,
+    pub model: LevelModel,
+}
+
+impl Deref for ActiveLevel {
+    type Target = LevelModel;
+
+    fn deref(&self) -> &Self::Target {
+        &self.model
+    }
+}
+
+/// 属于当前关卡的 entity，卸载关卡时会和子 entity 一起删除
+///
+/// 关卡、角色和游戏中的 ui 都需要添加，离开 `InGame` 之后不应该留下任何 entity
+#[derive(Component)]
+pub struct LevelEntity;
+
+/// 卸载当前关卡：删除关卡的 entity，释放 tileset 的贴图、material 和 mesh
+///
+/// 重新进入 `Loading` 时会加载新的关卡，不会再保留上一个关卡的任何资源
+pub fn unload_level(
+    mut c: Commands,
+    mut cache: ResMut<Cache>,
+    level: Option<Res<ActiveLevel>>,
+    query: Query<Entity, With<LevelEntity>>,
+) {
+    if let Some(level) = level {
+        debug!("unload level {}", level.path);
+    }
+    for entity in query.iter() {
+        c.entity(entity).despawn_recursive();
+    }
+    c.remove_resource::<ActiveLevel>();
+    cache.clear_level();
+}
+
+/// 游戏中按下 Esc 离开当前关卡，回到标题界面，离开 `InGame` 时会卸载关卡
+///
+/// 关卡加载失败时也用来从 loading 界面回到标题界面
+pub fn leave_level(keyboard: Res<Input<KeyCode>>, mut next_state: ResMut<NextState<AppState>>) {
+    if keyboard.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::Title);
+    }
+}
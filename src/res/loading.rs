@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+use std::f32::consts::SQRT_2;
+
+use bevy::prelude::*;
+use bevy_task_queue::{ComputeTasks, TaskQueue};
+use world_generator::LevelModel;
+
+use crate::res::manifest::{AssetManifest, SheetManifest, SheetTarget, SlicedImages};
+use crate::res::{
+    ActiveLevel, ActorAssets, AssetError, Cache, LoadingTask, ResActor, TileAnimationCache,
+};
+use crate::res::{GRID_SIZE, SCALE_RATIO};
+use crate::utils;
+
+/// 在后台线程中读取、解码和裁剪好的资源，回到主线程后由 [`insert_loaded_res`] 放入 [`Cache`]
+///
+/// 图片在后台线程只是解码成 [`Image`]，添加到 `Assets<Image>` 和创建 material、mesh 都需要在主线程完成
+pub enum LoadedRes {
+    /// 关卡和关卡中每个 tileset 的贴图，顺序和 `level.tilesets` 一致
+    Level {
+        /// 关卡文件相对 `assets` 目录的路径
+        path: String,
+        level: LevelModel,
+        textures: Vec<Image>,
+    },
+    /// 按照清单切割好的贴图
+    Sheet {
+        sheet: SheetManifest,
+        images: SlicedImages,
+    },
+    /// 读取或者切割失败的贴图，不影响进入游戏，用到的地方会显示占位贴图
+    Failed {
+        sheet: SheetManifest,
+        error: AssetError,
+    },
+}
+
+/// 把清单中需要读取和裁剪图片的工作放到后台线程，每个工作都是 `task_queue` 中的一个任务
+pub fn spawn_loading_jobs(
+    manifest: &AssetManifest,
+    task_queue: &mut TaskQueue<LoadingTask>,
+    compute_tasks: &mut ComputeTasks<LoadingTask, LoadedRes>,
+) {
+    let path = manifest.level.clone();
+    compute_tasks.spawn(
+        task_queue,
+        LoadingTask::Level(manifest.level.clone()),
+        move || -> Result<LoadedRes, AssetError> {
+            // 没有关卡没办法进入游戏，失败时停留在 loading 界面显示错误，按 Esc 回到标题界面
+            let level =
+                LevelModel::from(format!("assets/{path}")).map_err(|error| AssetError::Parse {
+                    path: path.clone(),
+                    error: error.to_string(),
+                })?;
+            // 每个 tileset 只有一张贴图（world_generator 打包好的 atlas）
+            let textures = level
+                .tilesets
+                .iter()
+                .map(|tileset| utils::open_image(format!("assets/{}", tileset.texture_src())))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(LoadedRes::Level {
+                path,
+                level,
+                textures,
+            })
+        },
+    );
+    for sheet in manifest.sheets.iter() {
+        // 标题界面的动画在启动时已经加载
+        if sheet.target == SheetTarget::TitleDragon {
+            continue;
+        }
+        let sheet = sheet.clone();
+        let task = LoadingTask::Image(sheet.path.clone());
+        compute_tasks.spawn(task_queue, task, move || {
+            let loaded = match sheet.slice() {
+                Ok(images) => LoadedRes::Sheet { sheet, images },
+                Err(error) => LoadedRes::Failed { sheet, error },
+            };
+            Ok::<_, AssetError>(loaded)
+        });
+    }
+}
+
+/// 把后台线程完成的资源放入 [`Cache`]
+///
+/// 必须在 [`bevy_task_queue::poll_compute_tasks`] 之后执行，保证最后一个任务完成的那一帧结果也会被放入 [`Cache`]，
+/// 否则下一帧已经进入 `InGame` 了
+pub fn insert_loaded_res(
+    mut c: Commands,
+    mut cache: ResMut<Cache>,
+    mut actor: ResMut<ResActor>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut compute_tasks: ResMut<ComputeTasks<LoadingTask, LoadedRes>>,
+) {
+    for (_, loaded) in compute_tasks.drain_results() {
+        match loaded {
+            LoadedRes::Level {
+                path,
+                level,
+                textures,
+            } => {
+                insert_level(
+                    &mut cache,
+                    &mut actor,
+                    &mut images,
+                    &mut meshes,
+                    &mut materials,
+                    &level,
+                    textures,
+                );
+                c.insert_resource(ActiveLevel { path, model: level });
+            }
+            LoadedRes::Failed { sheet, error } => {
+                error!("{error}, {:?} will use the placeholder", sheet.target);
+            }
+            LoadedRes::Sheet {
+                sheet,
+                images: sliced,
+            } => insert_sheet(
+                &mut cache,
+                &mut images,
+                &mut meshes,
+                &mut materials,
+                sheet,
+                sliced,
+            ),
+        }
+    }
+}
+
+/// 按照 `sheet.target` 把切割好的贴图放入 [`Cache`]
+///
+/// 保留整张贴图的 sheet 只创建一个 material，每一帧使用对应 uv 范围的 mesh 来显示贴图中不同的部分，
+/// [`Cache::get_actor_materials`] 和 [`Cache::get_gun_material`] 返回的每一帧都是同一个 material
+pub fn insert_sheet(
+    cache: &mut Cache,
+    images: &mut Assets<Image>,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    sheet: SheetManifest,
+    sliced: SlicedImages,
+) {
+    let (texture, frames, cropped) = match sliced {
+        SlicedImages::Grid(frames) => {
+            let frames = frames.into_iter().map(|frame| images.add(frame)).collect();
+            insert_frames(cache, materials, &sheet, frames);
+            return;
+        }
+        SlicedImages::Atlas {
+            texture,
+            frames,
+            cropped,
+        } => (texture, frames, cropped),
+    };
+    let texture_size = texture.size();
+    let texture = images.add(texture);
+    let material = materials.add(
+        sheet
+            .material
+            .clone()
+            .unwrap_or_default()
+            .material(texture.clone()),
+    );
+    let mut frame_mesh = |rect: Rect, flip: bool| {
+        let uv_rect = Rect {
+            min: rect.min / texture_size,
+            max: rect.max / texture_size,
+        };
+        meshes.add(utils::uv_quad(rect.size() * SCALE_RATIO, uv_rect, flip))
+    };
+    match sheet.target {
+        SheetTarget::Actor(name) => {
+            let mut actor_materials = ActorAssets::default();
+            let mut actor_meshes = ActorAssets::default();
+            let mut actor_meshes_flip = ActorAssets::default();
+            for (tag, rect) in frames {
+                actor_materials.insert_frame(&tag, material.clone());
+                actor_meshes.insert_frame(&tag, frame_mesh(rect, false));
+                actor_meshes_flip.insert_frame(&tag, frame_mesh(rect, true));
+            }
+            cache.actors_images.insert(name.clone(), texture);
+            cache.actors_materials.insert(name.clone(), actor_materials);
+            cache.actors_meshes.insert(name.clone(), actor_meshes);
+            cache.actors_meshes_flip.insert(name, actor_meshes_flip);
+        }
+        SheetTarget::Gun(name) => {
+            let mut gun_materials = HashMap::new();
+            let mut gun_meshes = HashMap::new();
+            let mut gun_meshes_flip = HashMap::new();
+            for (index, (_, rect)) in frames.into_iter().enumerate() {
+                let index = index as u8;
+                gun_materials.insert(index, material.clone());
+                gun_meshes.insert(index, frame_mesh(rect, false));
+                gun_meshes_flip.insert(index, frame_mesh(rect, true));
+            }
+            // gun card 中显示的是单独的贴图
+            let gun_images = cropped
+                .into_iter()
+                .enumerate()
+                .map(|(index, frame)| (index as u8, images.add(frame)))
+                .collect();
+            cache.gun_images.insert(name.clone(), gun_images);
+            cache.gun_materials.insert(name.clone(), gun_materials);
+            cache.gun_meshes.insert(name.clone(), gun_meshes);
+            cache.gun_meshes_flip.insert(name, gun_meshes_flip);
+        }
+        target => warn!("{}: {target:?} can not use a texture atlas", sheet.path),
+    }
+}
+
+/// 每一帧都是单独贴图的 sheet，主要是 ui 使用
+fn insert_frames(
+    cache: &mut Cache,
+    materials: &mut Assets<StandardMaterial>,
+    sheet: &SheetManifest,
+    frames: Vec<Handle<Image>>,
+) {
+    let material = sheet.material.clone().unwrap_or_default();
+    match &sheet.target {
+        SheetTarget::Hp => {
+            for (index, frame) in frames.into_iter().enumerate() {
+                cache.ui_hp_images.insert(index as u8, frame);
+            }
+        }
+        SheetTarget::Ammo(name) => {
+            let [ammo, empty, ..] = frames.as_slice() else {
+                warn!("{}: ammo needs 2 frames", sheet.path);
+                return;
+            };
+            cache
+                .ui_ammo_images
+                .insert(name.clone(), (ammo.clone(), empty.clone()));
+        }
+        SheetTarget::AsciiFont(first) => {
+            for (index, frame) in frames.into_iter().enumerate() {
+                cache
+                    .ui_ascii_font
+                    .set((*first as usize + index) as u8 as char, frame);
+            }
+        }
+        SheetTarget::CloudPuff => {
+            cache.actor_caches.cloud_puff_materials = frames
+                .into_iter()
+                .map(|frame| materials.add(material.material(frame)))
+                .collect();
+        }
+        SheetTarget::TitleDragon => {
+            cache.ui_title_dragon = frames;
+        }
+        target => warn!("{}: {target:?} needs a texture atlas", sheet.path),
+    }
+}
+
+/// 创建新关卡的 tileset 资源，上一个关卡的 tileset 资源会被替换掉
+fn insert_level(
+    cache: &mut Cache,
+    actor: &mut ResActor,
+    images: &mut Assets<Image>,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    level: &LevelModel,
+    textures: Vec<Image>,
+) {
+    cache.clear_level();
+    actor.set_tilemap_pos({
+        // 把birth point设定成[1,1]了
+        // [0,0]点是左下角为原点的位置
+        // 但是实际放入bevy坐标系时，是不处理gird point到world point的处理的
+        // 也就是说实际上x轴和y轴都偏移的8像素
+        let pos = level.brith_point;
+        [pos[0] as f32 * GRID_SIZE, pos[1] as f32 * GRID_SIZE]
+    });
+
+    // 所有 tile 共用 tileset 贴图的 material
+    // 每个 tile 使用对应 uv 范围的 mesh 来显示贴图中不同的部分
+    for (tileset, texture) in level.tilesets.iter().zip(textures) {
+        let texture_size = texture.size();
+        let image_handle = images.add(texture);
+        let material_handle = materials.add(StandardMaterial {
+            base_color_texture: Some(image_handle.clone()),
+            perceptual_roughness: 0.9,
+            metallic: 0.0,
+            reflectance: 0.1,
+            alpha_mode: AlphaMode::Blend,
+            depth_bias: 1.0,
+            ..default()
+        });
+        // 每个 tile 在贴图中的 uv 范围
+        let uv_rects = tileset
+            .tiles
+            .keys()
+            .filter_map(|index| {
+                let (min, size) = tileset.texture_rect(*index)?;
+                let uv_rect = Rect {
+                    min: Vec2::new(min[0] as f32, min[1] as f32) / texture_size,
+                    max: Vec2::new((min[0] + size[0]) as f32, (min[1] + size[1]) as f32)
+                        / texture_size,
+                };
+                Some((*index, (uv_rect, Vec2::new(size[0] as f32, size[1] as f32))))
+            })
+            .collect::<HashMap<u8, (Rect, Vec2)>>();
+        let mut tileset_meshes = HashMap::new();
+        let mut tileset_meshes_sqrt2 = HashMap::new();
+        for (index, (uv_rect, size)) in uv_rects.iter() {
+            let size = *size * SCALE_RATIO;
+            tileset_meshes.insert(*index, meshes.add(utils::uv_quad(size, *uv_rect, false)));
+            tileset_meshes_sqrt2.insert(
+                *index,
+                meshes.add(utils::uv_quad(
+                    Vec2::new(size.x, size.y * SQRT_2),
+                    *uv_rect,
+                    false,
+                )),
+            );
+        }
+        // 动态 tile 的所有实例共用同一个 mesh，播放时只需要修改 mesh 的 uv
+        for (index, animation) in tileset.animations.iter() {
+            let (Some(mesh), Some(mesh_sqrt2)) =
+                (tileset_meshes.get(index), tileset_meshes_sqrt2.get(index))
+            else {
+                continue;
+            };
+            let frame_uv_rects = animation
+                .frames
+                .iter()
+                .filter_map(|frame| Some(uv_rects.get(&frame.index)?.0))
+                .collect::<Vec<_>>();
+            if frame_uv_rects.len() == animation.frames.len() {
+                cache.tile_animations.push(TileAnimationCache {
+                    meshes: vec![mesh.clone(), mesh_sqrt2.clone()],
+                    animation: animation.clone(),
+                    uv_rects: frame_uv_rects,
+                });
+            }
+        }
+        cache.tile_images.insert(tileset.uuid.clone(), image_handle);
+        cache
+            .tile_materials
+            .insert(tileset.uuid.clone(), material_handle);
+        cache
+            .tile_meshes
+            .insert(tileset.uuid.clone(), tileset_meshes);
+        cache
+            .tile_meshes_sqrt2
+            .insert(tileset.uuid.clone(), tileset_meshes_sqrt2);
+    }
+}
@@ -0,0 +1,255 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::res::AssetError;
+use crate::utils;
+
+pub const MANIFEST_PATH: &str = "assets/manifest.json";
+
+/// 描述 [`crate::res::Cache`] 中所有资源的清单，新增 gun 或者 ui 贴图只需要修改 `assets/manifest.json`
+///
+/// 路径都是相对 `assets` 目录的路径
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+pub struct AssetManifest {
+    /// world_generator 生成的关卡文件
+    pub level: String,
+    /// 需要切割的贴图，在后台线程读取和切割
+    #[serde(default)]
+    pub sheets: Vec<SheetManifest>,
+    /// 直接通过 AssetServer 加载的贴图
+    #[serde(default)]
+    pub images: Vec<ImageManifest>,
+    #[serde(default)]
+    pub meshes: Vec<MeshManifest>,
+    /// 角色定义文件，角色用到的贴图由 [`crate::res::CharacterRegistry`] 补充到清单中
+    #[serde(default)]
+    pub characters: Vec<String>,
+}
+
+impl AssetManifest {
+    pub fn from<P: AsRef<std::path::Path>>(path: P) -> Result<Self, AssetError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|error| AssetError::Io {
+            path: path.display().to_string(),
+            error,
+        })?;
+        serde_json::from_str(&content).map_err(|error| AssetError::Parse {
+            path: path.display().to_string(),
+            error: error.to_string(),
+        })
+    }
+}
+
+/// 清单读取失败时使用的空清单，只有默认的关卡，所有贴图都会显示成占位贴图
+impl Default for AssetManifest {
+    fn default() -> Self {
+        Self {
+            level: String::from("levels/demo_output.json"),
+            sheets: vec![],
+            images: vec![],
+            meshes: vec![],
+            characters: vec![],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SheetManifest {
+    pub path: String,
+    pub slice: Slice,
+    pub target: SheetTarget,
+    /// 需要 material 的贴图才设置
+    #[serde(default)]
+    pub material: Option<MaterialManifest>,
+}
+
+/// 贴图的切割方式
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Slice {
+    /// 按照行列切割，每一帧的 index 是 `y * columns + x`
+    Grid {
+        tile_size: [u32; 2],
+        columns: usize,
+        rows: usize,
+    },
+    /// 按照 aseprite 导出的 json 切割，每一帧按照 filename 分组
+    Aseprite { config: String },
+}
+
+/// 切割后的贴图放到 Cache 的哪个位置
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum SheetTarget {
+    /// actor 的名称
+    Actor(String),
+    /// gun 的名称
+    Gun(String),
+    /// 0是满的一颗心，1是半颗心，2是空的心
+    Hp,
+    /// gun 的名称，index==0是有弹药，index==1是空弹药
+    Ammo(String),
+    /// 第一帧对应的ascii码
+    AsciiFont(u8),
+    CloudPuff,
+    /// 标题界面的动画，启动时就需要，所以不在 loading 中加载
+    TitleDragon,
+}
+
+impl SheetTarget {
+    /// 只有 actor 需要按照 tag 分组
+    pub fn is_tagged(&self) -> bool {
+        matches!(self, SheetTarget::Actor(_))
+    }
+    /// 在场景中显示的 actor 和 gun 保留整张贴图，所有帧共用一个 material，每一帧使用不同 uv 的 mesh
+    pub fn is_atlas(&self) -> bool {
+        matches!(self, SheetTarget::Actor(_) | SheetTarget::Gun(_))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImageManifest {
+    pub path: String,
+    pub target: ImageTarget,
+    #[serde(default)]
+    pub material: Option<MaterialManifest>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ImageTarget {
+    CharHand,
+    /// gun 的名称
+    Bullet(String),
+    UiBlank,
+    UiKey,
+    UiMoney,
+    /// card 的叠加数量，从1开始
+    UiCard(u8),
+    UiAmmoBorder,
+}
+
+/// 贴图使用的 material，都是 alpha blend
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MaterialManifest {
+    #[serde(default)]
+    pub depth_bias: f32,
+    #[serde(default = "default_unlit")]
+    pub unlit: bool,
+}
+
+fn default_unlit() -> bool {
+    true
+}
+
+impl Default for MaterialManifest {
+    fn default() -> Self {
+        Self {
+            depth_bias: 0.0,
+            unlit: default_unlit(),
+        }
+    }
+}
+
+impl MaterialManifest {
+    pub fn material(&self, texture: Handle<Image>) -> StandardMaterial {
+        StandardMaterial {
+            base_color_texture: Some(texture),
+            alpha_mode: AlphaMode::Blend,
+            unlit: self.unlit,
+            depth_bias: self.depth_bias,
+            ..default()
+        }
+    }
+}
+
+/// 像素大小的 quad，创建时乘以 SCALE_RATIO
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MeshManifest {
+    pub size: [u32; 2],
+    #[serde(default)]
+    pub flip: bool,
+    pub target: MeshTarget,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum MeshTarget {
+    /// 按照 size 保存
+    Bullet,
+    CharHand,
+    CloudPuff,
+}
+
+/// 切割好的贴图
+pub enum SlicedImages {
+    /// 每一帧单独的贴图，ui 只能使用单独的贴图
+    Grid(Vec<Image>),
+    /// 整张贴图和每一帧的 tag、像素范围，按照行列切割时 tag 是空的
+    Atlas {
+        texture: Image,
+        frames: Vec<(String, Rect)>,
+        /// 同时需要在 ui 中显示的帧单独复制出来的贴图
+        cropped: Vec<Image>,
+    },
+}
+
+impl SheetManifest {
+    /// 读取并切割贴图，比较耗时，应该在后台线程执行
+    pub fn slice(&self) -> Result<SlicedImages, AssetError> {
+        let frames = match &self.slice {
+            Slice::Grid { .. } if self.target.is_tagged() => {
+                return Err(AssetError::Manifest {
+                    path: self.path.clone(),
+                    message: format!("{:?} needs an aseprite slice", self.target),
+                });
+            }
+            Slice::Aseprite { .. } if !self.target.is_tagged() => {
+                return Err(AssetError::Manifest {
+                    path: self.path.clone(),
+                    message: format!("{:?} needs a grid slice", self.target),
+                });
+            }
+            Slice::Grid {
+                tile_size,
+                columns,
+                rows,
+            } => utils::grid_rects(
+                Vec2::new(tile_size[0] as f32, tile_size[1] as f32),
+                *columns,
+                *rows,
+            )
+            .into_iter()
+            .map(|rect| (String::new(), rect))
+            .collect::<Vec<_>>(),
+            Slice::Aseprite { config } => utils::load_aseprite_frames(format!("assets/{config}"))?,
+        };
+        let texture = utils::open_image(format!("assets/{}", self.path))?;
+        let size = texture.size();
+        if let Some((_, rect)) = frames
+            .iter()
+            .find(|(_, rect)| rect.max.x > size.x || rect.max.y > size.y)
+        {
+            return Err(AssetError::OutOfBounds {
+                path: self.path.clone(),
+                rect: *rect,
+                size,
+            });
+        }
+        let crop = |frames: &[(String, Rect)]| {
+            frames
+                .iter()
+                .map(|(_, rect)| utils::crop_image(&texture, *rect))
+                .collect::<Vec<_>>()
+        };
+        if !self.target.is_atlas() {
+            return Ok(SlicedImages::Grid(crop(&frames)));
+        }
+        // gun card 中需要显示 gun 的贴图
+        let cropped = match self.target {
+            SheetTarget::Gun(_) => crop(&frames),
+            _ => vec![],
+        };
+        Ok(SlicedImages::Atlas {
+            texture,
+            frames,
+            cropped,
+        })
+    }
+}
@@ -1,337 +1,182 @@
-use std::collections::{HashMap, HashSet};
-use std::f32::consts::SQRT_2;
-
+use bevy::asset::LoadState;
 use bevy::prelude::*;
-use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy_task_queue::{AssetTasks, ComputeTasks, TaskQueue};
 
-pub use actor::{ActorAction, ActorDirection, ActorGunHand, ResActor, ResGun};
-pub use cache::{ActorAssets, Cache};
-use world_generator::LevelModel;
+pub use actor::{
+    ActorAction, ActorDirection, ActorGunHand, ResActor, ResGun, ROLL_COOLDOWN, ROLL_DISTANCE,
+    ROLL_DURATION, ROLL_INVULNERABLE,
+};
+pub use cache::{ActorAssets, Cache, Placeholder, TileAnimationCache};
+pub use error::AssetError;
+pub use level::{leave_level, unload_level, ActiveLevel, LevelEntity};
+pub use loading::{insert_loaded_res, LoadedRes};
+pub use manifest::AssetManifest;
+pub use registry::{CharacterDefinition, CharacterRegistry, SelectedCharacter};
 
-use crate::character::CopActor;
-use crate::res::cache::ActorCache;
+use crate::character::{CopActor, SPAWN_TAG};
+use crate::res::manifest::{
+    ImageManifest, ImageTarget, MeshTarget, SheetTarget, SlicedImages, MANIFEST_PATH,
+};
 use crate::utils;
 
 mod actor;
 mod cache;
+mod error;
+mod level;
+mod loading;
+pub mod manifest;
+mod registry;
 
 pub const SCALE_RATIO: f32 = 0.05;
 pub const GRID_SIZE: f32 = 16.0;
 pub const GRID_SIZE_HALF: f32 = 8.0;
 
-pub fn initial_res(mut cache: ResMut<Cache>, mut images: ResMut<Assets<Image>>) {
-    for image in utils::split_images_to_vec("assets/art/ui/dragon.png", Vec2::new(74.0, 77.0), 8, 1)
-    {
-        cache.ui_title_dragon.push(images.add(image));
-    }
-}
-
-pub fn reset_res(
+/// 创建占位资源，读取资源清单和角色定义，并加载启动时就需要的标题界面动画
+///
+/// 清单或者贴图有问题时只输出错误，缺少的资源会显示成占位贴图
+/// 角色用到的贴图会补充到清单中，和其他贴图一起在 loading 中加载，
+/// 选择角色时预览的待机动画在打开选择界面时才裁剪，见 [`insert_character_previews`]
+pub fn initial_res(
+    mut c: Commands,
     mut cache: ResMut<Cache>,
-    mut actor: ResMut<ResActor>,
     mut images: ResMut<Assets<Image>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    server: Res<AssetServer>,
 ) {
-    let level = LevelModel::from("assets/levels/demo_output.json").unwrap();
-    cache.levels.push(level.clone());
-    actor.set_tilemap_pos({
-        // 把birth point设定成[1,1]了
-        // [0,0]点是左下角为原点的位置
-        // 但是实际放入bevy坐标系时，是不处理gird point到world point的处理的
-        // 也就是说实际上x轴和y轴都偏移的8像素
-        let pos = level.brith_point;
-        [pos[0] as f32 * GRID_SIZE, pos[1] as f32 * GRID_SIZE]
-    });
+    cache.placeholder = Placeholder::new(&mut images, &mut meshes, &mut materials);
+    let fallback = cache.placeholder.image.clone();
+    cache.ui_ascii_font.set_fallback(fallback.clone());
+    cache.ui_title_dragon = vec![fallback];
 
-    // 收集所有的尺寸用来创建mesh
-    let mut tile_meshes_set = HashSet::new();
-    // 加载所有的 tile image
-    for tileset in level.tilesets.iter() {
-        let mut tileset_images = HashMap::new();
-        let mut tileset_materials = HashMap::new();
-        let mut dynamic_image = image::open(format!("assets/{}", tileset.src)).unwrap();
-        let buffer = dynamic_image.as_mut_rgba8().unwrap();
-        for (index, rect) in tileset.tiles.iter() {
-            tile_meshes_set.insert((rect.1[0], rect.1[1]));
-            let sub_buffer = image::imageops::crop(
-                buffer,
-                rect.0[0] as u32,
-                rect.0[1] as u32,
-                rect.1[0] as u32,
-                rect.1[1] as u32,
-            )
-            .to_image();
-            let sub_image = Image::new(
-                Extent3d {
-                    width: sub_buffer.width(),
-                    height: sub_buffer.height(),
-                    depth_or_array_layers: 1,
-                },
-                TextureDimension::D2,
-                sub_buffer.into_raw(),
-                TextureFormat::Rgba8UnormSrgb,
-            );
-            let sub_image_handle = images.add(sub_image);
-            let material_handle = materials.add(StandardMaterial {
-                base_color_texture: Some(sub_image_handle.clone()),
-                perceptual_roughness: 0.9,
-                metallic: 0.0,
-                reflectance: 0.1,
-                alpha_mode: AlphaMode::Blend,
-                depth_bias: 1.0,
-                ..default()
-            });
-            tileset_images.insert(*index, sub_image_handle);
-            tileset_materials.insert(*index, material_handle);
+    let mut manifest = AssetManifest::from(MANIFEST_PATH).unwrap_or_else(|e| {
+        error!("{e}, all sprites will use the placeholder");
+        AssetManifest::default()
+    });
+    let registry = CharacterRegistry::load(&manifest.characters);
+    manifest.sheets.extend(registry.sheets());
+    manifest.images.extend(registry.images());
+    for sheet in manifest.sheets.iter() {
+        if sheet.target == SheetTarget::TitleDragon {
+            match sheet.slice() {
+                Ok(sliced) => loading::insert_sheet(
+                    &mut cache,
+                    &mut images,
+                    &mut meshes,
+                    &mut materials,
+                    sheet.clone(),
+                    sliced,
+                ),
+                Err(e) => error!("{e}, {:?} will use the placeholder", sheet.target),
+            }
         }
-        cache
-            .tile_images
-            .insert(tileset.uuid.clone(), tileset_images);
-        cache
-            .tile_materials
-            .insert(tileset.uuid.clone(), tileset_materials);
     }
+    c.insert_resource(manifest);
+    c.insert_resource(registry);
+}
 
-    // 汇总好的tile的每种尺寸都创建mesh
-    for (width, height) in tile_meshes_set {
-        cache.tile_meshes.insert(
-            (width as u32, height as u32),
-            meshes.add(Mesh::from(shape::Quad::new(Vec2::new(
-                SCALE_RATIO * width as f32,
-                SCALE_RATIO * height as f32,
-            )))),
-        );
-        cache.tile_meshes_sqrt2.insert(
-            (width as u32, height as u32),
-            meshes.add(Mesh::from(shape::Quad::new(Vec2::new(
-                SCALE_RATIO * width as f32,
-                SCALE_RATIO * height as f32 * SQRT_2,
-            )))),
-        );
-    }
-
-    cache.old_meshes.insert(
-        String::from("Tile28"),
-        meshes.add(Mesh::from(shape::Quad::new(Vec2::new(
-            28.0 * SCALE_RATIO,
-            28.0 * SCALE_RATIO,
-        )))),
-    );
-    cache.old_meshes.insert(
-        String::from("Tile28Flip"),
-        meshes.add(Mesh::from(shape::Quad {
-            size: Vec2::new(28.0 * SCALE_RATIO, 28.0 * SCALE_RATIO),
-            flip: true,
-        })),
-    );
-
-    // 加载actor convict相关资源
-    {
-        let mut convict_images = ActorAssets::default();
-        let mut convict_materials = ActorAssets::default();
-        for (tag, frames) in utils::load_actor_sprite(
-            "assets/art/character/The Convict.json",
-            "assets/art/character/The Convict.png",
-        ) {
-            for frame in frames {
-                let image_handle = images.add(frame);
-                let material_handle = materials.add(StandardMaterial {
-                    base_color_texture: Some(image_handle.clone()),
-                    alpha_mode: AlphaMode::Blend,
-                    unlit: true,
-                    depth_bias: 10.0,
-                    ..default()
-                });
-                convict_images.insert_frame(&tag, image_handle);
-                convict_materials.insert_frame(&tag, material_handle);
-            }
+/// 裁剪选择角色时预览的待机动画，已经裁剪过的贴图不会重复裁剪
+///
+/// 需要读取和解码角色的整张贴图，所以只在第一次打开选择界面时执行，不影响启动的速度
+pub fn insert_character_previews(
+    cache: &mut Cache,
+    images: &mut Assets<Image>,
+    registry: &CharacterRegistry,
+) {
+    for sheet in registry.sheets() {
+        let SheetTarget::Actor(name) = &sheet.target else {
+            continue;
+        };
+        if cache.ui_character_previews.contains_key(name) {
+            continue;
         }
-        cache
-            .actors_images
-            .insert(String::from("Convict"), convict_images);
-        cache
-            .actors_materials
-            .insert(String::from("Convict"), convict_materials);
+        let preview = match sheet.slice() {
+            Ok(SlicedImages::Atlas {
+                texture, frames, ..
+            }) => frames
+                .iter()
+                .filter(|(tag, _)| tag == SPAWN_TAG)
+                .map(|(_, rect)| images.add(utils::crop_image(&texture, *rect)))
+                .collect(),
+            Ok(SlicedImages::Grid(_)) => vec![],
+            Err(e) => {
+                error!("{e}, the preview of {name} will use the placeholder");
+                vec![]
+            }
+        };
+        // 失败时也记录下来，避免每次打开都重新读取
+        cache.ui_character_previews.insert(name.clone(), preview);
     }
+}
 
-    fn initial_texture<P>(
-        path: P,
-        tile_size: Vec2,
-        columns: usize,
-        rows: usize,
-        images: &mut ResMut<Assets<Image>>,
-        materials: &mut ResMut<Assets<StandardMaterial>>,
-        depth_bias: f32,
-    ) -> (
-        HashMap<u8, Handle<Image>>,
-        HashMap<u8, Handle<StandardMaterial>>,
-    )
-    where
-        P: AsRef<std::path::Path>,
-    {
-        let mut texture_atlas = HashMap::new();
-        let mut material_set = HashMap::new();
-        let mut dynamic_image = image::open(path).unwrap();
-        let buffer = dynamic_image.as_mut_rgba8().unwrap();
-        for y in 0..rows {
-            for x in 0..columns {
-                let cell = Vec2::new(x as f32, y as f32);
-                let rect_min = tile_size * cell;
-                let rect = Rect {
-                    min: rect_min,
-                    max: rect_min + tile_size,
-                };
-                let sub_buffer = image::imageops::crop(
-                    buffer,
-                    rect.min.x as u32,
-                    rect.min.y as u32,
-                    tile_size.x as u32,
-                    tile_size.y as u32,
-                )
-                .to_image();
-                let sub_image = Image::new(
-                    Extent3d {
-                        width: sub_buffer.width(),
-                        height: sub_buffer.height(),
-                        depth_or_array_layers: 1,
-                    },
-                    TextureDimension::D2,
-                    sub_buffer.into_raw(),
-                    TextureFormat::Rgba8UnormSrgb,
-                );
-                let sub_image_handle = images.add(sub_image);
-                let material_handle = materials.add(StandardMaterial {
-                    base_color_texture: Some(sub_image_handle.clone()),
-                    alpha_mode: AlphaMode::Blend,
-                    unlit: true,
-                    depth_bias,
-                    ..default()
-                });
-                material_set.insert((y * columns + x) as u8, material_handle);
-                texture_atlas.insert((y * columns + x) as u8, sub_image_handle);
-            }
+/// 每次进入游戏都按照选择的角色重新设置 [`ResActor`]，没有选择时使用第一个角色
+pub fn reset_actor(
+    registry: Res<CharacterRegistry>,
+    selected: Res<SelectedCharacter>,
+    mut actor: ResMut<ResActor>,
+) {
+    let character = registry.get(&selected.0).or_else(|| registry.first());
+    match character {
+        Some(character) => {
+            info!("start a run as {} ({})", character.name, character.id);
+            *actor = ResActor::from(character);
         }
-        (texture_atlas, material_set)
+        None => error!("there is no playable character"),
     }
+}
 
-    cache.char_hand_mesh = meshes.add(Mesh::from(shape::Quad {
-        size: Vec2::new(4.0 * SCALE_RATIO, 4.0 * SCALE_RATIO),
-        flip: false,
-    }));
-    cache.char_hand_image = server.load("art/character/hand.png");
-    cache.char_hand_material = materials.add(StandardMaterial {
-        base_color_texture: Some(cache.char_hand_image.clone()),
-        alpha_mode: AlphaMode::Blend,
-        unlit: true,
-        depth_bias: 5.0,
-        ..default()
-    });
+/// 进入游戏前需要等待完成的任务
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadingTask {
+    /// 关卡文件的路径
+    Level(String),
+    /// 图片的路径
+    Image(String),
+    Sounds,
+}
 
-    // 加载gun相关贴图资源
-    {
-        let (atlas, material_set) = initial_texture(
-            "assets/art/gun/Budget Revolver.png",
-            Vec2::new(16.0, 16.0),
-            4,
-            2,
-            &mut images,
-            &mut materials,
-            0.0,
-        );
-        cache
-            .gun_images
-            .insert(String::from("Budget Revolver"), atlas);
-        cache
-            .gun_materials
-            .insert(String::from("Budget Revolver"), material_set);
-        cache.gun_meshes.insert(
-            (16, 16),
-            meshes.add(Mesh::from(shape::Quad {
-                size: Vec2::new(16.0 * SCALE_RATIO, 16.0 * SCALE_RATIO),
-                flip: false,
-            })),
-        );
-        cache.gun_meshes_flip.insert(
-            (16, 16),
-            meshes.add(Mesh::from(shape::Quad {
-                size: Vec2::new(16.0 * SCALE_RATIO, 16.0 * SCALE_RATIO),
-                flip: true,
-            })),
-        );
+impl std::fmt::Display for LoadingTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadingTask::Level(path) => write!(f, "Loading level {path}"),
+            LoadingTask::Image(path) => write!(f, "Loading {path}"),
+            LoadingTask::Sounds => write!(f, "Loading sounds"),
+        }
     }
+}
 
-    // 加载bullet相关贴图资源
-    {
-        let bullet_image = server.load("art/gun/Budget Revolver Bullet.png");
-        cache
-            .bullet_images
-            .insert(String::from("Budget Revolver"), bullet_image.clone());
-        cache.bullet_materials.insert(
-            String::from("Budget Revolver"),
-            materials.add(StandardMaterial {
-                base_color_texture: Some(bullet_image.clone()),
-                alpha_mode: AlphaMode::Blend,
-                unlit: true,
-                depth_bias: 15.0,
-                ..default()
-            }),
-        );
-        cache.bullet_meshes.insert(
-            (5, 5),
-            meshes.add(Mesh::from(shape::Quad::new(Vec2::new(
-                5.0 * SCALE_RATIO,
-                5.0 * SCALE_RATIO,
-            )))),
-        );
-    }
+pub fn reset_res(
+    mut cache: ResMut<Cache>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    server: Res<AssetServer>,
+    manifest: Res<AssetManifest>,
+    mut task_queue: ResMut<TaskQueue<LoadingTask>>,
+    mut asset_tasks: ResMut<AssetTasks<LoadingTask>>,
+    mut compute_tasks: ResMut<ComputeTasks<LoadingTask, LoadedRes>>,
+) {
+    // 读取关卡和裁剪图片都在后台线程完成，结果由 insert_loaded_res 陆续放入 cache
+    loading::spawn_loading_jobs(&manifest, &mut task_queue, &mut compute_tasks);
 
-    {
-        for (index, image) in
-            utils::split_images("assets/art/ui/heart.png", Vec2::new(15.0, 13.0), 2, 2)
-        {
-            cache.ui_hp_images.insert(index, images.add(image));
-        }
-        cache.ui_blank_image = server.load("art/ui/blank.png");
-        cache.ui_key_image = server.load("art/ui/key.png");
-        cache.ui_money_image = server.load("art/ui/money.png");
-        cache
-            .ui_card_image
-            .insert(1, server.load("art/ui/gun_card.png"));
+    // 不需要裁剪的图片直接通过AssetServer加载，加载失败时由 replace_failed_images 换成占位贴图
+    for image in manifest.images.iter() {
+        let handle: Handle<Image> = server.load(image.path.as_str());
+        let task = LoadingTask::Image(image.path.clone());
+        asset_tasks.track_optional(&mut task_queue, &server, &handle, task);
+        insert_image(&mut cache, &mut materials, image, handle);
+    }
 
-        {
-            let mut ammo = None;
-            let mut empty_ammo = None;
-            for (index, image) in utils::split_images(
-                "assets/art/ui/ammo/ammo_budget_revolver.png",
-                Vec2::new(5.0, 3.0),
-                2,
-                1,
-            ) {
-                match index {
-                    0 => ammo = Some(image),
-                    1 => empty_ammo = Some(image),
-                    _ => break,
-                }
-            }
-            let ammo = ammo.unwrap();
-            let empty_ammo = empty_ammo.unwrap();
-            cache.ui_ammo_images.insert(
-                String::from("budget_revolver"),
-                (images.add(ammo), images.add(empty_ammo)),
-            );
-            cache.ui_ammo_border = server.load("art/ui/ammo_border.png")
-        }
-        {
-            // 目前ascii的贴图中存的就是从第33个ascii码的空格开始到第127个（最后第二个）ascii码的～的像素
-            for (index, image) in
-                utils::split_images("assets/art/ui/font/ascii.png", Vec2::new(7.0, 9.0), 10, 10)
-            {
-                cache
-                    .ui_ascii_font
-                    .set((index + 32) as char, images.add(image));
+    for mesh in manifest.meshes.iter() {
+        let key = (mesh.size[0], mesh.size[1]);
+        let handle = meshes.add(Mesh::from(shape::Quad {
+            size: Vec2::new(mesh.size[0] as f32, mesh.size[1] as f32) * SCALE_RATIO,
+            flip: mesh.flip,
+        }));
+        match &mesh.target {
+            MeshTarget::Bullet => {
+                cache.bullet_meshes.insert(key, handle);
             }
+            MeshTarget::CharHand => cache.char_hand_mesh = handle,
+            MeshTarget::CloudPuff => cache.actor_caches.cloud_puff_mesh = handle,
         }
     }
 
@@ -363,29 +208,66 @@ pub fn reset_res(
             ..default()
         });
     }
+    // 进入游戏前需要等待音效加载完成，缺少音效时只是没有声音
     {
-        let mut cloud_puff_materials = vec![];
-        for image in utils::split_images_to_vec(
-            "assets/art/actor/the_convict/cloud_puff.png",
-            Vec2::new(11.0, 11.0),
-            6,
-            1,
-        ) {
-            cloud_puff_materials.push(materials.add(StandardMaterial {
-                base_color_texture: Some(images.add(image)),
-                alpha_mode: AlphaMode::Blend,
-                unlit: true,
-                depth_bias: 15.0,
-                ..default()
-            }));
+        let (_, sounds) = asset_tasks.track_folder_optional(
+            &mut task_queue,
+            &server,
+            "sound",
+            LoadingTask::Sounds,
+        );
+        cache.sounds = sounds;
+    }
+}
+
+/// 把通过 AssetServer 加载失败的图片换成占位贴图，加载任务只输出警告，不会阻止进入游戏
+///
+/// 在离开 loading 时执行，这时所有图片都已经加载完成或者失败了
+pub fn replace_failed_images(
+    mut cache: ResMut<Cache>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    server: Res<AssetServer>,
+    manifest: Res<AssetManifest>,
+) {
+    for image in manifest.images.iter() {
+        if server.get_load_state(image.path.as_str()) != LoadState::Failed {
+            continue;
         }
-        cache.actor_caches = ActorCache {
-            cloud_puff_mesh: meshes.add(Mesh::from(shape::Quad::new(Vec2::new(
-                11.0 * SCALE_RATIO,
-                11.0 * SCALE_RATIO,
-            )))),
-            cloud_puff_materials,
-        };
+        let placeholder = cache.placeholder.image.clone();
+        insert_image(&mut cache, &mut materials, image, placeholder);
+    }
+}
+
+/// 按照 `image.target` 把图片放入 [`Cache`]，需要 material 的图片同时创建 material
+fn insert_image(
+    cache: &mut Cache,
+    materials: &mut Assets<StandardMaterial>,
+    image: &ImageManifest,
+    handle: Handle<Image>,
+) {
+    let material = image
+        .material
+        .as_ref()
+        .map(|material| materials.add(material.material(handle.clone())));
+    match (&image.target, material) {
+        (ImageTarget::CharHand, Some(material)) => {
+            cache.char_hand_images.insert(image.path.clone(), handle);
+            cache
+                .char_hand_materials
+                .insert(image.path.clone(), material);
+        }
+        (ImageTarget::Bullet(name), Some(material)) => {
+            cache.bullet_images.insert(name.clone(), handle);
+            cache.bullet_materials.insert(name.clone(), material);
+        }
+        (ImageTarget::UiBlank, _) => cache.ui_blank_image = handle,
+        (ImageTarget::UiKey, _) => cache.ui_key_image = handle,
+        (ImageTarget::UiMoney, _) => cache.ui_money_image = handle,
+        (ImageTarget::UiCard(index), _) => {
+            cache.ui_card_image.insert(*index, handle);
+        }
+        (ImageTarget::UiAmmoBorder, _) => cache.ui_ammo_border = handle,
+        (target, None) => warn!("{}: {target:?} needs a material", image.path),
     }
 }
 
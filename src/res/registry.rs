@@ -0,0 +1,169 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::res::manifest::{
+    ImageManifest, ImageTarget, MaterialManifest, SheetManifest, SheetTarget, Slice,
+};
+use crate::res::AssetError;
+
+/// 角色贴图和 hand 贴图的 depth_bias
+const ACTOR_DEPTH_BIAS: f32 = 10.0;
+const HAND_DEPTH_BIAS: f32 = 5.0;
+
+/// 一个可以选择的角色，保存在 `assets/characters` 目录下，在 `assets/manifest.json` 的 `characters` 中列出
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CharacterDefinition {
+    /// 角色的唯一标识
+    pub id: String,
+    /// 显示的名称
+    pub name: String,
+    pub stats: CharacterStats,
+    #[serde(default)]
+    pub items: CharacterItems,
+    pub sprite: CharacterSprite,
+    /// hand 贴图相对 `assets` 目录的路径
+    pub hand: String,
+    /// 初始的枪械，目前只会装备第一把
+    #[serde(default)]
+    pub guns: Vec<GunDefinition>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CharacterStats {
+    /// 半颗心为1
+    pub hp: u8,
+    pub move_speed: f32,
+}
+
+/// 初始的道具数量
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CharacterItems {
+    #[serde(default)]
+    pub blanks: u8,
+    #[serde(default)]
+    pub keys: u8,
+    #[serde(default)]
+    pub money: u16,
+}
+
+/// 角色使用的 aseprite 贴图，不同角色可以共用同一个 `name` 的贴图
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CharacterSprite {
+    /// 在 [`crate::res::Cache`] 中的名称
+    pub name: String,
+    pub path: String,
+    /// aseprite 导出的 json
+    pub config: String,
+}
+
+/// gun 的贴图在 `assets/manifest.json` 中，这里是角色握住 gun 的位置信息，都是像素单位
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GunDefinition {
+    pub name: String,
+    /// 枪械相对角色的位置偏移
+    pub offset: [f32; 2],
+    /// 手的位置相对枪械的偏移
+    pub hand_offset: [f32; 2],
+    /// 发射bullet的位置相对枪械的偏移
+    pub fire_offset: [f32; 2],
+}
+
+impl CharacterDefinition {
+    pub fn from<P: AsRef<std::path::Path>>(path: P) -> Result<Self, AssetError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|error| AssetError::Io {
+            path: path.display().to_string(),
+            error,
+        })?;
+        serde_json::from_str(&content).map_err(|error| AssetError::Parse {
+            path: path.display().to_string(),
+            error: error.to_string(),
+        })
+    }
+}
+
+/// 标题界面选择的角色 id
+#[derive(Resource, Debug, Clone, Default)]
+pub struct SelectedCharacter(pub String);
+
+/// 所有可以选择的角色，顺序和清单中一致
+#[derive(Resource, Debug, Clone, Default)]
+pub struct CharacterRegistry {
+    characters: Vec<CharacterDefinition>,
+}
+
+impl CharacterRegistry {
+    /// `paths` 是相对 `assets` 目录的路径，读取失败的角色只输出错误
+    pub fn load(paths: &[String]) -> Self {
+        Self::load_from("assets", paths)
+    }
+    /// `paths` 是相对 `dir` 的路径
+    pub fn load_from<P: AsRef<std::path::Path>>(dir: P, paths: &[String]) -> Self {
+        let characters = paths
+            .iter()
+            .filter_map(
+                |path| match CharacterDefinition::from(dir.as_ref().join(path)) {
+                    Ok(character) => Some(character),
+                    Err(e) => {
+                        error!("{e}, the character will not be available");
+                        None
+                    }
+                },
+            )
+            .collect();
+        Self { characters }
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &CharacterDefinition> {
+        self.characters.iter()
+    }
+    pub fn get(&self, id: &str) -> Option<&CharacterDefinition> {
+        self.characters.iter().find(|c| c.id == id)
+    }
+    pub fn len(&self) -> usize {
+        self.characters.len()
+    }
+    /// 没有选择角色时使用的角色
+    pub fn first(&self) -> Option<&CharacterDefinition> {
+        self.characters.first()
+    }
+    /// 角色需要切割的贴图，共用的贴图只切割一次
+    pub fn sheets(&self) -> Vec<SheetManifest> {
+        let mut sheets: Vec<SheetManifest> = vec![];
+        for sprite in self.characters.iter().map(|c| &c.sprite) {
+            let target = SheetTarget::Actor(sprite.name.clone());
+            if sheets.iter().any(|sheet| sheet.target == target) {
+                continue;
+            }
+            sheets.push(SheetManifest {
+                path: sprite.path.clone(),
+                slice: Slice::Aseprite {
+                    config: sprite.config.clone(),
+                },
+                target,
+                material: Some(MaterialManifest {
+                    depth_bias: ACTOR_DEPTH_BIAS,
+                    ..default()
+                }),
+            });
+        }
+        sheets
+    }
+    /// 角色的 hand 贴图，相同路径的只加载一次
+    pub fn images(&self) -> Vec<ImageManifest> {
+        let mut images: Vec<ImageManifest> = vec![];
+        for hand in self.characters.iter().map(|c| &c.hand) {
+            if images.iter().any(|image| &image.path == hand) {
+                continue;
+            }
+            images.push(ImageManifest {
+                path: hand.clone(),
+                target: ImageTarget::CharHand,
+                material: Some(MaterialManifest {
+                    depth_bias: HAND_DEPTH_BIAS,
+                    ..default()
+                }),
+            });
+        }
+        images
+    }
+}
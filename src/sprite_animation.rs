@@ -56,10 +56,12 @@ pub fn update_sprite(
             if let Some(material) = &sprite.material {
                 *material_handle = material.clone();
             }
-            if sprite.flip_x {
-                *mesh_handle = cache.get_character_mesh_flip().clone();
-            } else {
-                *mesh_handle = cache.get_character_mesh().clone();
+            // 同一个actor的material是共用的，每一帧只有mesh的uv不同
+            if let Some(detail) = &sprite.detail {
+                let meshes = cache.get_actor_meshes(&detail.name, &detail.tag, sprite.flip_x);
+                if let Some(mesh) = meshes.get(detail.index) {
+                    *mesh_handle = mesh.clone();
+                }
             }
             sprite.changed = false;
         }
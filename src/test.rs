@@ -0,0 +1,256 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use bevy::prelude::*;
+use world_generator::LevelModel;
+
+use crate::character::{ActorTag, SPAWN_TAG};
+use crate::cursor::ResCursor;
+use crate::res::manifest::{Slice, MANIFEST_PATH};
+use crate::res::{
+    ActiveLevel, AssetManifest, Cache, CharacterRegistry, LevelEntity, ResActor, ROLL_COOLDOWN,
+    ROLL_DISTANCE, ROLL_DURATION, ROLL_INVULNERABLE,
+};
+use crate::{character, res, tilemap, ui, utils, AppState};
+
+/// 和 [`crate::res::initial_res`] 一样，角色用到的贴图也补充到清单中
+fn manifest() -> AssetManifest {
+    let mut manifest = AssetManifest::from(MANIFEST_PATH).unwrap();
+    let registry = CharacterRegistry::load(&manifest.characters);
+    manifest.sheets.extend(registry.sheets());
+    manifest.images.extend(registry.images());
+    manifest
+}
+
+#[test]
+fn check_characters() {
+    for path in AssetManifest::from(MANIFEST_PATH).unwrap().characters {
+        let registry = CharacterRegistry::load(&[path.clone()]);
+        assert!(
+            registry.first().is_some(),
+            "{path} is not a valid character"
+        );
+    }
+}
+
+/// 读取多个角色定义，读取失败的跳过，顺序和 `paths` 一致
+#[test]
+fn check_character_registry() {
+    let paths = ["rookie.json", "broken.json", "veteran.json", "missing.json"].map(String::from);
+    let registry = CharacterRegistry::load_from("tests/characters", &paths);
+    let ids = registry.iter().map(|c| c.id.as_str()).collect::<Vec<_>>();
+    assert_eq!(ids, ["rookie", "veteran"]);
+    assert_eq!(registry.len(), 2);
+    assert_eq!(registry.first().unwrap().id, "rookie");
+
+    let rookie = registry.get("rookie").unwrap();
+    assert_eq!(rookie.stats.hp, 4);
+    assert_eq!(rookie.items.money, 0);
+    assert!(rookie.guns.is_empty());
+    let veteran = registry.get("veteran").unwrap();
+    assert_eq!(veteran.items.money, 50);
+    assert_eq!(veteran.guns[0].name, "Budget Revolver");
+    assert!(registry.get("broken").is_none());
+
+    // 贴图名称不同的角色分别切割，相同的 hand 贴图只加载一次
+    assert_eq!(registry.sheets().len(), 2);
+    assert_eq!(registry.images().len(), 1);
+}
+
+#[test]
+fn check_character_tags() {
+    for sheet in manifest().sheets.iter() {
+        let Slice::Aseprite { config } = &sheet.slice else {
+            continue;
+        };
+        let frames = utils::load_aseprite_frames(format!("assets/{config}")).unwrap();
+        let tags = frames
+            .into_iter()
+            .map(|(tag, _)| tag)
+            .collect::<HashSet<_>>();
+        for tag in ActorTag::ALL.iter().map(|tag| tag.tag()).chain([SPAWN_TAG]) {
+            assert!(tags.contains(tag), "{config} is missing tag {tag}");
+        }
+    }
+}
+
+/// 清单和角色定义中的路径都需要存在
+#[test]
+fn check_paths_resolve() {
+    let manifest = manifest();
+    let registry = CharacterRegistry::load(&manifest.characters);
+    let mut paths = manifest
+        .characters
+        .iter()
+        .map(|path| path.as_str())
+        .collect::<Vec<_>>();
+    for sheet in manifest.sheets.iter() {
+        paths.push(sheet.path.as_str());
+        if let Slice::Aseprite { config } = &sheet.slice {
+            paths.push(config.as_str());
+        }
+    }
+    paths.extend(manifest.images.iter().map(|image| image.path.as_str()));
+    for character in registry.iter() {
+        paths.push(character.sprite.path.as_str());
+        paths.push(character.sprite.config.as_str());
+        paths.push(character.hand.as_str());
+    }
+    // 关卡文件需要先通过 world_generator 生成，所以不在这里检查
+    for path in paths {
+        assert!(
+            Path::new("assets").join(path).exists(),
+            "assets/{path} does not exist"
+        );
+    }
+}
+
+#[test]
+fn check_sheet_slices() {
+    for sheet in manifest().sheets.iter() {
+        if let Slice::Grid {
+            tile_size,
+            columns,
+            rows,
+        } = &sheet.slice
+        {
+            let (width, height) =
+                image::image_dimensions(format!("assets/{}", sheet.path)).unwrap();
+            assert_eq!(
+                [width, height],
+                [tile_size[0] * *columns as u32, tile_size[1] * *rows as u32],
+                "{} does not match its grid",
+                sheet.path
+            );
+        }
+        // aseprite 的每一帧都需要在贴图范围内
+        if let Err(e) = sheet.slice() {
+            panic!("{e}");
+        }
+    }
+}
+
+/// 和 `main` 中一样在进入 `InGame` 时创建关卡、角色和 ui，离开时卸载关卡
+fn level_app() -> App {
+    let mut app = App::new();
+    let registry = CharacterRegistry::load(&manifest().characters);
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(AssetPlugin::default())
+        .add_state::<AppState>()
+        .init_resource::<Cache>()
+        .init_resource::<ResCursor>()
+        .init_resource::<Input<KeyCode>>()
+        .insert_resource(ResActor::from(registry.first().unwrap()))
+        .insert_resource(ActiveLevel {
+            path: String::from("levels/test.json"),
+            model: LevelModel::default(),
+        })
+        .add_systems(
+            (
+                tilemap::setup,
+                ui::cursor::setup,
+                ui::status::setup,
+                ui::gun_card::setup,
+                ui::item_card::setup,
+                ui::ammo::setup,
+                character::setup,
+            )
+                .in_schedule(OnEnter(AppState::InGame)),
+        )
+        .add_system(res::unload_level.in_schedule(OnExit(AppState::InGame)))
+        .add_system(res::leave_level.in_set(OnUpdate(AppState::InGame)));
+    app
+}
+
+#[test]
+fn check_leave_level() {
+    let mut app = level_app();
+    app.world
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::InGame);
+    app.update();
+    assert_eq!(app.world.resource::<State<AppState>>().0, AppState::InGame);
+    let spawned = app
+        .world
+        .query_filtered::<(), With<LevelEntity>>()
+        .iter(&app.world)
+        .count();
+    assert!(spawned > 0);
+
+    // 按下 Esc 离开关卡，下一帧切换到标题界面
+    app.world
+        .resource_mut::<Input<KeyCode>>()
+        .press(KeyCode::Escape);
+    app.update();
+    app.update();
+    assert_eq!(app.world.resource::<State<AppState>>().0, AppState::Title);
+    assert!(!app.world.contains_resource::<ActiveLevel>());
+    // 关卡、角色和 ui 都是 LevelEntity 或者它的子 entity，卸载后不会留下任何 entity
+    let remaining = app.world.query::<Entity>().iter(&app.world).count();
+    assert_eq!(remaining, 0);
+}
+
+#[test]
+fn check_roll_blocked_by_cooldown() {
+    let mut actor = ResActor::default();
+    assert!(!actor.try_roll(Vec2::ZERO));
+    assert!(actor.try_roll(Vec2::X));
+    // 翻滚中不能再次翻滚
+    assert!(!actor.try_roll(Vec2::Y));
+    actor.roll_step(ROLL_DURATION);
+    assert!(!actor.is_rolling());
+    // 刚结束时还在冷却
+    assert!(!actor.try_roll(Vec2::Y));
+    actor.tick_roll_cooldown(ROLL_COOLDOWN);
+    assert!(actor.try_roll(Vec2::Y));
+}
+
+#[test]
+fn check_roll_distance() {
+    let mut actor = ResActor::default();
+    assert!(actor.try_roll(Vec2::new(3.0, 4.0)));
+    let delta = 1.0 / 60.0;
+    let mut offset = Vec2::ZERO;
+    let mut elapsed = 0.0;
+    while actor.is_rolling() {
+        offset += actor.roll_step(delta);
+        elapsed += delta;
+    }
+    // 沿着方向移动固定的距离，用时是 ROLL_DURATION
+    assert!((offset.length() - ROLL_DISTANCE).abs() < 1e-3, "{offset}");
+    assert!((offset.normalize() - Vec2::new(0.6, 0.8)).length() < 1e-4);
+    assert!(elapsed > ROLL_DURATION - 1e-4 && elapsed < ROLL_DURATION + delta);
+    assert_eq!(actor.roll_step(delta), Vec2::ZERO);
+}
+
+#[test]
+fn check_roll_cooldown_after_roll() {
+    let mut actor = ResActor::default();
+    assert!(actor.try_roll(Vec2::X));
+    // 翻滚中不会冷却，冷却从翻滚结束时开始计算
+    actor.tick_roll_cooldown(ROLL_COOLDOWN);
+    actor.roll_step(ROLL_DURATION / 2.0);
+    actor.tick_roll_cooldown(ROLL_COOLDOWN);
+    actor.roll_step(ROLL_DURATION / 2.0);
+    assert!(!actor.is_rolling());
+    actor.tick_roll_cooldown(ROLL_COOLDOWN / 2.0);
+    assert!(!actor.try_roll(Vec2::X));
+    actor.tick_roll_cooldown(ROLL_COOLDOWN / 2.0);
+    assert!(actor.try_roll(Vec2::X));
+}
+
+#[test]
+fn check_roll_invulnerable() {
+    let mut actor = ResActor::default();
+    assert!(!actor.is_invulnerable());
+    assert!(actor.try_roll(Vec2::X));
+    assert!(actor.is_invulnerable());
+    actor.roll_step(ROLL_INVULNERABLE / 2.0);
+    assert!(actor.is_invulnerable());
+    // 翻滚后半段可以被击中
+    actor.roll_step(ROLL_INVULNERABLE / 2.0);
+    assert!(actor.is_rolling());
+    assert!(!actor.is_invulnerable());
+    actor.roll_step(ROLL_DURATION);
+    assert!(!actor.is_invulnerable());
+}
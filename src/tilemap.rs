@@ -3,18 +3,16 @@ use std::f32::consts::SQRT_2;
 
 use bevy::prelude::*;
 
-use crate::res::{Cache, GRID_SIZE, SCALE_RATIO};
+use crate::res::{
+    ActiveLevel, Cache, LevelEntity, ResActor, GRID_SIZE, GRID_SIZE_HALF, SCALE_RATIO,
+};
 use crate::utils;
 
-pub fn setup(mut c: Commands, cache: Res<Cache>) {
-    let level = &cache.levels[0];
-    let mut tilesets = HashMap::new();
-    for tileset in cache.levels[0].tilesets.iter() {
-        tilesets.insert(tileset.uuid.clone(), tileset.clone());
-    }
-
+pub fn setup(mut c: Commands, cache: Res<Cache>, level: Res<ActiveLevel>) {
+    // tileset 或者 tile 缺失时 Cache 会返回占位资源，只输出一次警告
     c.spawn(SpatialBundle::default())
         .insert(Name::new("Rooms"))
+        .insert(LevelEntity)
         .with_children(|p| {
             p.spawn((
                 Name::from("debug node"),
@@ -63,22 +61,17 @@ pub fn setup(mut c: Commands, cache: Res<Cache>) {
                     ))
                     .with_children(|p| {
                         for tile_group in room.walls.iter() {
-                            let tileset = tilesets.get(&tile_group.tileset_uuid).unwrap();
                             for (grid_x, col) in tile_group.tiles.iter() {
                                 for (grid_y, index) in col.iter() {
-                                    let tile_info = tileset.tiles.get(index).unwrap();
-                                    let width = tile_info.1[0] as u32;
-                                    let height = tile_info.1[1] as u32;
                                     p.spawn((
                                         Name::new("Wall"),
                                         utils::tile_wall_sprite(
-                                            cache.get_tile_mesh_sqrt2((width, height)),
+                                            cache.get_tile_mesh_sqrt2(&tile_group.tileset_uuid, *index),
                                             cache.get_tile_material(
                                                 &tile_group.tileset_uuid,
                                                 *index,
                                             ),
                                             [*grid_x as i32, *grid_y as i32],
-                                            height,
                                         ),
                                     ));
                                 }
@@ -90,16 +83,12 @@ pub fn setup(mut c: Commands, cache: Res<Cache>) {
                     p.spawn((Name::new("Floors"), SpatialBundle::default()))
                         .with_children(|p| {
                             for tile_group in room.floors.iter() {
-                                let tileset = tilesets.get(&tile_group.tileset_uuid).unwrap();
                                 for (grid_x, col) in tile_group.tiles.iter() {
                                     for (grid_y, index) in col.iter() {
-                                        let tile_info = tileset.tiles.get(index).unwrap();
-                                        let width = tile_info.1[0] as u32;
-                                        let height = tile_info.1[1] as u32;
                                         p.spawn((
                                             Name::new("Floor"),
                                             utils::tile_floor_sprite(
-                                                cache.get_tile_mesh_sqrt2((width, height)),
+                                                cache.get_tile_mesh_sqrt2(&tile_group.tileset_uuid, *index),
                                                 cache.get_tile_material(
                                                     &tile_group.tileset_uuid,
                                                     *index,
@@ -129,16 +118,12 @@ pub fn setup(mut c: Commands, cache: Res<Cache>) {
                     ))
                     .with_children(|p| {
                         for tile_group in room.roofs.iter() {
-                            let tileset = tilesets.get(&tile_group.tileset_uuid).unwrap();
                             for (grid_x, col) in tile_group.tiles.iter() {
                                 for (grid_y, index) in col.iter() {
-                                    let tile_info = tileset.tiles.get(index).unwrap();
-                                    let width = tile_info.1[0] as u32;
-                                    let height = tile_info.1[1] as u32;
                                     p.spawn((
                                         Name::new("Roof"),
                                         utils::tile_floor_sprite(
-                                            cache.get_tile_mesh_sqrt2((width, height)),
+                                            cache.get_tile_mesh_sqrt2(&tile_group.tileset_uuid, *index),
                                             cache.get_tile_material(
                                                 &tile_group.tileset_uuid,
                                                 *index,
@@ -192,5 +177,84 @@ pub fn setup(mut c: Commands, cache: Res<Cache>) {
             .looking_to(Vec3::new(0.0, -1.0, -1.0), Vec3::Y),
         ..default()
     })
-    .insert(Name::new("Global Light"));
+    .insert(Name::new("Global Light"))
+    .insert(LevelEntity);
+}
+
+/// 播放动态 tile 的动画
+///
+/// 同一种动态 tile 的所有实例共用 mesh，所以只需要在换帧的时候修改 mesh 的 uv，所有实例保持同步
+pub fn animate_tiles(
+    time: Res<Time>,
+    cache: Res<Cache>,
+    level: Res<ActiveLevel>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut current_frames: Local<HashMap<usize, usize>>,
+) {
+    if level.is_added() {
+        current_frames.clear();
+    }
+    for (i, tile_animation) in cache.tile_animations.iter().enumerate() {
+        let frame = tile_animation.animation.frame_at(time.elapsed_seconds());
+        if current_frames.get(&i) == Some(&frame) {
+            continue;
+        }
+        current_frames.insert(i, frame);
+        let uvs = utils::quad_uvs(tile_animation.uv_rects[frame], false);
+        for handle in tile_animation.meshes.iter() {
+            if let Some(mesh) = meshes.get_mut(handle) {
+                mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs.clone());
+            }
+        }
+    }
+}
+
+/// actor 进入了一个 room，room 是在 [`world_generator::LevelModel::rooms`] 中的位置
+pub struct RoomEnterEvent {
+    pub room: usize,
+    pub display_name: String,
+}
+
+/// actor 离开了一个 room
+pub struct RoomExitEvent {
+    pub room: usize,
+    pub display_name: String,
+}
+
+/// actor 所在的 room 发生变化时发出 [`RoomExitEvent`] 和 [`RoomEnterEvent`]
+pub fn detect_room_change(
+    actor: Res<ResActor>,
+    level: Res<ActiveLevel>,
+    mut current_room: Local<Option<usize>>,
+    mut enter_ev: EventWriter<RoomEnterEvent>,
+    mut exit_ev: EventWriter<RoomExitEvent>,
+) {
+    // 换了新的关卡之后上一个关卡的 room 已经不存在了
+    if level.is_added() {
+        *current_room = None;
+    }
+    // 和判断碰撞时一样，actor 的位置是 tile 的中心点，需要偏移(8,8)
+    let pos = actor.get_tilemap_pos();
+    let grid_pos = [
+        ((pos[0] + GRID_SIZE_HALF) / GRID_SIZE).floor() as i32,
+        ((pos[1] + GRID_SIZE_HALF) / GRID_SIZE).floor() as i32,
+    ];
+    let room = level.room_index_at(grid_pos);
+    if room == *current_room {
+        return;
+    }
+    if let Some(old) = *current_room {
+        exit_ev.send(RoomExitEvent {
+            room: old,
+            display_name: level.rooms[old].display_name.clone(),
+        });
+    }
+    if let Some(new) = room {
+        debug!("enter room {}", level.rooms[new].display_name);
+        enter_ev.send(RoomEnterEvent {
+            room: new,
+            display_name: level.rooms[new].display_name.clone(),
+        });
+    }
+    *current_room = room;
 }
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::res::Cache;
+use crate::res::{Cache, LevelEntity};
 
 #[derive(Component)]
 pub struct AmmoBorderTopUi;
@@ -33,6 +33,7 @@ pub fn setup(mut c: Commands, cache: Res<Cache>) {
             ..default()
         },
         AmmoBorderBottomUi,
+        LevelEntity,
     ));
     bottom += 10.0;
     let ui_ammo_image = cache.get_ui_ammo_images("budget_revolver").0.clone();
@@ -53,6 +54,7 @@ pub fn setup(mut c: Commands, cache: Res<Cache>) {
                 ..default()
             },
             AmmoUi { index: i },
+            LevelEntity,
         ));
         bottom += 6.0;
     }
@@ -72,5 +74,6 @@ pub fn setup(mut c: Commands, cache: Res<Cache>) {
             ..default()
         },
         AmmoBorderTopUi,
+        LevelEntity,
     ));
 }
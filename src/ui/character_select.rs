@@ -0,0 +1,281 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::res::{self, Cache, CharacterDefinition, CharacterRegistry, SelectedCharacter};
+use crate::ui::title::{TitleStart, TitleUi};
+use crate::ui_image_animation::UiImageAnimation;
+use crate::AppState;
+
+const CARD_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.1);
+const CARD_SELECTED_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.4);
+/// 角色贴图每一帧是28像素，预览时放大4倍
+const PREVIEW_SIZE: f32 = 28.0 * 4.0;
+
+/// 正在选择角色，`index` 是 [`CharacterRegistry`] 中的顺序，只在选择界面打开时存在
+#[derive(Resource, Default)]
+pub struct CharacterSelect {
+    index: usize,
+}
+
+#[derive(Component)]
+pub struct CharacterSelectUi;
+
+#[derive(Component)]
+pub struct CharacterCard {
+    index: usize,
+}
+
+#[derive(Component)]
+pub struct CharacterPreview;
+
+#[derive(Component)]
+pub struct CharacterStatsText;
+
+/// 标题界面的键盘和手柄输入，鼠标通过 [`Interaction`] 处理
+#[derive(SystemParam)]
+pub struct MenuInput<'w> {
+    keyboard: Res<'w, Input<KeyCode>>,
+    gamepads: Res<'w, Gamepads>,
+    buttons: Res<'w, Input<GamepadButton>>,
+}
+
+impl MenuInput<'_> {
+    fn just_pressed(&self, keys: &[KeyCode], button: GamepadButtonType) -> bool {
+        self.keyboard.any_just_pressed(keys.iter().copied())
+            || self.gamepads.iter().any(|gamepad| {
+                self.buttons
+                    .just_pressed(GamepadButton::new(gamepad, button))
+            })
+    }
+    fn confirm(&self) -> bool {
+        self.just_pressed(&[KeyCode::Space, KeyCode::Return], GamepadButtonType::South)
+    }
+    fn back(&self) -> bool {
+        self.just_pressed(&[KeyCode::Escape], GamepadButtonType::East)
+    }
+    fn left(&self) -> bool {
+        self.just_pressed(&[KeyCode::A, KeyCode::Left], GamepadButtonType::DPadLeft)
+    }
+    fn right(&self) -> bool {
+        self.just_pressed(&[KeyCode::D, KeyCode::Right], GamepadButtonType::DPadRight)
+    }
+}
+
+/// 在标题界面按下开始后打开角色选择界面，界面是 [`TitleUi`] 的子节点，离开标题界面时一起销毁
+///
+/// 第一次打开时才裁剪角色的预览动画
+#[allow(clippy::too_many_arguments)]
+pub fn open(
+    mut c: Commands,
+    input: MenuInput,
+    mut cache: ResMut<Cache>,
+    mut images: ResMut<Assets<Image>>,
+    registry: Res<CharacterRegistry>,
+    asset_server: Res<AssetServer>,
+    start_query: Query<&Interaction, (Changed<Interaction>, With<TitleStart>)>,
+    title_query: Query<Entity, With<TitleUi>>,
+) {
+    let clicked = start_query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+    if !clicked && !input.confirm() {
+        return;
+    }
+    let Some(first) = registry.first() else {
+        error!("there is no playable character");
+        return;
+    };
+    res::insert_character_previews(&mut cache, &mut images, &registry);
+    let frames = cache.get_character_preview(&first.sprite.name).clone();
+    let font = asset_server.load("fonts/ThaleahFat.ttf");
+    let text_style = |font_size: f32| TextStyle {
+        font: font.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+    let Ok(title) = title_query.get_single() else {
+        return;
+    };
+    c.entity(title).with_children(|p| {
+        p.spawn((
+            Name::new("Character Select"),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    gap: Size::all(Val::Px(20.0)),
+                    ..default()
+                },
+                background_color: Color::rgba_u8(3, 12, 14, 230).into(),
+                ..default()
+            },
+            CharacterSelectUi,
+        ))
+        .with_children(|p| {
+            p.spawn(TextBundle::from_section(
+                "Select Character",
+                text_style(40.0),
+            ));
+            p.spawn(NodeBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    gap: Size::all(Val::Px(40.0)),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|p| {
+                p.spawn((
+                    ImageBundle {
+                        style: Style {
+                            size: Size::all(Val::Px(PREVIEW_SIZE)),
+                            ..default()
+                        },
+                        image: UiImage {
+                            texture: frames[0].clone(),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    UiImageAnimation::from_loop(0.1, frames.clone()),
+                    CharacterPreview,
+                ));
+                p.spawn((
+                    TextBundle::from_section("", text_style(25.0)),
+                    CharacterStatsText,
+                ));
+            });
+            p.spawn(NodeBundle {
+                style: Style {
+                    gap: Size::all(Val::Px(10.0)),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|p| {
+                for (index, character) in registry.iter().enumerate() {
+                    p.spawn((
+                        ButtonBundle {
+                            style: Style {
+                                padding: UiRect::all(Val::Px(10.0)),
+                                ..default()
+                            },
+                            background_color: CARD_COLOR.into(),
+                            ..default()
+                        },
+                        CharacterCard { index },
+                    ))
+                    .with_children(|p| {
+                        p.spawn(TextBundle::from_section(
+                            character.name.clone(),
+                            text_style(25.0),
+                        ));
+                    });
+                }
+            });
+            p.spawn(TextBundle::from_section(
+                "A/D to choose, Space to start, Esc to go back",
+                text_style(20.0),
+            ));
+        });
+    });
+    c.insert_resource(CharacterSelect::default());
+}
+
+/// 键盘、手柄切换角色，鼠标悬停时也会切换，确认后带着选择的角色进入 loading
+pub fn navigate(
+    mut c: Commands,
+    input: MenuInput,
+    mut select: ResMut<CharacterSelect>,
+    registry: Res<CharacterRegistry>,
+    mut next_state: ResMut<NextState<AppState>>,
+    card_query: Query<(&Interaction, &CharacterCard), Changed<Interaction>>,
+    ui_query: Query<Entity, With<CharacterSelectUi>>,
+) {
+    if input.back() {
+        for entity in ui_query.iter() {
+            c.entity(entity).despawn_recursive();
+        }
+        c.remove_resource::<CharacterSelect>();
+        return;
+    }
+    let count = registry.len();
+    if input.left() {
+        select.index = (select.index + count - 1) % count;
+    }
+    if input.right() {
+        select.index = (select.index + 1) % count;
+    }
+    let mut confirm = input.confirm();
+    for (interaction, card) in card_query.iter() {
+        match interaction {
+            Interaction::Hovered => select.index = card.index,
+            Interaction::Clicked => {
+                select.index = card.index;
+                confirm = true;
+            }
+            Interaction::None => {}
+        }
+    }
+    if confirm {
+        if let Some(character) = registry.iter().nth(select.index) {
+            c.insert_resource(SelectedCharacter(character.id.clone()));
+            next_state.set(AppState::Loading);
+        }
+    }
+}
+
+/// 切换角色后更新卡片的高亮、预览动画和属性
+pub fn update(
+    cache: Res<Cache>,
+    select: Res<CharacterSelect>,
+    registry: Res<CharacterRegistry>,
+    mut card_query: Query<(&mut BackgroundColor, &CharacterCard)>,
+    mut preview_query: Query<(&mut UiImage, &mut UiImageAnimation), With<CharacterPreview>>,
+    mut text_query: Query<&mut Text, With<CharacterStatsText>>,
+) {
+    if !select.is_changed() {
+        return;
+    }
+    let Some(character) = registry.iter().nth(select.index) else {
+        return;
+    };
+    for (mut color, card) in card_query.iter_mut() {
+        *color = if card.index == select.index {
+            CARD_SELECTED_COLOR
+        } else {
+            CARD_COLOR
+        }
+        .into();
+    }
+    let frames = cache.get_character_preview(&character.sprite.name).clone();
+    for (mut image, mut anima) in preview_query.iter_mut() {
+        image.texture = frames[0].clone();
+        *anima = UiImageAnimation::from_loop(0.1, frames.clone());
+    }
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = stats_text(character);
+    }
+}
+
+fn stats_text(character: &CharacterDefinition) -> String {
+    let guns = character
+        .guns
+        .iter()
+        .map(|gun| gun.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "{}\nHP {}\nSpeed {}\nBlanks {}  Keys {}  Money {}\nGuns {}",
+        character.name,
+        character.stats.hp,
+        character.stats.move_speed,
+        character.items.blanks,
+        character.items.keys,
+        character.items.money,
+        guns
+    )
+}
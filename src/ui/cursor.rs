@@ -1,6 +1,7 @@
 use bevy::prelude::*;
 
 use crate::cursor::ResCursor;
+use crate::res::LevelEntity;
 
 #[derive(Component)]
 pub struct CursorUi {
@@ -46,7 +47,8 @@ pub fn setup(mut c: Commands, cursor: Res<ResCursor>, asset_server: Res<AssetSer
         },
         ..default()
     })
-    .insert(cursor_ui);
+    .insert(cursor_ui)
+    .insert(LevelEntity);
 }
 
 pub fn update(mut query: Query<(&mut Style, &CursorUi)>, cursor: Res<ResCursor>) {
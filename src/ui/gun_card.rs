@@ -1,11 +1,12 @@
 use bevy::prelude::*;
 
-use crate::res::Cache;
+use crate::res::{Cache, LevelEntity, ResActor};
 
 #[derive(Component)]
 pub struct GunCardUi;
 
-pub fn setup(mut c: Commands, cache: Res<Cache>) {
+pub fn setup(mut c: Commands, cache: Res<Cache>, actor: Res<ResActor>) {
+    let gun_name = actor.get_cur_gun().map(|gun| gun.name.as_str());
     c.spawn((
         Name::new("Gun Card"),
         ImageBundle {
@@ -24,6 +25,7 @@ pub fn setup(mut c: Commands, cache: Res<Cache>) {
             ..default()
         },
         GunCardUi,
+        LevelEntity,
     ))
     .with_children(|p| {
         p.spawn((
@@ -34,7 +36,7 @@ pub fn setup(mut c: Commands, cache: Res<Cache>) {
                     ..default()
                 },
                 image: UiImage {
-                    texture: cache.get_gun_image("Budget Revolver", 1).clone(),
+                    texture: cache.get_gun_image(gun_name.unwrap_or_default(), 1).clone(),
                     ..default()
                 },
                 ..default()
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::res::Cache;
+use crate::res::{Cache, LevelEntity};
 
 #[derive(Component)]
 pub struct GunCardUi;
@@ -24,5 +24,6 @@ pub fn setup(mut c: Commands, cache: Res<Cache>) {
             ..default()
         },
         GunCardUi,
+        LevelEntity,
     ));
 }
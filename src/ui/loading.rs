@@ -0,0 +1,168 @@
+use bevy::prelude::*;
+use bevy_task_queue::TaskQueue;
+
+use crate::res::{Cache, LoadingTask};
+use crate::ui_image_animation::UiImageAnimation;
+
+/// 进入游戏后 loading 界面淡出的时间
+const FADE_SECONDS: f32 = 0.5;
+
+#[derive(Component)]
+pub struct LoadingUi;
+
+/// 进度条中表示已完成部分的节点
+#[derive(Component)]
+pub struct LoadingProgressBar;
+
+#[derive(Component)]
+pub struct LoadingTaskLabel;
+
+/// 正在淡出的 loading 界面，淡出结束后被删除
+#[derive(Component)]
+pub struct LoadingFade {
+    timer: Timer,
+}
+
+pub fn setup(mut c: Commands, cache: Res<Cache>, asset_server: Res<AssetServer>) {
+    let font_handle = asset_server.load("fonts/ThaleahFat.ttf");
+    c.spawn((
+        Name::new("UI Loading"),
+        NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                size: Size {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                },
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            // 和 ClearColor 一样的背景，挡住正在生成的场景
+            background_color: Color::rgba_u8(3, 12, 14, 255).into(),
+            z_index: ZIndex::Global(100),
+            ..default()
+        },
+        LoadingUi,
+    ))
+    .with_children(|p| {
+        p.spawn((
+            ImageBundle::default(),
+            UiImageAnimation::from_loop(0.2, cache.ui_title_dragon.clone()),
+        ));
+        // 进度条的边框和底色
+        p.spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(300.0), Val::Px(12.0)),
+                margin: UiRect::top(Val::Px(20.0)),
+                padding: UiRect::all(Val::Px(2.0)),
+                ..default()
+            },
+            background_color: Color::rgb_u8(60, 60, 60).into(),
+            ..default()
+        })
+        .with_children(|p| {
+            p.spawn((
+                NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                        ..default()
+                    },
+                    background_color: Color::WHITE.into(),
+                    ..default()
+                },
+                LoadingProgressBar,
+            ));
+        });
+        p.spawn((
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: font_handle,
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                margin: UiRect::top(Val::Px(10.0)),
+                ..default()
+            }),
+            LoadingTaskLabel,
+        ));
+    });
+}
+
+/// 有任务失败时没办法进入游戏，只能回到标题界面
+pub fn failed(task_queue: Res<TaskQueue<LoadingTask>>) -> bool {
+    task_queue.failures().next().is_some()
+}
+
+/// 根据任务队列更新进度条和当前任务的名称，有任务失败时显示失败的原因
+pub fn update(
+    task_queue: Res<TaskQueue<LoadingTask>>,
+    mut bar_query: Query<&mut Style, With<LoadingProgressBar>>,
+    mut label_query: Query<&mut Text, With<LoadingTaskLabel>>,
+) {
+    if !task_queue.is_changed() {
+        return;
+    }
+    for mut style in bar_query.iter_mut() {
+        style.size.width = Val::Percent(task_queue.progress() * 100.0);
+    }
+    let (label, color) = if let Some((entry, error)) = task_queue.failures().next() {
+        (
+            format!("{} failed: {error}\nPress Esc to go back", entry.task),
+            Color::RED,
+        )
+    } else if let Some(entry) = task_queue.iter().find(|entry| !entry.is_finished()) {
+        (entry.task.to_string(), Color::WHITE)
+    } else {
+        (String::from("Ready"), Color::WHITE)
+    };
+    for mut text in label_query.iter_mut() {
+        text.sections[0].value = label.clone();
+        text.sections[0].style.color = color;
+    }
+}
+
+/// 离开 loading 时不直接删除界面，而是淡出显示已经生成的场景
+pub fn dismount(mut c: Commands, query: Query<Entity, With<LoadingUi>>) {
+    for entity in query.iter() {
+        c.entity(entity).insert(LoadingFade {
+            timer: Timer::from_seconds(FADE_SECONDS, TimerMode::Once),
+        });
+    }
+}
+
+pub fn fade_out(
+    mut c: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut LoadingFade)>,
+    children_query: Query<&Children>,
+    mut color_query: Query<(Option<&mut BackgroundColor>, Option<&mut Text>)>,
+) {
+    for (entity, mut fade) in query.iter_mut() {
+        fade.timer.tick(time.delta());
+        if fade.timer.finished() {
+            c.entity(entity).despawn_recursive();
+            continue;
+        }
+        let alpha = fade.timer.percent_left();
+        for node in std::iter::once(entity).chain(children_query.iter_descendants(entity)) {
+            let Ok((background, text)) = color_query.get_mut(node) else {
+                continue;
+            };
+            // 本来就是透明的节点保持不变
+            if let Some(mut background) = background {
+                let a = background.0.a().min(alpha);
+                background.0.set_a(a);
+            }
+            if let Some(mut text) = text {
+                for section in text.sections.iter_mut() {
+                    let a = section.style.color.a().min(alpha);
+                    section.style.color.set_a(a);
+                }
+            }
+        }
+    }
+}
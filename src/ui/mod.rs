@@ -1,6 +1,8 @@
 pub mod ammo;
+pub mod character_select;
 pub mod cursor;
 pub mod gun_card;
 pub mod item_card;
+pub mod loading;
 pub mod status;
 pub mod title;
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::res::{Cache, ResActor};
+use crate::res::{Cache, LevelEntity, ResActor};
 use crate::utils::{u16_to_chars, u8_to_chars};
 
 #[derive(Component)]
@@ -64,7 +64,8 @@ pub fn setup(mut c: Commands, actor: Res<ResActor>, cache: Res<Cache>) {
             .insert(HpUi {
                 first: i * 2 + 1,
                 second: i * 2 + 2,
-            });
+            })
+            .insert(LevelEntity);
             offset_x += size[0] + space_x;
         }
         offset_y += size[1] + 2.0;
@@ -92,7 +93,8 @@ pub fn setup(mut c: Commands, actor: Res<ResActor>, cache: Res<Cache>) {
                 },
                 ..default()
             })
-            .insert(BlankUi { index: i + 1 });
+            .insert(BlankUi { index: i + 1 })
+            .insert(LevelEntity);
             offset_x += size[0] + 2.0;
         }
         offset_y += size[1] + 2.0;
@@ -123,7 +125,8 @@ pub fn setup(mut c: Commands, actor: Res<ResActor>, cache: Res<Cache>) {
                 },
                 ..default()
             })
-            .insert(KeyUi);
+            .insert(KeyUi)
+            .insert(LevelEntity);
             offset_x += size[0];
             for v in u8_to_chars(status.get_keys()) {
                 c.spawn(ImageBundle {
@@ -144,7 +147,8 @@ pub fn setup(mut c: Commands, actor: Res<ResActor>, cache: Res<Cache>) {
                     },
                     ..default()
                 })
-                .insert(KeyCountUi);
+                .insert(KeyCountUi)
+                .insert(LevelEntity);
                 offset_x += font_size[0];
             }
         }
@@ -170,7 +174,8 @@ pub fn setup(mut c: Commands, actor: Res<ResActor>, cache: Res<Cache>) {
                 },
                 ..default()
             })
-            .insert(MoneyUi);
+            .insert(MoneyUi)
+            .insert(LevelEntity);
             offset_x += size[0];
             for v in u16_to_chars(status.get_money()) {
                 c.spawn(ImageBundle {
@@ -191,7 +196,8 @@ pub fn setup(mut c: Commands, actor: Res<ResActor>, cache: Res<Cache>) {
                     },
                     ..default()
                 })
-                .insert(MoneyCountUi);
+                .insert(MoneyCountUi)
+                .insert(LevelEntity);
                 offset_x += font_size[0];
             }
         }
@@ -1,12 +1,16 @@
 use bevy::prelude::*;
 
 use crate::res::Cache;
+use crate::ui::character_select::CharacterSelect;
 use crate::ui_image_animation::UiImageAnimation;
-use crate::AppState;
 
 #[derive(Component)]
 pub struct TitleUi;
 
+/// 可以点击的 Start，点击后打开角色选择界面
+#[derive(Component)]
+pub struct TitleStart;
+
 pub fn setup(mut c: Commands, cache: Res<Cache>, asset_server: Res<AssetServer>) {
     c.spawn(NodeBundle {
         style: Style {
@@ -84,7 +88,7 @@ pub fn setup(mut c: Commands, cache: Res<Cache>, asset_server: Res<AssetServer>)
                 ..default()
             }),
         );
-        p.spawn(
+        p.spawn((
             TextBundle::from_section(
                 "Start",
                 TextStyle {
@@ -98,18 +102,15 @@ pub fn setup(mut c: Commands, cache: Res<Cache>, asset_server: Res<AssetServer>)
                 position: UiRect::new(Val::Px(50.0), Val::Auto, Val::Auto, Val::Px(100.0)),
                 ..default()
             }),
-        );
+            Interaction::default(),
+            TitleStart,
+        ));
     });
 }
 
-pub fn detect_start(mut next_state: ResMut<NextState<AppState>>, keyboard: Res<Input<KeyCode>>) {
-    if keyboard.just_pressed(KeyCode::Space) {
-        next_state.set(AppState::Loading);
-    }
-}
-
 pub fn dismount(mut c: Commands, query: Query<Entity, With<TitleUi>>) {
     for entity in query.iter() {
         c.entity(entity).despawn_recursive();
     }
+    c.remove_resource::<CharacterSelect>();
 }
@@ -1,10 +1,9 @@
-use std::collections::HashMap;
 use std::f32::consts::{PI, SQRT_2};
 
 use bevy::prelude::*;
 use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
 
-use crate::res::{GRID_SIZE, SCALE_RATIO};
+use crate::res::{AssetError, GRID_SIZE, SCALE_RATIO};
 use crate::CAMERA_FAR;
 
 /// wall tile
@@ -12,7 +11,6 @@ pub fn tile_wall_sprite(
     mesh: &Handle<Mesh>,
     material: &Handle<StandardMaterial>,
     relative_pos: [i32; 2],
-    _: u32,
 ) -> PbrBundle {
     let x = relative_pos[0] as f32 * GRID_SIZE * SCALE_RATIO;
     let z = -relative_pos[1] as f32 * GRID_SIZE * SCALE_RATIO * SQRT_2;
@@ -139,122 +137,141 @@ impl MoveDirection {
     }
 }
 
-/// 读取并按照行列切割图片
-pub fn split_images<P>(path: P, tile_size: Vec2, columns: usize, rows: usize) -> HashMap<u8, Image>
+/// 创建只显示贴图中 `uv_rect` 范围的 quad，uv 的取值范围是 0～1
+///
+/// 顶点的顺序和 [`shape::Quad`] 保持一致
+pub fn uv_quad(size: Vec2, uv_rect: Rect, flip: bool) -> Mesh {
+    let mut mesh = Mesh::from(shape::Quad { size, flip });
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, quad_uvs(uv_rect, flip));
+    mesh
+}
+
+/// [`uv_quad`] 四个顶点的 uv，修改已有 mesh 的 uv 时也使用这个顺序
+pub fn quad_uvs(uv_rect: Rect, flip: bool) -> Vec<[f32; 2]> {
+    let (u_left, u_right) = if flip {
+        (uv_rect.max.x, uv_rect.min.x)
+    } else {
+        (uv_rect.min.x, uv_rect.max.x)
+    };
+    vec![
+        [u_left, uv_rect.max.y],
+        [u_left, uv_rect.min.y],
+        [u_right, uv_rect.min.y],
+        [u_right, uv_rect.max.y],
+    ]
+}
+
+/// 读取整张图片，统一转换成rgba8
+pub fn open_image<P>(path: P) -> Result<Image, AssetError>
 where
     P: AsRef<std::path::Path>,
 {
-    let mut texture_atlas = HashMap::new();
-    let mut dynamic_image = image::open(path).unwrap();
-    let buffer = dynamic_image.as_mut_rgba8().unwrap();
-    for y in 0..rows {
-        for x in 0..columns {
-            let cell = Vec2::new(x as f32, y as f32);
-            let rect_min = tile_size * cell;
-            let rect = Rect {
-                min: rect_min,
-                max: rect_min + tile_size,
-            };
-            let sub_buffer = image::imageops::crop(
-                buffer,
-                rect.min.x as u32,
-                rect.min.y as u32,
-                tile_size.x as u32,
-                tile_size.y as u32,
-            )
-            .to_image();
-            let sub_image = Image::new(
-                Extent3d {
-                    width: sub_buffer.width(),
-                    height: sub_buffer.height(),
-                    depth_or_array_layers: 1,
-                },
-                TextureDimension::D2,
-                sub_buffer.into_raw(),
-                TextureFormat::Rgba8UnormSrgb,
-            );
-            texture_atlas.insert((y * columns + x) as u8, sub_image);
-        }
+    let path = path.as_ref();
+    let buffer = image::open(path)
+        .map_err(|error| AssetError::Image {
+            path: path.display().to_string(),
+            error,
+        })?
+        .into_rgba8();
+    Ok(Image::new(
+        Extent3d {
+            width: buffer.width(),
+            height: buffer.height(),
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        buffer.into_raw(),
+        TextureFormat::Rgba8UnormSrgb,
+    ))
+}
+
+/// 从 [`open_image`] 读取的图片中复制出 `rect` 范围的部分，`rect` 是像素坐标
+pub fn crop_image(image: &Image, rect: Rect) -> Image {
+    let width = image.texture_descriptor.size.width as usize;
+    let (x, y) = (rect.min.x as usize, rect.min.y as usize);
+    let (w, h) = (rect.width() as usize, rect.height() as usize);
+    let mut data = Vec::with_capacity(w * h * 4);
+    for row in y..y + h {
+        let start = (row * width + x) * 4;
+        data.extend_from_slice(&image.data[start..start + w * 4]);
     }
-    texture_atlas
+    Image::new(
+        Extent3d {
+            width: w as u32,
+            height: h as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
 }
 
-/// 读取并按照行列切割图片
-pub fn split_images_to_vec<P>(path: P, tile_size: Vec2, columns: usize, rows: usize) -> Vec<Image>
-where
-    P: AsRef<std::path::Path>,
-{
-    let mut texture_atlas = vec![];
-    let mut dynamic_image = image::open(path).unwrap();
-    let buffer = dynamic_image.as_mut_rgba8().unwrap();
+/// 按照行列切割时每一帧的像素范围，第 `y * columns + x` 个是第y行第x列
+pub fn grid_rects(tile_size: Vec2, columns: usize, rows: usize) -> Vec<Rect> {
+    let mut rects = vec![];
     for y in 0..rows {
         for x in 0..columns {
-            let cell = Vec2::new(x as f32, y as f32);
-            let rect_min = tile_size * cell;
-            let rect = Rect {
+            let rect_min = tile_size * Vec2::new(x as f32, y as f32);
+            rects.push(Rect {
                 min: rect_min,
                 max: rect_min + tile_size,
-            };
-            let sub_buffer = image::imageops::crop(
-                buffer,
-                rect.min.x as u32,
-                rect.min.y as u32,
-                tile_size.x as u32,
-                tile_size.y as u32,
-            )
-            .to_image();
-            let sub_image = Image::new(
-                Extent3d {
-                    width: sub_buffer.width(),
-                    height: sub_buffer.height(),
-                    depth_or_array_layers: 1,
-                },
-                TextureDimension::D2,
-                sub_buffer.into_raw(),
-                TextureFormat::Rgba8UnormSrgb,
-            );
-            texture_atlas.push(sub_image);
+            });
         }
     }
-    texture_atlas
+    rects
 }
 
-pub fn load_actor_sprite<P>(config_path: P, image_path: P) -> HashMap<String, Vec<Image>>
+/// aseprite 导出的 json 中每一帧的 filename（也就是 tag）和像素范围
+pub fn load_aseprite_frames<P>(config_path: P) -> Result<Vec<(String, Rect)>, AssetError>
 where
     P: AsRef<std::path::Path>,
 {
-    let mut result: HashMap<String, Vec<Image>> = HashMap::new();
+    let path = config_path.as_ref();
+    let config = serde_aseprite::AsepriteDate::from(path).map_err(|error| AssetError::Parse {
+        path: path.display().to_string(),
+        error: error.to_string(),
+    })?;
+    Ok(config
+        .frames
+        .into_iter()
+        .map(|item| {
+            let min = Vec2::new(item.frame.x as f32, item.frame.y as f32);
+            let size = Vec2::new(item.frame.w as f32, item.frame.h as f32);
+            (
+                item.filename,
+                Rect {
+                    min,
+                    max: min + size,
+                },
+            )
+        })
+        .collect())
+}
 
-    let config = serde_aseprite::AsepriteDate::from(config_path).unwrap();
-    let mut dynamic_image = image::open(image_path).unwrap();
-    let buffer = dynamic_image.as_mut_rgba8().unwrap();
-    for item in config.frames {
-        let tag = item.filename;
-        let sub_buffer = image::imageops::crop(
-            buffer,
-            item.frame.x,
-            item.frame.y,
-            item.frame.w,
-            item.frame.h,
-        )
-        .to_image();
-        let sub_image = Image::new(
-            Extent3d {
-                width: sub_buffer.width(),
-                height: sub_buffer.height(),
-                depth_or_array_layers: 1,
-            },
-            TextureDimension::D2,
-            sub_buffer.into_raw(),
-            TextureFormat::Rgba8UnormSrgb,
-        );
-        if let Some(frames) = result.get_mut(&tag) {
-            frames.push(sub_image);
-        } else {
-            result.insert(tag, vec![sub_image]);
+/// 资源缺失时显示的占位贴图，品红和黑色的棋盘格，一眼就能看出来哪里有问题
+pub fn placeholder_image() -> Image {
+    const SIZE: u32 = 16;
+    let mut data = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            if (x / 4 + y / 4) % 2 == 0 {
+                data.extend_from_slice(&[255, 0, 255, 255]);
+            } else {
+                data.extend_from_slice(&[0, 0, 0, 255]);
+            }
         }
     }
-    result
+    Image::new(
+        Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
 }
 
 pub fn u8_to_chars(value: u8) -> Vec<char> {